@@ -0,0 +1,168 @@
+use crate::error::{AskError, Result};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Default per-attachment limit for `max_attachment_bytes`
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: usize = 100_000;
+
+/// A file or piped input sent to the AI along with the question
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// Shown to the model, e.g. the file path or "stdin"
+    pub name: String,
+    pub content: String,
+    /// Original size in bytes if `content` was cut to the limit
+    pub truncated_from: Option<usize>,
+}
+
+impl Attachment {
+    /// Read a file, keeping at most `limit` bytes
+    pub fn from_file(path: &Path, limit: usize) -> Result<Self> {
+        let bytes = fs::read(path)
+            .map_err(|e| AskError::Generic(format!("Cannot attach {}: {}", path.display(), e)))?;
+        Self::from_bytes(&path.display().to_string(), bytes, limit)
+    }
+
+    /// Read all of `reader` (usually stdin), keeping at most `limit` bytes
+    pub fn from_reader(name: &str, mut reader: impl Read, limit: usize) -> Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_bytes(name, bytes, limit)
+    }
+
+    fn from_bytes(name: &str, bytes: Vec<u8>, limit: usize) -> Result<Self> {
+        if bytes.contains(&0) {
+            return Err(AskError::Generic(format!(
+                "Cannot attach {}: it looks like a binary file",
+                name
+            )));
+        }
+
+        let original_len = bytes.len();
+        let mut content = String::from_utf8_lossy(&bytes).into_owned();
+        let truncated_from = if content.len() > limit {
+            // Cut on a character boundary at or below the limit
+            let mut end = limit;
+            while !content.is_char_boundary(end) {
+                end -= 1;
+            }
+            content.truncate(end);
+            Some(original_len)
+        } else {
+            None
+        };
+
+        Ok(Attachment {
+            name: name.to_string(),
+            content,
+            truncated_from,
+        })
+    }
+
+    /// Tell the user that part of the attachment will not be sent
+    pub fn warn_if_truncated(&self) {
+        if let Some(original) = self.truncated_from {
+            eprintln!(
+                "Note: {} is {} bytes; only the first {} bytes are sent (see max_attachment_bytes)",
+                self.name,
+                original,
+                self.content.len()
+            );
+        }
+    }
+}
+
+/// Whether stdin is a pipe or a file, as in `make 2>&1 | ask why` or
+/// `ask why < build.log`. Terminals, `/dev/null` and sockets are not read:
+/// cron, editors and CI runners may leave them open without ever sending
+/// EOF.
+pub fn stdin_is_piped() -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsFd;
+        use std::os::unix::fs::FileTypeExt;
+
+        io::stdin()
+            .as_fd()
+            .try_clone_to_owned()
+            .and_then(|fd| fs::File::from(fd).metadata())
+            .is_ok_and(|meta| meta.file_type().is_fifo() || meta.is_file())
+    }
+    #[cfg(not(unix))]
+    {
+        use std::io::IsTerminal;
+        !io::stdin().is_terminal()
+    }
+}
+
+/// Build the user message: the question followed by each attachment in a
+/// delimited block the model can refer to by name
+pub fn embed(query: &str, attachments: &[Attachment]) -> String {
+    let mut message = query.to_string();
+    for attachment in attachments {
+        message.push_str(&format!("\n\n[{}]\n", attachment.name));
+        message.push_str(attachment.content.trim_end_matches('\n'));
+        match attachment.truncated_from {
+            Some(original) => message.push_str(&format!(
+                "\n[end of {}, truncated: showing the first {} of {} bytes]",
+                attachment.name,
+                attachment.content.len(),
+                original
+            )),
+            None => message.push_str(&format!("\n[end of {}]", attachment.name)),
+        }
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_embed_without_attachments() {
+        assert_eq!(embed("why?", &[]), "why?");
+    }
+
+    #[test]
+    fn test_embed_attachments() {
+        let log = Attachment::from_reader("stdin", "line 1\nline 2\n".as_bytes(), 100).unwrap();
+        let message = embed("why is this failing", &[log]);
+        assert_eq!(
+            message,
+            "why is this failing\n\n[stdin]\nline 1\nline 2\n[end of stdin]"
+        );
+    }
+
+    #[test]
+    fn test_truncation() {
+        let attachment = Attachment::from_reader("big", "a".repeat(50).as_bytes(), 10).unwrap();
+        assert_eq!(attachment.content.len(), 10);
+        assert_eq!(attachment.truncated_from, Some(50));
+        assert!(embed("q", &[attachment])
+            .ends_with("[end of big, truncated: showing the first 10 of 50 bytes]"));
+
+        // Never split a multi-byte character
+        let attachment = Attachment::from_reader("utf8", "ééé".as_bytes(), 3).unwrap();
+        assert_eq!(attachment.content, "é");
+    }
+
+    #[test]
+    fn test_from_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "hello").unwrap();
+        let attachment = Attachment::from_file(&path, 100).unwrap();
+        assert_eq!(attachment.name, path.display().to_string());
+        assert_eq!(attachment.content, "hello");
+        assert_eq!(attachment.truncated_from, None);
+
+        assert!(Attachment::from_file(&dir.path().join("missing"), 100).is_err());
+
+        let binary = dir.path().join("blob");
+        fs::write(&binary, [0x7f, b'E', b'L', b'F', 0, 1]).unwrap();
+        assert!(Attachment::from_file(&binary, 100).is_err());
+    }
+}
@@ -1,13 +1,24 @@
+use crate::attachment::DEFAULT_MAX_ATTACHMENT_BYTES;
+use crate::context::ContextSettings;
+use crate::credentials::{Credentials, KeyOwner, KeySource};
 use crate::error::{AskError, Result};
-use crate::providers::{ProviderConfig, ProviderType};
+use crate::providers::retry::RetryPolicy;
+use crate::providers::{ollama, ProviderConfig, ProviderType};
+use crate::usage::Price;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    #[serde(default)]
+    /// Key for the provider in use when given directly, e.g. in its
+    /// environment variable; otherwise [`Config::api_key`] reads it from its
+    /// source. In the config file this is only read: older versions kept the
+    /// key here, and [`Config::save`] moves it to the credentials file.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub api_key: String,
     #[serde(default = "default_mode")]
     pub default_mode: String,
@@ -19,12 +30,102 @@ pub struct Config {
     pub color: bool,
     #[serde(default = "default_max_tokens")]
     pub max_tokens: u32,
+    /// Largest file or stdin attachment sent to the AI, in bytes
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: usize,
+    /// How many times to retry rate limits, server and connection errors
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry in milliseconds, doubled for each retry
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
     #[serde(default)]
     pub provider: ProviderType,
     #[serde(default)]
     pub api_url: Option<String>,
+    /// Providers to try in order when the main one fails before answering
+    #[serde(default)]
+    pub fallback_providers: Vec<ProviderType>,
     #[serde(default)]
     pub custom_provider_confirmed: bool,
+    /// Profile used when neither `--profile` nor `ASK_PROFILE` is given
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Prices per model overriding the built-in estimates
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prices: BTreeMap<String, Price>,
+    /// Replaces the built-in system prompt for questions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Which facts about the environment the system prompt mentions
+    #[serde(default)]
+    pub context: ContextSettings,
+    /// System prompt given with `--system` for this run
+    #[serde(skip)]
+    pub system_prompt_override: Option<String>,
+    /// Name of the profile applied by [`Config::load`], if any
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+    /// Where each provider's key is kept, filled in by [`Config::load`]
+    #[serde(skip)]
+    pub api_keys: BTreeMap<ProviderType, KeySource>,
+    /// Where the active profile's key is kept, if it has one
+    #[serde(skip)]
+    pub profile_key: Option<KeySource>,
+}
+
+/// Named provider settings, selected with `--profile`, `ASK_PROFILE` or
+/// `default_profile`. Unset fields fall back to the top-level settings. The
+/// profile's API key is kept in the credentials file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub custom_provider_confirmed: bool,
+}
+
+impl Profile {
+    pub fn valid_keys() -> &'static [&'static str] {
+        &["provider", "model", "api_url"]
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = (!value.is_empty()).then(|| value.to_string());
+        match key {
+            "provider" => {
+                self.provider = value
+                    .map(|v| {
+                        v.parse::<ProviderType>()
+                            .map_err(|_| AskError::UnknownProvider(v.to_string()))
+                    })
+                    .transpose()?
+            }
+            "model" => self.model = value,
+            "api_url" => self.api_url = value,
+            _ => return Err(AskError::UnknownConfigKey(key.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// Check that `name` can be used as a profile name
+pub fn validate_profile_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(AskError::InvalidProfileName(name.to_string()))
+    }
 }
 
 fn default_mode() -> String {
@@ -39,6 +140,18 @@ fn default_max_tokens() -> u32 {
     1024
 }
 
+fn default_max_attachment_bytes() -> usize {
+    DEFAULT_MAX_ATTACHMENT_BYTES
+}
+
+fn default_max_retries() -> u32 {
+    RetryPolicy::default().max_retries
+}
+
+fn default_retry_delay_ms() -> u64 {
+    RetryPolicy::default().base_delay.as_millis() as u64
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -48,9 +161,22 @@ impl Default for Config {
             confirm_exec: default_true(),
             color: default_true(),
             max_tokens: default_max_tokens(),
+            max_attachment_bytes: default_max_attachment_bytes(),
+            max_retries: default_max_retries(),
+            retry_delay_ms: default_retry_delay_ms(),
             provider: ProviderType::default(),
             api_url: None,
+            fallback_providers: Vec::new(),
             custom_provider_confirmed: false,
+            default_profile: None,
+            profiles: BTreeMap::new(),
+            prices: BTreeMap::new(),
+            system_prompt: None,
+            context: ContextSettings::default(),
+            system_prompt_override: None,
+            active_profile: None,
+            api_keys: BTreeMap::new(),
+            profile_key: None,
         }
     }
 }
@@ -64,8 +190,19 @@ impl Config {
             "confirm_exec",
             "color",
             "max_tokens",
+            "max_attachment_bytes",
+            "max_retries",
+            "retry_delay_ms",
             "provider",
             "api_url",
+            "fallback_providers",
+            "default_profile",
+            "system_prompt",
+            "context.distro",
+            "context.shell",
+            "context.package_manager",
+            "context.coreutils",
+            "context.project",
         ]
     }
 
@@ -78,32 +215,121 @@ impl Config {
 
     /// Get the effective API URL (configured or provider default)
     pub fn effective_api_url(&self) -> String {
-        self.api_url
-            .clone()
-            .unwrap_or_else(|| self.provider.default_api_url().to_string())
+        if let Some(url) = &self.api_url {
+            return url.clone();
+        }
+        if self.provider == ProviderType::Ollama {
+            if let Some(url) = ollama::url_from_env() {
+                return url;
+            }
+        }
+        self.provider.default_api_url().to_string()
     }
 
-    /// Build a ProviderConfig from the current configuration
-    pub fn provider_config(&self) -> ProviderConfig {
+    /// Build a ProviderConfig from the current configuration, sending
+    /// `system_prompt` with requests
+    pub fn provider_config(&self, system_prompt: &str) -> ProviderConfig {
         ProviderConfig {
-            api_key: self.api_key.clone(),
+            api_key: self.api_key(),
             api_url: self.effective_api_url(),
             model: self.effective_model(),
             max_tokens: self.max_tokens,
+            system_prompt: system_prompt.to_string(),
+            retry: RetryPolicy {
+                max_retries: self.max_retries,
+                base_delay: Duration::from_millis(self.retry_delay_ms),
+            },
+        }
+    }
+
+    /// API key for the provider in use: `api_key` if one was given, else the
+    /// key from its source. A command or keyring is only asked once a request
+    /// needs the key, and only once per run.
+    pub fn api_key(&self) -> String {
+        if !self.api_key.is_empty() {
+            return self.api_key.clone();
+        }
+        self.key_source()
+            .and_then(|(owner, source)| source.read_once(&owner))
+            .unwrap_or_default()
+    }
+
+    /// Whether the provider in use has a key, without reading it
+    pub fn has_api_key(&self) -> bool {
+        !self.api_key.is_empty() || self.key_source().is_some()
+    }
+
+    /// Where the key for the provider in use is kept: the active profile's
+    /// source, else the provider's
+    pub fn key_source(&self) -> Option<(KeyOwner, &KeySource)> {
+        match (&self.profile_key, &self.active_profile) {
+            (Some(source), Some(name)) => Some((KeyOwner::Profile(name.clone()), source)),
+            _ => self
+                .api_keys
+                .get(&self.provider)
+                .map(|source| (KeyOwner::Provider(self.provider), source)),
+        }
+    }
+
+    /// Use the key of `provider` after switching to it: its environment
+    /// variable, else its configured source
+    pub fn use_provider_key(&mut self) {
+        self.api_key = env_api_key(self.provider).unwrap_or_default();
+        self.profile_key = None;
+    }
+
+    /// Settings for using `provider` instead of the configured one: its own
+    /// API key and its default model and URL
+    pub fn for_provider(&self, provider: ProviderType) -> Config {
+        Config {
+            provider,
+            api_key: env_api_key(provider).unwrap_or_default(),
+            profile_key: None,
+            model: None,
+            api_url: None,
+            custom_provider_confirmed: false,
+            ..self.clone()
+        }
+    }
+
+    /// Settings for answering with fallback `provider`, which has no
+    /// fallbacks of its own
+    pub fn for_fallback(&self, provider: ProviderType) -> Config {
+        Config {
+            fallback_providers: Vec::new(),
+            ..self.for_provider(provider)
         }
     }
 
     pub fn get(&self, key: &str) -> Option<String> {
         match key {
-            "api_key" => Some(self.api_key.clone()),
+            "api_key" => Some(self.api_key()),
             "default_mode" => Some(self.default_mode.clone()),
             "model" => self.model.clone(),
             "confirm_exec" => Some(self.confirm_exec.to_string()),
             "color" => Some(self.color.to_string()),
             "max_tokens" => Some(self.max_tokens.to_string()),
+            "max_attachment_bytes" => Some(self.max_attachment_bytes.to_string()),
+            "max_retries" => Some(self.max_retries.to_string()),
+            "retry_delay_ms" => Some(self.retry_delay_ms.to_string()),
             "provider" => Some(self.provider.to_string()),
             "api_url" => self.api_url.clone(),
-            _ => None,
+            "default_profile" => self.default_profile.clone(),
+            "system_prompt" => self.system_prompt.clone(),
+            "fallback_providers" => (!self.fallback_providers.is_empty()).then(|| {
+                self.fallback_providers
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }),
+            _ => {
+                if let Some(name) = key.strip_prefix("context.") {
+                    return self.context.get(name).map(|on| on.to_string());
+                }
+                let price = self.prices.get(key.strip_prefix("price.")?)?;
+                Some(format!("{},{}", price.input, price.output))
+            }
         }
     }
 
@@ -127,6 +353,21 @@ impl Config {
                     .parse()
                     .map_err(|_| AskError::Config(format!("Invalid max_tokens value: {}", value)))?
             }
+            "max_attachment_bytes" => {
+                self.max_attachment_bytes = value.parse().map_err(|_| {
+                    AskError::Config(format!("Invalid max_attachment_bytes value: {}", value))
+                })?
+            }
+            "max_retries" => {
+                self.max_retries = value.parse().map_err(|_| {
+                    AskError::Config(format!("Invalid max_retries value: {}", value))
+                })?
+            }
+            "retry_delay_ms" => {
+                self.retry_delay_ms = value.parse().map_err(|_| {
+                    AskError::Config(format!("Invalid retry_delay_ms value: {}", value))
+                })?
+            }
             "provider" => {
                 let provider_type = value
                     .parse::<ProviderType>()
@@ -144,12 +385,88 @@ impl Config {
                     self.api_url = Some(value.to_string());
                 }
             }
-            _ => return Err(AskError::UnknownConfigKey(key.to_string())),
+            "default_profile" => {
+                if value.is_empty() {
+                    self.default_profile = None;
+                } else if self.profiles.contains_key(value) {
+                    self.default_profile = Some(value.to_string());
+                } else {
+                    return Err(AskError::ProfileNotFound(value.to_string()));
+                }
+            }
+            "system_prompt" => {
+                self.system_prompt = (!value.trim().is_empty()).then(|| value.to_string())
+            }
+            "fallback_providers" => {
+                self.fallback_providers = value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        name.parse::<ProviderType>()
+                            .map_err(|_| AskError::UnknownProvider(name.to_string()))
+                    })
+                    .collect::<Result<_>>()?;
+            }
+            _ if key.starts_with("context.") => {
+                let setting = self
+                    .context
+                    .get_mut(&key["context.".len()..])
+                    .ok_or_else(|| AskError::UnknownConfigKey(key.to_string()))?;
+                *setting = matches!(value.to_lowercase().as_str(), "true" | "1" | "yes");
+            }
+            _ => {
+                let Some(model) = key.strip_prefix("price.").filter(|m| !m.is_empty()) else {
+                    return Err(AskError::UnknownConfigKey(key.to_string()));
+                };
+                if value.is_empty() {
+                    self.prices.remove(model);
+                } else {
+                    let price = Price::parse(value).ok_or_else(|| {
+                        AskError::Config(format!(
+                            "Invalid price '{}': expected <input>,<output> in USD per million tokens",
+                            value
+                        ))
+                    })?;
+                    self.prices.insert(model.to_string(), price);
+                }
+            }
         }
         Ok(())
     }
 
-    pub fn load() -> Result<Self> {
+    /// Load the effective configuration: the config file, then the selected
+    /// profile (`profile`, else `ASK_PROFILE`, else `default_profile`), then
+    /// environment overrides
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        let mut config = Config::load_file()?;
+        let credentials = Credentials::load()?;
+
+        config.api_keys = credentials.providers.clone();
+        // A key left in the config file belongs to the provider set next to it
+        if !config.api_key.is_empty() {
+            let key = std::mem::take(&mut config.api_key);
+            config
+                .api_keys
+                .entry(config.provider)
+                .or_insert(KeySource::Stored(key));
+        }
+
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| env::var("ASK_PROFILE").ok().filter(|p| !p.is_empty()))
+            .or_else(|| config.default_profile.clone());
+        if let Some(name) = profile {
+            config.apply_profile(&name, &credentials)?;
+        }
+
+        // Environment overrides, then the stored key if none was given
+        config.apply_env_overrides();
+
+        Ok(config)
+    }
+
+    /// Load only what is stored in the config file, for editing it
+    pub fn load_file() -> Result<Self> {
         let mut config = Config::default();
 
         // Try XDG config path first
@@ -168,40 +485,66 @@ impl Config {
             }
         }
 
-        // Environment overrides
-        config.apply_env_overrides();
-
         Ok(config)
     }
 
-    pub fn save(&self) -> Result<()> {
-        use std::fs::OpenOptions;
-        use std::io::Write;
-        #[cfg(unix)]
-        use std::os::unix::fs::OpenOptionsExt;
-
-        let config_path = get_config_path();
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Use the provider settings of profile `name`
+    fn apply_profile(&mut self, name: &str, credentials: &Credentials) -> Result<()> {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AskError::ProfileNotFound(name.to_string()))?;
+        let changes_endpoint = profile.provider.is_some() || profile.api_url.is_some();
+
+        if let Some(provider) = profile.provider {
+            if provider != self.provider {
+                // Top-level settings belong to another provider
+                self.api_key.clear();
+                self.model = None;
+                self.api_url = None;
+            }
+            self.provider = provider;
         }
-        let content = serde_json::to_string_pretty(self)?;
-
-        #[cfg(unix)]
-        {
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .mode(0o600)
-                .open(&config_path)?;
-            file.write_all(content.as_bytes())?;
+        if profile.model.is_some() {
+            self.model = profile.model;
         }
-
-        #[cfg(not(unix))]
-        {
-            fs::write(&config_path, content)?;
+        if profile.api_url.is_some() {
+            self.api_url = profile.api_url;
+        }
+        if changes_endpoint {
+            self.custom_provider_confirmed = profile.custom_provider_confirmed;
         }
+        self.profile_key = credentials
+            .source(&KeyOwner::Profile(name.to_string()))
+            .cloned();
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
 
+    /// Write the settings from the config file. Values that came from a
+    /// profile or the environment are not part of a file-loaded config.
+    pub fn save(&self) -> Result<()> {
+        let mut file = self.clone();
+        file.migrate_api_key()?;
+        write_private(&get_config_path(), &serde_json::to_string_pretty(&file)?)
+    }
+
+    /// Move a key kept in the config file by older versions to the
+    /// credentials file, under the provider it was configured with. Call
+    /// before changing `provider` on a file-loaded config.
+    pub fn migrate_api_key(&mut self) -> Result<()> {
+        if self.api_key.is_empty() {
+            return Ok(());
+        }
+        let mut credentials = Credentials::load()?;
+        let owner = KeyOwner::Provider(self.provider);
+        if credentials.source(&owner).is_none() {
+            let key = std::mem::take(&mut self.api_key);
+            credentials.set_source(&owner, Some(KeySource::Stored(key)));
+            credentials.save()?;
+        }
+        self.api_key.clear();
         Ok(())
     }
 
@@ -209,7 +552,11 @@ impl Config {
         // Provider override
         if let Ok(provider) = env::var("ASK_PROVIDER") {
             if let Ok(provider_type) = provider.parse::<ProviderType>() {
-                self.provider = provider_type;
+                if provider_type != self.provider {
+                    self.provider = provider_type;
+                    // A profile's key belongs to the profile's provider
+                    self.use_provider_key();
+                }
             }
         }
 
@@ -218,15 +565,13 @@ impl Config {
             self.api_url = Some(url);
         }
 
-        // API key: check provider-specific env var first, then generic
-        let provider_env_var = self.provider.env_var_name();
-        if !provider_env_var.is_empty() {
-            if let Ok(key) = env::var(provider_env_var) {
-                self.api_key = key;
-            }
+        // API key: check provider-specific env var first. A configured key is
+        // only read when a request needs it.
+        if let Some(key) = env_api_key(self.provider) {
+            self.api_key = key;
         }
         // Also check ASK_API_KEY as a fallback for any provider
-        if self.api_key.is_empty() {
+        if !self.has_api_key() {
             if let Ok(key) = env::var("ASK_API_KEY") {
                 self.api_key = key;
             }
@@ -248,6 +593,46 @@ impl Config {
     }
 }
 
+/// Key in the environment variable of `provider`, e.g. `GROQ_API_KEY`
+fn env_api_key(provider: ProviderType) -> Option<String> {
+    match provider.env_var_name() {
+        "" => None,
+        env_var => env::var(env_var).ok().filter(|key| !key.is_empty()),
+    }
+}
+
+/// Write `content` to `path`, readable only by the user on Unix
+pub fn write_private(path: &Path, content: &str) -> Result<()> {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    #[cfg(unix)]
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    #[cfg(unix)]
+    {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // The mode only applies to new files
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(content.as_bytes())?;
+    }
+
+    #[cfg(not(unix))]
+    {
+        fs::write(path, content)?;
+    }
+
+    Ok(())
+}
+
 pub fn get_config_path() -> PathBuf {
     let xdg_config = env::var("XDG_CONFIG_HOME")
         .map(PathBuf::from)
@@ -259,6 +644,18 @@ pub fn get_config_path() -> PathBuf {
     xdg_config.join("ask").join("config.json")
 }
 
+/// Directory for persistent data such as conversation sessions
+pub fn get_data_dir() -> PathBuf {
+    let xdg_data = env::var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::home_dir()
+                .map(|h| h.join(".local").join("share"))
+                .unwrap_or_default()
+        });
+    xdg_data.join("ask")
+}
+
 fn parse_legacy_config(content: &str, mut config: Config) -> Config {
     for line in content.lines() {
         let line = line.trim();
@@ -299,17 +696,167 @@ mod tests {
     }
 
     #[test]
-    fn test_provider_config() {
+    fn test_context_settings() {
+        let mut config = Config::default();
+        assert_eq!(config.get("context.project"), Some("false".to_string()));
+        config.set("context.project", "true").unwrap();
+        assert!(config.context.project);
+        config.set("context.distro", "yes").unwrap();
+        assert!(config.context.distro);
+        config.set("context.distro", "no").unwrap();
+        assert!(!config.context.distro);
+        assert!(config.set("context.hostname", "true").is_err());
+    }
+
+    #[test]
+    fn test_fallback_providers_setting() {
+        let mut config = Config::default();
+        config.set("fallback_providers", "groq, ollama").unwrap();
+        assert_eq!(
+            config.fallback_providers,
+            vec![ProviderType::Groq, ProviderType::Ollama]
+        );
+        assert_eq!(
+            config.get("fallback_providers"),
+            Some("groq,ollama".to_string())
+        );
+        assert!(config.set("fallback_providers", "groq,nope").is_err());
+        config.set("fallback_providers", "").unwrap();
+        assert_eq!(config.get("fallback_providers"), None);
+    }
+
+    #[test]
+    fn test_max_attachment_bytes() {
+        let mut config = Config::default();
+        assert_eq!(config.max_attachment_bytes, DEFAULT_MAX_ATTACHMENT_BYTES);
+        config.set("max_attachment_bytes", "5000").unwrap();
+        assert_eq!(config.get("max_attachment_bytes"), Some("5000".to_string()));
+        assert!(config.set("max_attachment_bytes", "lots").is_err());
+    }
+
+    #[test]
+    fn test_for_fallback() {
+        let config = Config {
+            api_key: "primary-key".to_string(),
+            model: Some("claude-opus".to_string()),
+            api_url: Some("https://proxy.example.com".to_string()),
+            max_tokens: 2048,
+            fallback_providers: vec![ProviderType::Ollama],
+            ..Default::default()
+        };
+        let fallback = config.for_fallback(ProviderType::Ollama);
+        assert_eq!(fallback.provider, ProviderType::Ollama);
+        assert!(fallback.api_key.is_empty());
+        assert_eq!(fallback.effective_model(), "llama3.2");
+        assert_eq!(
+            fallback.effective_api_url(),
+            "http://localhost:11434/api/chat"
+        );
+        assert_eq!(fallback.max_tokens, 2048);
+        assert!(fallback.fallback_providers.is_empty());
+    }
+
+    #[test]
+    fn test_for_provider() {
+        let config = Config {
+            api_key: "primary-key".to_string(),
+            model: Some("claude-opus".to_string()),
+            fallback_providers: vec![ProviderType::Ollama],
+            custom_provider_confirmed: true,
+            ..Default::default()
+        };
+        let groq = config.for_provider(ProviderType::Groq);
+        assert_eq!(groq.provider, ProviderType::Groq);
+        assert!(groq.api_key.is_empty());
+        assert_eq!(groq.model, None);
+        assert!(!groq.custom_provider_confirmed);
+        assert_eq!(groq.fallback_providers, vec![ProviderType::Ollama]);
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let mut config = Config {
+            api_key: "anthropic-key".to_string(),
+            model: Some("claude-opus".to_string()),
+            ..Default::default()
+        };
+        let mut work = Profile::default();
+        work.set("provider", "openai").unwrap();
+        work.set("model", "gpt-4o-mini").unwrap();
+        config.profiles.insert("work".to_string(), work);
+        let mut credentials = Credentials::default();
+        credentials.set_source(
+            &KeyOwner::Profile("work".to_string()),
+            Some(KeySource::Stored("sk-work".to_string())),
+        );
+
+        let mut selected = config.clone();
+        selected.apply_profile("work", &credentials).unwrap();
+        assert_eq!(selected.provider, ProviderType::OpenAI);
+        assert_eq!(selected.effective_model(), "gpt-4o-mini");
+        assert_eq!(selected.api_key(), "sk-work");
+        assert_eq!(selected.active_profile.as_deref(), Some("work"));
+
+        // Without a stored key the other provider's key is not reused
+        let mut selected = config.clone();
+        selected
+            .apply_profile("work", &Credentials::default())
+            .unwrap();
+        assert!(selected.api_key.is_empty());
+
+        assert!(matches!(
+            config.apply_profile("home", &credentials),
+            Err(AskError::ProfileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_profile_names() {
         let mut config = Config::default();
-        config.api_key = "test-key".to_string();
+        assert!(config.set("default_profile", "work").is_err());
+        config
+            .profiles
+            .insert("work".to_string(), Profile::default());
+        config.set("default_profile", "work").unwrap();
+        assert_eq!(config.get("default_profile"), Some("work".to_string()));
+        config.set("default_profile", "").unwrap();
+        assert_eq!(config.default_profile, None);
+
+        assert!(validate_profile_name("work-2.openai_eu").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("my profile").is_err());
+        assert!(validate_profile_name("../etc").is_err());
+    }
+
+    #[test]
+    fn test_api_keys_per_provider() {
+        let mut config = Config::default();
+        config.api_keys.insert(
+            ProviderType::Mistral,
+            KeySource::Stored("mistral-key".to_string()),
+        );
+        assert_eq!(config.api_key(), "");
+        assert!(!config.has_api_key());
+        let mistral = config.for_fallback(ProviderType::Mistral);
+        assert!(mistral.has_api_key());
+        assert_eq!(mistral.api_key(), "mistral-key");
+    }
 
-        let provider_config = config.provider_config();
+    #[test]
+    fn test_provider_config() {
+        let config = Config {
+            api_key: "test-key".to_string(),
+            ..Default::default()
+        };
+
+        let provider_config = config.provider_config("Be brief.");
         assert_eq!(provider_config.api_key, "test-key");
         assert_eq!(
             provider_config.api_url,
             "https://api.anthropic.com/v1/messages"
         );
         assert_eq!(provider_config.model, "claude-sonnet-4-20250514");
+        assert_eq!(provider_config.system_prompt, "Be brief.");
     }
 
     #[test]
@@ -329,9 +876,11 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let config_path = temp_dir.path().join("config.json");
 
-        let mut config = Config::default();
-        config.api_key = "test-key".to_string();
-        config.max_tokens = 512;
+        let config = Config {
+            api_key: "test-key".to_string(),
+            max_tokens: 512,
+            ..Default::default()
+        };
 
         let content = serde_json::to_string_pretty(&config).unwrap();
         fs::write(&config_path, content).unwrap();
@@ -0,0 +1,420 @@
+//! Facts about the user's environment for the system prompt.
+//!
+//! Each fact has a `context.*` setting, and all are off until turned on, so
+//! by default only the OS and architecture are described. What the current
+//! directory holds is only described with `context.project`, and never by
+//! name or path; the path is only sent by templates that use `{cwd}` and by
+//! `ask fix`.
+
+use crate::prompts::current_shell;
+use crate::util::find_in_path;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+/// Which facts about the environment go into the system prompt; all are
+/// off until turned on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextSettings {
+    /// Name and version of the distribution, e.g. "Fedora Linux 40"
+    pub distro: bool,
+    /// The shell in `$SHELL` and its version
+    pub shell: bool,
+    /// Package managers found in `PATH`
+    pub package_manager: bool,
+    /// Whether `ls` and friends are GNU, BSD or BusyBox
+    pub coreutils: bool,
+    /// Whether the current directory is in a git repository, and the kind
+    /// of project in it
+    pub project: bool,
+}
+
+impl ContextSettings {
+    /// The setting called `name`, as in `context.<name>`
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "distro" => Some(self.distro),
+            "shell" => Some(self.shell),
+            "package_manager" => Some(self.package_manager),
+            "coreutils" => Some(self.coreutils),
+            "project" => Some(self.project),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "distro" => Some(&mut self.distro),
+            "shell" => Some(&mut self.shell),
+            "package_manager" => Some(&mut self.package_manager),
+            "coreutils" => Some(&mut self.coreutils),
+            "project" => Some(&mut self.project),
+            _ => None,
+        }
+    }
+}
+
+/// Commands of package managers, in the order they are listed
+const PACKAGE_MANAGERS: &[&str] = &[
+    "apt",
+    "dnf",
+    "yum",
+    "zypper",
+    "pacman",
+    "apk",
+    "emerge",
+    "xbps-install",
+    "pkg",
+    "nix-env",
+    "brew",
+    "port",
+    "flatpak",
+    "snap",
+];
+
+/// Files that mark the kind of project in a directory
+const PROJECT_FILES: &[(&str, &str)] = &[
+    ("Cargo.toml", "Rust (Cargo)"),
+    ("package.json", "JavaScript (npm)"),
+    ("deno.json", "Deno"),
+    ("pyproject.toml", "Python"),
+    ("setup.py", "Python"),
+    ("requirements.txt", "Python"),
+    ("go.mod", "Go"),
+    ("pom.xml", "Java (Maven)"),
+    ("build.gradle", "JVM (Gradle)"),
+    ("build.gradle.kts", "JVM (Gradle)"),
+    ("Gemfile", "Ruby"),
+    ("composer.json", "PHP (Composer)"),
+    ("mix.exs", "Elixir (Mix)"),
+    ("Package.swift", "Swift"),
+    ("pubspec.yaml", "Dart"),
+    ("CMakeLists.txt", "C/C++ (CMake)"),
+    ("meson.build", "Meson"),
+    ("Makefile", "Make"),
+];
+
+static VERSION_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+\.\d+(\.\d+)?").unwrap());
+
+/// Descriptions gathered so far in this run, for each settings used
+static DESCRIBED: Lazy<Mutex<Vec<(ContextSettings, String)>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Sentences describing the environment for the `{context}` placeholder,
+/// e.g. "The user runs Fedora Linux 40 on x86_64 with zsh 5.9." Gathered
+/// only once per run for the same settings.
+pub fn describe(settings: &ContextSettings) -> String {
+    let mut described = DESCRIBED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, text)) = described.iter().find(|(known, _)| known == settings) {
+        return text.clone();
+    }
+    let text = gather(settings);
+    described.push((settings.clone(), text.clone()));
+    text
+}
+
+fn gather(settings: &ContextSettings) -> String {
+    let system = settings
+        .distro
+        .then(distribution)
+        .flatten()
+        .unwrap_or_else(|| env::consts::OS.to_string());
+    let mut text = format!("The user runs {} on {}", system, env::consts::ARCH);
+    if let Some(shell) = settings.shell.then(shell).flatten() {
+        text.push_str(&format!(" with {}", shell));
+    }
+    text.push('.');
+
+    if settings.package_manager {
+        match package_managers().as_slice() {
+            [] => {}
+            [one] => text.push_str(&format!(" Package manager: {}.", one)),
+            all => text.push_str(&format!(" Package managers: {}.", all.join(", "))),
+        }
+    }
+    if let Some(coreutils) = settings.coreutils.then(describe_coreutils).flatten() {
+        text.push_str(&format!(" Core utilities: {}.", coreutils));
+    }
+    if settings.project {
+        if let Some(project) = env::current_dir().ok().and_then(|dir| project(&dir)) {
+            text.push(' ');
+            text.push_str(&project);
+        }
+    }
+    text
+}
+
+#[cfg(target_os = "linux")]
+fn distribution() -> Option<String> {
+    crate::sysinfo::distribution()
+}
+
+#[cfg(target_os = "macos")]
+fn distribution() -> Option<String> {
+    let version = command_output("sw_vers", &["-productVersion"])?;
+    Some(format!("macOS {}", version.trim()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn distribution() -> Option<String> {
+    command_output("uname", &["-sr"]).map(|release| release.trim().to_string())
+}
+
+/// The shell's name with its version, if it tells it, e.g. "bash 5.2.21"
+fn shell() -> Option<String> {
+    let name = current_shell()?;
+    let version = env::var("SHELL")
+        .ok()
+        .and_then(|path| command_output(&path, &["--version"]))
+        .and_then(|output| first_version(&output));
+    Some(match version {
+        Some(version) => format!("{} {}", name, version),
+        None => name,
+    })
+}
+
+fn package_managers() -> Vec<&'static str> {
+    let found: Vec<&str> = PACKAGE_MANAGERS
+        .iter()
+        .copied()
+        .filter(|command| find_in_path(command).is_some())
+        .collect();
+    // Where dnf is installed, yum is only an alias for it
+    if found.contains(&"dnf") {
+        return found.into_iter().filter(|&c| c != "yum").collect();
+    }
+    found
+}
+
+/// Kinds of core utilities, which differ in the flags they take
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Coreutils {
+    Gnu,
+    Uutils,
+    Bsd,
+    BusyBox,
+    Toybox,
+}
+
+impl Coreutils {
+    /// Name of the flavor that howto recipe variants are written for
+    pub fn flavor(self) -> &'static str {
+        match self {
+            Coreutils::Gnu | Coreutils::Uutils => "gnu",
+            Coreutils::Bsd => "bsd",
+            Coreutils::BusyBox | Coreutils::Toybox => "busybox",
+        }
+    }
+}
+
+/// Flavors of the installed tools that howto recipe variants are written
+/// for: "gnu", "bsd" or "busybox" for the core utilities, and "perl-rename"
+/// or "util-linux-rename" for `rename`
+pub fn tool_flavors() -> Vec<&'static str> {
+    let mut flavors = Vec::new();
+    if let Some((kind, _)) = coreutils() {
+        flavors.push(kind.flavor());
+    }
+    if find_in_path("rename").is_some() {
+        if let Some(flavor) = command_output("rename", &["--version"]).and_then(|v| rename_kind(&v))
+        {
+            flavors.push(flavor);
+        }
+    }
+    flavors
+}
+
+fn describe_coreutils() -> Option<String> {
+    let (kind, version) = coreutils()?;
+    let version = version
+        .map(|version| format!(" {}", version))
+        .unwrap_or_default();
+    let description = match kind {
+        Coreutils::Gnu => format!("GNU coreutils{}", version),
+        Coreutils::Uutils => format!("uutils coreutils{} (GNU-compatible)", version),
+        Coreutils::Bsd => "BSD".to_string(),
+        Coreutils::BusyBox => "BusyBox".to_string(),
+        Coreutils::Toybox => "Toybox".to_string(),
+    };
+    if find_in_path("gls").is_some() {
+        return Some(format!(
+            "{} (GNU coreutils are also installed with a g prefix, e.g. gls)",
+            description
+        ));
+    }
+    Some(description)
+}
+
+/// The kind of core utilities `ls` belongs to, with their version if they
+/// tell it
+pub fn coreutils() -> Option<(Coreutils, Option<String>)> {
+    let ls = find_in_path("ls")?;
+    // BusyBox and Toybox install each tool as a link to one binary
+    if let Some(binary) = ls.canonicalize().ok().as_deref().and_then(Path::file_name) {
+        match binary.to_str() {
+            Some("busybox") => return Some((Coreutils::BusyBox, None)),
+            Some("toybox") => return Some((Coreutils::Toybox, None)),
+            _ => {}
+        }
+    }
+    coreutils_kind(command_output("ls", &["--version"]).as_deref())
+}
+
+/// Tell the kind of core utilities from the output of `ls --version`,
+/// which only GNU-compatible ones understand
+fn coreutils_kind(version_output: Option<&str>) -> Option<(Coreutils, Option<String>)> {
+    let Some(output) = version_output else {
+        return (!cfg!(target_os = "linux")).then_some((Coreutils::Bsd, None));
+    };
+    let first_line = output.lines().next().unwrap_or_default();
+    let version = first_version(first_line);
+    if first_line.contains("GNU coreutils") {
+        Some((Coreutils::Gnu, version))
+    } else if first_line.contains("uutils") {
+        Some((Coreutils::Uutils, version))
+    } else if output.contains("BusyBox") {
+        Some((Coreutils::BusyBox, None))
+    } else {
+        None
+    }
+}
+
+/// Tell Perl's `rename` from util-linux's by their `--version`, as they
+/// take different arguments
+fn rename_kind(version_output: &str) -> Option<&'static str> {
+    if version_output.contains("util-linux") {
+        Some("util-linux-rename")
+    } else if version_output.contains("File::Rename") || version_output.contains("perl") {
+        Some("perl-rename")
+    } else {
+        None
+    }
+}
+
+/// Whether `dir` is in a git repository and what kind of project it holds
+fn project(dir: &Path) -> Option<String> {
+    let git = dir.ancestors().any(|d| d.join(".git").exists());
+    let mut kinds: Vec<&str> = Vec::new();
+    for (file, kind) in PROJECT_FILES {
+        if dir.join(file).is_file() && !kinds.contains(kind) {
+            kinds.push(kind);
+        }
+    }
+    match (git, kinds.is_empty()) {
+        (false, true) => None,
+        (true, true) => Some("The current directory is in a git repository.".to_string()),
+        (false, false) => Some(format!(
+            "The current directory holds a {} project.",
+            kinds.join(" and ")
+        )),
+        (true, false) => Some(format!(
+            "The current directory is in a git repository with a {} project.",
+            kinds.join(" and ")
+        )),
+    }
+}
+
+fn first_version(text: &str) -> Option<String> {
+    VERSION_PATTERN
+        .find(text)
+        .map(|version| version.as_str().to_string())
+}
+
+/// Standard output of a command that succeeded
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_first_version() {
+        assert_eq!(
+            first_version("GNU bash, version 5.2.21(1)-release (x86_64-pc-linux-gnu)").as_deref(),
+            Some("5.2.21")
+        );
+        assert_eq!(
+            first_version("zsh 5.9 (x86_64-apple-darwin23.0)").as_deref(),
+            Some("5.9")
+        );
+        assert_eq!(first_version("dash"), None);
+    }
+
+    #[test]
+    fn test_coreutils_kind() {
+        assert_eq!(
+            coreutils_kind(Some("ls (GNU coreutils) 9.4\nCopyright (C) 2023\n")),
+            Some((Coreutils::Gnu, Some("9.4".to_string())))
+        );
+        assert_eq!(
+            coreutils_kind(Some("ls (uutils coreutils) 0.2.2\n")),
+            Some((Coreutils::Uutils, Some("0.2.2".to_string())))
+        );
+        assert_eq!(
+            coreutils_kind(Some("BusyBox v1.36.1 (2023-11-07) multi-call binary.\n")),
+            Some((Coreutils::BusyBox, None))
+        );
+        assert_eq!(Coreutils::Uutils.flavor(), "gnu");
+    }
+
+    #[test]
+    fn test_rename_kind() {
+        assert_eq!(
+            rename_kind("/usr/bin/rename using File::Rename version 2.02\n"),
+            Some("perl-rename")
+        );
+        assert_eq!(
+            rename_kind("rename from util-linux 2.39.3\n"),
+            Some("util-linux-rename")
+        );
+        assert_eq!(rename_kind("rename 1.0\n"), None);
+    }
+
+    #[test]
+    fn test_project() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(project(dir.path()), None);
+
+        fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        fs::write(dir.path().join("Makefile"), "").unwrap();
+        assert_eq!(
+            project(dir.path()).as_deref(),
+            Some("The current directory holds a Rust (Cargo) and Make project.")
+        );
+
+        let src = dir.path().join("src");
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir(&src).unwrap();
+        assert_eq!(
+            project(&src).as_deref(),
+            Some("The current directory is in a git repository.")
+        );
+    }
+
+    #[test]
+    fn test_settings_by_name() {
+        let mut settings = ContextSettings::default();
+        assert_eq!(settings.get("distro"), Some(false));
+        assert_eq!(settings.get("project"), Some(false));
+        *settings.get_mut("project").unwrap() = true;
+        assert!(settings.project);
+        assert_eq!(settings.get("hostname"), None);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::config::{get_config_path, write_private};
+use crate::error::{AskError, Result};
+use crate::keyring::{self, Keyring};
+use crate::providers::ProviderType;
+use colored::Colorize;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Keys read from their sources so far in this run
+static READ_KEYS: Lazy<Mutex<Vec<ReadKey>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+struct ReadKey {
+    account: String,
+    source: KeySource,
+    key: Option<String>,
+}
+
+/// Where API keys are found, kept out of `config.json` in a file only the
+/// user can read
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Credentials {
+    /// Keys for each provider, used when no profile supplies one
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub providers: BTreeMap<ProviderType, KeySource>,
+    /// Keys for named profiles
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, KeySource>,
+}
+
+/// How to get an API key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeySource {
+    /// The key itself
+    Stored(String),
+    /// A command that prints the key, e.g. `pass show anthropic`
+    Command { command: String },
+    /// An entry in an OS keyring, named after its owner
+    Keyring { keyring: Keyring },
+}
+
+/// The provider or profile a key belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyOwner {
+    Provider(ProviderType),
+    Profile(String),
+}
+
+impl Credentials {
+    /// Load the credentials file, or empty credentials if there is none
+    pub fn load() -> Result<Self> {
+        let path = get_credentials_path();
+        if !path.exists() {
+            return Ok(Credentials::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        write_private(
+            &get_credentials_path(),
+            &serde_json::to_string_pretty(self)?,
+        )
+    }
+
+    pub fn source(&self, owner: &KeyOwner) -> Option<&KeySource> {
+        match owner {
+            KeyOwner::Provider(provider) => self.providers.get(provider),
+            KeyOwner::Profile(name) => self.profiles.get(name),
+        }
+        .filter(|source| !matches!(source, KeySource::Stored(key) if key.is_empty()))
+    }
+
+    /// Set or remove the source for `owner`, returning the previous one
+    pub fn set_source(&mut self, owner: &KeyOwner, source: Option<KeySource>) -> Option<KeySource> {
+        match (owner, source) {
+            (KeyOwner::Provider(provider), Some(source)) => {
+                self.providers.insert(*provider, source)
+            }
+            (KeyOwner::Provider(provider), None) => self.providers.remove(provider),
+            (KeyOwner::Profile(name), Some(source)) => self.profiles.insert(name.clone(), source),
+            (KeyOwner::Profile(name), None) => self.profiles.remove(name),
+        }
+    }
+}
+
+impl KeySource {
+    /// Fetch the key, running the command or asking the keyring if needed
+    pub fn resolve(&self, owner: &KeyOwner) -> Result<String> {
+        let key = match self {
+            KeySource::Stored(key) => return Ok(key.clone()),
+            KeySource::Command { command } => {
+                keyring::run(Command::new("sh").arg("-c").arg(command), None)
+                    .map_err(|e| AskError::Generic(format!("api_key_cmd `{}`: {}", command, e)))?
+            }
+            KeySource::Keyring { keyring } => keyring.lookup(&owner.account())?,
+        };
+        if key.is_empty() {
+            return Err(AskError::Generic(format!("{} returned an empty key", self)));
+        }
+        Ok(key)
+    }
+
+    /// Delete the keyring entry behind this source, if any. Best effort:
+    /// the entry may already be gone.
+    pub fn forget(&self, owner: &KeyOwner) {
+        if let KeySource::Keyring { keyring } = self {
+            let _ = keyring.delete(&owner.account());
+        }
+    }
+
+    /// Like [`KeySource::resolve`], but a failure becomes a warning so the
+    /// usual missing-key guidance follows
+    pub fn read(&self, owner: &KeyOwner) -> Option<String> {
+        match self.resolve(owner) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                eprintln!(
+                    "{} cannot read the API key for {}: {}",
+                    "Warning:".yellow(),
+                    owner,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Like [`KeySource::read`], but a command or keyring is only asked
+    /// once per run
+    pub fn read_once(&self, owner: &KeyOwner) -> Option<String> {
+        let account = owner.account();
+        let mut read = READ_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(known) = read
+            .iter()
+            .find(|known| known.account == account && known.source == *self)
+        {
+            return known.key.clone();
+        }
+        let key = self.read(owner);
+        read.push(ReadKey {
+            account,
+            source: self.clone(),
+            key: key.clone(),
+        });
+        key
+    }
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Stored(_) => write!(f, "stored"),
+            KeySource::Command { .. } => write!(f, "api_key_cmd"),
+            KeySource::Keyring { keyring } => write!(f, "{}", keyring),
+        }
+    }
+}
+
+impl KeyOwner {
+    /// Entry name in a keyring
+    pub fn account(&self) -> String {
+        match self {
+            KeyOwner::Provider(provider) => provider.to_string(),
+            KeyOwner::Profile(name) => format!("profile:{}", name),
+        }
+    }
+}
+
+impl fmt::Display for KeyOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyOwner::Provider(provider) => write!(f, "{}", provider),
+            KeyOwner::Profile(name) => write!(f, "profile {}", name),
+        }
+    }
+}
+
+/// `credentials.json` next to `config.json`
+pub fn get_credentials_path() -> PathBuf {
+    get_config_path().with_file_name("credentials.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(credentials: &Credentials, owner: &KeyOwner) -> Option<String> {
+        credentials.source(owner)?.read(owner)
+    }
+
+    #[test]
+    fn test_sources() {
+        let work = KeyOwner::Profile("work".to_string());
+        let groq = KeyOwner::Provider(ProviderType::Groq);
+        let mut credentials = Credentials::default();
+        assert_eq!(key(&credentials, &work), None);
+
+        credentials.set_source(&work, Some(KeySource::Stored("sk-work".to_string())));
+        credentials.set_source(
+            &groq,
+            Some(KeySource::Command {
+                command: "echo gsk-from-command".to_string(),
+            }),
+        );
+        assert_eq!(key(&credentials, &work).as_deref(), Some("sk-work"));
+        assert_eq!(
+            key(&credentials, &groq).as_deref(),
+            Some("gsk-from-command")
+        );
+
+        let json = serde_json::to_string(&credentials).unwrap();
+        assert_eq!(
+            json,
+            r#"{"providers":{"groq":{"command":"echo gsk-from-command"}},"profiles":{"work":"sk-work"}}"#
+        );
+        let loaded: Credentials = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, credentials);
+
+        credentials.set_source(&work, None);
+        credentials.set_source(&groq, None);
+        assert_eq!(serde_json::to_string(&credentials).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_failing_command() {
+        let owner = KeyOwner::Provider(ProviderType::OpenAI);
+        let failing = KeySource::Command {
+            command: "echo locked >&2; exit 3".to_string(),
+        };
+        let error = failing.resolve(&owner).unwrap_err().to_string();
+        assert!(error.contains("locked"), "{}", error);
+
+        let silent = KeySource::Command {
+            command: "true".to_string(),
+        };
+        assert!(silent.resolve(&owner).is_err());
+        assert_eq!(silent.read(&owner), None);
+    }
+
+    #[test]
+    fn test_keyring_source_format() {
+        let source: KeySource = serde_json::from_str(r#"{"keyring": "secret-service"}"#).unwrap();
+        assert_eq!(
+            source,
+            KeySource::Keyring {
+                keyring: Keyring::SecretService
+            }
+        );
+        assert_eq!(
+            KeyOwner::Profile("work".to_string()).account(),
+            "profile:work"
+        );
+    }
+}
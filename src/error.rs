@@ -1,3 +1,5 @@
+use colored::Colorize;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -9,7 +11,15 @@ pub enum AskError {
     MissingApiKey { provider: String, env_var: String },
 
     #[error("API error ({status}): {message}")]
-    Api { status: u16, message: String },
+    Api {
+        status: u16,
+        message: String,
+        /// How long the server asked us to wait before retrying
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Stream error: {0}")]
+    Stream(String),
 
     #[error("Unknown resource: {0}\nAvailable: disk, memory, cpu, ports, uptime, os")]
     UnknownResource(String),
@@ -26,6 +36,26 @@ pub enum AskError {
     #[error("Custom provider not confirmed. Run 'ask config' to confirm the custom provider configuration.")]
     CustomProviderNotConfirmed,
 
+    #[error("Session not found: {0}\nList sessions with: ask session list")]
+    SessionNotFound(String),
+
+    #[error("Invalid session name: {0}\nUse letters, digits, '-', '_' and '.'")]
+    InvalidSessionName(String),
+
+    #[error("Profile not found: {0}\nList profiles with: ask config profile list")]
+    ProfileNotFound(String),
+
+    #[error("Invalid profile name: {0}\nUse letters, digits, '-', '_' and '.'")]
+    InvalidProfileName(String),
+
+    /// A command run for the user failed; `ask` exits with its status
+    #[error("Command failed ({0})")]
+    CommandFailed(std::process::ExitStatus),
+
+    /// The user answered no to `ask prompt`
+    #[error("Declined")]
+    Declined,
+
     #[error("Not sure how to query: {0}")]
     UnknownQuery(String),
 
@@ -40,6 +70,30 @@ pub enum AskError {
 
     #[error(transparent)]
     Request(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Readline(#[from] rustyline::error::ReadlineError),
+}
+
+impl AskError {
+    /// Print the error to stderr, unless the handler already explained it
+    pub fn report(&self) {
+        match self {
+            // Already printed helpful message
+            AskError::MissingApiKey { .. }
+            | AskError::CustomProviderNotConfirmed
+            | AskError::Declined => {}
+            _ => eprintln!("{}: {}", "Error".red(), self),
+        }
+    }
+
+    /// Exit status of `ask` after this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AskError::CommandFailed(status) => status.code().unwrap_or(1),
+            _ => 1,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, AskError>;
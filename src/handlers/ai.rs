@@ -1,46 +1,357 @@
+use crate::attachment::{self, Attachment};
 use crate::config::Config;
 use crate::error::{AskError, Result};
-use crate::providers::{create_provider, is_known_provider_url, ProviderType};
+use crate::handlers::code;
+use crate::markdown::{code_blocks, CodeBlock};
+use crate::output::Output;
+use crate::prompts::{self, PromptKind};
+use crate::providers::retry;
+use crate::providers::streaming::{collect, Completion, EventStream, Usage};
+use crate::providers::{
+    create_provider, is_known_provider_url, Message, ProviderConfig, ProviderType,
+};
+use crate::render::render;
+use crate::session::Session;
+use crate::usage::{self, Record};
 use colored::Colorize;
+use serde::Serialize;
+use std::io::{self, IsTerminal};
+use std::time::Instant;
+
+/// JSON document describing an AI answer
+#[derive(Serialize)]
+struct Answer<'a> {
+    query: &'a str,
+    provider: String,
+    model: &'a str,
+    response: &'a str,
+    stop_reason: Option<&'a str>,
+    usage: Usage,
+    /// Estimated from the price table, if the model is in it
+    cost_usd: Option<f64>,
+    timing: Timing,
+    code_blocks: &'a [CodeBlock],
+}
+
+#[derive(Serialize)]
+struct Timing {
+    total_ms: u128,
+    first_token_ms: Option<u128>,
+}
+
+/// Answer `query` with any `attachments` as context, continuing the
+/// conversation in `session` if one is given. `system_prompt` replaces the
+/// default system prompt if given.
+pub async fn handle(
+    query: &str,
+    attachments: &[Attachment],
+    system_prompt: Option<&str>,
+    config: &Config,
+    session: Option<&mut Session>,
+    output: &Output,
+) -> Result<()> {
+    let mut messages = session
+        .as_ref()
+        .map(|s| s.messages.clone())
+        .unwrap_or_default();
+    let message = attachment::embed(query, attachments);
+    messages.push(Message::user(&message));
+
+    let started = Instant::now();
+    let (stream, answered_by) = start(config, query, &messages, system_prompt).await?;
+    // Pipes and files get the Markdown as written
+    let markdown = !output.json && !output.raw && io::stdout().is_terminal();
+    let completion = if output.json {
+        collect(stream).await?
+    } else {
+        render(stream, &mut io::stdout(), markdown).await?
+    };
+
+    let record = Record::new(&answered_by, &completion.usage);
+    record_usage(&record);
+    let blocks = code_blocks(&completion.text);
+    if let Err(e) = code::remember(&blocks) {
+        eprintln!(
+            "{} could not keep the answer's code blocks: {}",
+            "Warning:".yellow(),
+            e
+        );
+    }
+    if output.json {
+        print_answer(
+            query,
+            &answered_by,
+            &completion,
+            &record,
+            &blocks,
+            started,
+            output,
+        )?;
+    } else if output.stats {
+        print_stats(&completion, &record, started);
+    }
+    if markdown && !blocks.is_empty() {
+        print_follow_ups(blocks.len(), output);
+    }
+
+    if let Some(session) = session {
+        // Keep the attachments so follow-up questions can refer to them
+        session.push_exchange(&message, &completion.text);
+        session.save()?;
+    }
+    Ok(())
+}
+
+fn print_answer(
+    query: &str,
+    config: &Config,
+    completion: &Completion,
+    record: &Record,
+    blocks: &[CodeBlock],
+    started: Instant,
+    output: &Output,
+) -> Result<()> {
+    let model = config.effective_model();
+    output.print_json(&Answer {
+        query,
+        provider: config.provider.to_string(),
+        model: &model,
+        response: &completion.text,
+        stop_reason: completion.stop_reason.as_deref(),
+        usage: completion.usage,
+        cost_usd: record.cost_usd,
+        timing: Timing {
+            total_ms: started.elapsed().as_millis(),
+            first_token_ms: completion
+                .first_text_at
+                .map(|t| t.duration_since(started).as_millis()),
+        },
+        code_blocks: blocks,
+    })
+}
+
+/// Point out how to use the numbered code blocks of an answer, e.g.
+/// "Code block 1: ask --copy 1 | --run 1 | --save 1 FILE"
+fn print_follow_ups(count: usize, output: &Output) {
+    let (label, n) = match count {
+        1 => ("Code block 1".to_string(), "1".to_string()),
+        count => (format!("Code blocks 1-{}", count), "N".to_string()),
+    };
+    let hint = if output.interactive {
+        format!("{}: /copy {n} | /run {n} | /save {n} FILE", label, n = n)
+    } else {
+        format!(
+            "{}: ask --copy {n} | --run {n} | --save {n} FILE",
+            label,
+            n = n
+        )
+    };
+    eprintln!("{}", hint.dimmed());
+}
+
+/// Add an answer to the usage ledger. A ledger that cannot be written is
+/// not worth failing the answer for.
+pub fn record_usage(record: &Record) {
+    if let Err(e) = usage::record(record) {
+        eprintln!(
+            "{} could not update the usage ledger: {}",
+            "Warning:".yellow(),
+            e
+        );
+    }
+}
+
+/// `--stats` summary on stderr, e.g.
+/// "Tokens: 12 in, 42 out | 1.84s (first token 0.41s) | ~$0.0007"
+fn print_stats(completion: &Completion, record: &Record, started: Instant) {
+    let tokens = match (record.input_tokens, record.output_tokens) {
+        (None, None) => "Tokens: not reported".to_string(),
+        (input, output) => format!(
+            "Tokens: {} in, {} out",
+            input.map_or("?".to_string(), |n| n.to_string()),
+            output.map_or("?".to_string(), |n| n.to_string())
+        ),
+    };
+    let mut timing = format!("{:.2}s", started.elapsed().as_secs_f64());
+    if let Some(first) = completion.first_text_at {
+        timing.push_str(&format!(
+            " (first token {:.2}s)",
+            first.duration_since(started).as_secs_f64()
+        ));
+    }
+    let cost = match record.cost_usd {
+        Some(cost) => format!("~{}", usage::format_cost(cost)),
+        None => "cost unknown".to_string(),
+    };
+    eprintln!(
+        "{}",
+        format!("{} | {} | {} | {}", record.model, tokens, timing, cost).dimmed()
+    );
+}
+
+/// Start a response from the configured provider. If it cannot be used or
+/// fails before any output, each of `fallback_providers` is tried in turn.
+/// Returns the stream and the settings of the provider that is answering.
+/// Guidance for `query` is printed when no provider can be used.
+///
+/// `system_prompt` replaces the default system prompt if given.
+pub async fn start(
+    config: &Config,
+    query: &str,
+    messages: &[Message],
+    system_prompt: Option<&str>,
+) -> Result<(EventStream, Config)> {
+    let mut candidates = vec![config.clone()];
+    candidates.extend(
+        config
+            .fallback_providers
+            .iter()
+            .filter(|&&provider| provider != config.provider && provider.is_known())
+            .map(|&provider| config.for_fallback(provider)),
+    );
+
+    let system_prompt = match system_prompt {
+        Some(prompt) => prompt.to_string(),
+        None => prompts::system_prompt(PromptKind::Ask, config),
+    };
+
+    // Why the configured provider cannot be used, if it cannot
+    let mut unusable = None;
+    // The last provider that failed, and how
+    let mut failed: Option<(ProviderType, AskError)> = None;
+    for candidate in candidates {
+        // The key is only read for a provider that is tried
+        let provider_config = match check_provider_quietly(&candidate)
+            .and_then(|()| read_key(&candidate, &system_prompt))
+        {
+            Ok(provider_config) => provider_config,
+            Err(e) => {
+                if candidate.provider == config.provider {
+                    unusable = Some(e);
+                }
+                continue;
+            }
+        };
+        match (&failed, &unusable) {
+            (Some((provider, e)), _) => eprintln!(
+                "{} {} failed ({}), trying {}…",
+                "Warning:".yellow(),
+                provider,
+                retry::summary(e),
+                candidate.provider
+            ),
+            (None, Some(e)) => eprintln!(
+                "{} {} cannot be used ({}), trying {}…",
+                "Warning:".yellow(),
+                config.provider,
+                problem(e),
+                candidate.provider
+            ),
+            (None, None) => {}
+        }
+
+        let provider = create_provider(candidate.provider);
+        match provider.stream(&provider_config, messages).await {
+            Ok(stream) => {
+                if candidate.provider != config.provider {
+                    eprintln!(
+                        "Answered by {} ({})",
+                        candidate.provider,
+                        candidate.effective_model()
+                    );
+                }
+                return Ok((stream, candidate));
+            }
+            Err(e) => failed = Some((candidate.provider, e)),
+        }
+    }
+
+    match (failed, unusable) {
+        (Some((_, e)), _) => Err(e),
+        (None, Some(e)) => {
+            print_guidance(&e, Some(query));
+            Err(e)
+        }
+        (None, None) => unreachable!("the configured provider is always a candidate"),
+    }
+}
+
+/// Settings for a request to `config`'s provider, failing if its key
+/// source gave no key
+fn read_key(config: &Config, system_prompt: &str) -> Result<ProviderConfig> {
+    let provider_config = config.provider_config(system_prompt);
+    if config.provider.requires_api_key() && provider_config.api_key.is_empty() {
+        return Err(missing_key(config.provider));
+    }
+    Ok(provider_config)
+}
+
+fn missing_key(provider: ProviderType) -> AskError {
+    AskError::MissingApiKey {
+        provider: provider.to_string(),
+        env_var: provider.env_var_name().to_string(),
+    }
+}
+
+/// Short reason for a provider that cannot be used, e.g. "no API key"
+fn problem(error: &AskError) -> &'static str {
+    match error {
+        AskError::CustomProviderNotConfirmed => "custom provider not confirmed",
+        _ => "no API key",
+    }
+}
+
+/// Whether `config`'s provider has what it needs without asking the user:
+/// an API key if it takes one, and confirmation of a custom URL. The key
+/// itself is not read.
+pub fn check_provider_quietly(config: &Config) -> Result<()> {
+    let provider = config.provider;
+    if provider.requires_api_key() && !config.has_api_key() {
+        return Err(missing_key(provider));
+    }
 
-pub async fn handle(query: &str, config: &Config) -> Result<()> {
-    let provider_type = config.provider;
-
-    // Check if API key is required and missing
-    if provider_type.requires_api_key() && config.api_key.is_empty() {
-        let env_var = provider_type.env_var_name();
-        println!("{}", format!("{} requires an API key", provider_type).red());
-        println!("Set it via: ask config api_key=<your-key>");
-        println!("Or set {} environment variable", env_var);
-        println!("\nYour query: {}", query);
-        println!("\n{}:", "Alternatives".yellow());
-        println!("  ask howto <task>      - Get command suggestions");
-        println!("  ask explain <cmd>     - Get help for a command");
-        println!("  ask system <resource> - System information");
-        return Err(AskError::MissingApiKey {
-            provider: provider_type.to_string(),
-            env_var: env_var.to_string(),
-        });
-    }
-
-    // Check if custom provider/URL needs confirmation
     let has_custom_url = config
         .api_url
         .as_ref()
         .is_some_and(|url| !is_known_provider_url(url));
-    let is_custom_provider = matches!(provider_type, ProviderType::Custom);
-
-    if (has_custom_url || is_custom_provider) && !config.custom_provider_confirmed {
-        println!(
-            "{}",
-            "Custom provider configuration requires confirmation.".red()
-        );
-        println!("Run 'ask config' and re-enter your custom settings to confirm.");
+    if (has_custom_url || provider == ProviderType::Custom) && !config.custom_provider_confirmed {
         return Err(AskError::CustomProviderNotConfirmed);
     }
+    Ok(())
+}
 
-    let provider = create_provider(provider_type);
-    let provider_config = config.provider_config();
+/// Make sure `config`'s provider can be used, printing guidance if not
+pub fn check_provider(config: &Config, query: Option<&str>) -> Result<()> {
+    let result = check_provider_quietly(config);
+    if let Err(e) = &result {
+        print_guidance(e, query);
+    }
+    result
+}
 
-    provider.stream_response(&provider_config, query).await
+/// Explain how to make a provider usable after `check_provider_quietly`
+/// turned it down, with other ways to answer `query` if there is one
+fn print_guidance(error: &AskError, query: Option<&str>) {
+    match error {
+        AskError::MissingApiKey { provider, env_var } => {
+            println!("{}", format!("{} requires an API key", provider).red());
+            println!("Set it via: ask config api_key=<your-key>");
+            println!("Or set {} environment variable", env_var);
+            if let Some(query) = query {
+                println!("\nYour query: {}", query);
+                println!("\n{}:", "Alternatives".yellow());
+                println!("  ask howto <task>      - Get command suggestions");
+                println!("  ask explain <cmd>     - Get help for a command");
+                println!("  ask system <resource> - System information");
+            }
+        }
+        AskError::CustomProviderNotConfirmed => {
+            println!(
+                "{}",
+                "Custom provider configuration requires confirmation.".red()
+            );
+            println!("Run 'ask config' and re-enter your custom settings to confirm.");
+        }
+        _ => {}
+    }
 }
@@ -0,0 +1,277 @@
+use crate::config::{get_data_dir, write_private, Config};
+use crate::error::{AskError, Result};
+use crate::handlers::exec::{confirm, run_in_shell};
+use crate::markdown::CodeBlock;
+use colored::Colorize;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Something to do with a numbered code block of the last answer
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodeAction {
+    /// Copy it to the clipboard
+    Copy(usize),
+    /// Run it in the shell, after confirmation if `confirm_exec` is set
+    Run(usize),
+    /// Write it to a file
+    Save(usize, PathBuf),
+}
+
+impl CodeAction {
+    fn number(&self) -> usize {
+        match self {
+            CodeAction::Copy(n) | CodeAction::Run(n) | CodeAction::Save(n, _) => *n,
+        }
+    }
+}
+
+/// Info strings of blocks that hold shell commands
+const SHELL_LANGUAGES: &[&str] = &[
+    "",
+    "sh",
+    "bash",
+    "zsh",
+    "shell",
+    "fish",
+    "ksh",
+    "console",
+    "shell-session",
+    "terminal",
+];
+
+fn last_blocks_path() -> PathBuf {
+    get_data_dir().join("last_code_blocks.json")
+}
+
+/// Keep the code blocks of an answer for `--copy`, `--run` and `--save`,
+/// replacing those of the previous answer
+pub fn remember(blocks: &[CodeBlock]) -> Result<()> {
+    let path = last_blocks_path();
+    if blocks.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+    // Answers can repeat secrets pasted into the question
+    write_private(&path, &serde_json::to_string(blocks)?)
+}
+
+fn last_blocks() -> Result<Vec<CodeBlock>> {
+    match fs::read_to_string(last_blocks_path()) {
+        Ok(content) => Ok(serde_json::from_str(&content).unwrap_or_default()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Carry out `action` on a code block of the last answer
+pub fn act(action: &CodeAction, config: &Config) -> Result<()> {
+    let blocks = last_blocks()?;
+    let number = action.number();
+    let block = match blocks.len() {
+        0 => {
+            return Err(AskError::Generic(
+                "The last answer has no code blocks".to_string(),
+            ))
+        }
+        len if number == 0 || number > len => {
+            return Err(AskError::Generic(format!(
+                "No code block {}; the last answer has {} (1-{})",
+                number, len, len
+            )))
+        }
+        _ => &blocks[number - 1],
+    };
+
+    match action {
+        CodeAction::Copy(_) => {
+            copy(&block.code)?;
+            eprintln!("Copied code block {} to the clipboard.", number);
+            Ok(())
+        }
+        CodeAction::Run(_) => run(block, number, config),
+        CodeAction::Save(_, path) => save(block, number, path),
+    }
+}
+
+/// Parse the N of `--copy N` and friends
+pub fn parse_number(value: &str) -> Result<usize> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or_else(|| AskError::Generic(format!("Not a code block number: {}", value)))
+}
+
+/// Hand `text` to the terminal's clipboard with an OSC 52 escape sequence,
+/// which also works over SSH
+fn copy(text: &str) -> Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    let mut terminal: Box<dyn Write> = match OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(tty) => Box::new(tty),
+        Err(_) if io::stderr().is_terminal() => Box::new(io::stderr()),
+        Err(_) => {
+            return Err(AskError::Generic(
+                "Copying needs a terminal to send the text to".to_string(),
+            ))
+        }
+    };
+    terminal.write_all(sequence.as_bytes())?;
+    terminal.flush()?;
+    Ok(())
+}
+
+fn run(block: &CodeBlock, number: usize, config: &Config) -> Result<()> {
+    let command = shell_command(block).ok_or_else(|| {
+        AskError::Generic(format!(
+            "Code block {} is {}, not shell commands. Save it with: ask --save {} <file>",
+            number, block.language, number
+        ))
+    })?;
+
+    match command.lines().collect::<Vec<_>>().as_slice() {
+        [line] => println!("{} {}", "Command:".bold(), line.green().bold()),
+        lines => {
+            println!("{}", "Commands:".bold());
+            for line in lines {
+                println!("  {}", line.green().bold());
+            }
+        }
+    }
+    println!();
+
+    if config.confirm_exec && !confirm("Run this command?")? {
+        println!("{}", "Command not run.".yellow());
+        return Ok(());
+    }
+
+    let status = run_in_shell(&command)?;
+    if !status.success() {
+        return Err(AskError::CommandFailed(status));
+    }
+    Ok(())
+}
+
+/// The commands in a shell block, without the `$ ` prompts models like to
+/// show. Console transcripts keep only the prompted lines, dropping their
+/// output. `None` for other languages.
+fn shell_command(block: &CodeBlock) -> Option<String> {
+    let language = block.language.to_lowercase();
+    if !SHELL_LANGUAGES.contains(&language.as_str()) {
+        return None;
+    }
+    let lines: Vec<&str> = block.code.lines().collect();
+    let prompted: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("$ "))
+        .collect();
+    let is_transcript = matches!(language.as_str(), "console" | "shell-session" | "terminal");
+    let all_prompted = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.starts_with("$ "));
+    if prompted.is_empty() || !(is_transcript || all_prompted) {
+        return Some(block.code.clone());
+    }
+    Some(prompted.join("\n"))
+}
+
+fn save(block: &CodeBlock, number: usize, path: &Path) -> Result<()> {
+    if path.exists() {
+        if !io::stdin().is_terminal() {
+            return Err(AskError::Generic(format!(
+                "{} already exists",
+                path.display()
+            )));
+        }
+        if !confirm(&format!("{} already exists. Overwrite it?", path.display()))? {
+            println!("{}", "Not saved.".yellow());
+            return Ok(());
+        }
+    }
+    fs::write(path, &block.code)?;
+
+    // Scripts with a shebang are ready to run
+    #[cfg(unix)]
+    if block.code.starts_with("#!") {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    println!("Saved code block {} to {}", number, path.display());
+    Ok(())
+}
+
+/// Standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let byte = |i: usize| u32::from(chunk.get(i).copied().unwrap_or(0));
+        let group = (byte(0) << 16) | (byte(1) << 8) | byte(2);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(
+                    ALPHABET[((group >> (18 - 6 * i)) & 63) as usize],
+                ));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(language: &str, code: &str) -> CodeBlock {
+        CodeBlock {
+            language: language.to_string(),
+            code: code.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"ls -la\n"), "bHMgLWxhCg==");
+        assert_eq!(base64("✓".as_bytes()), "4pyT");
+    }
+
+    #[test]
+    fn test_shell_command() {
+        assert_eq!(
+            shell_command(&block("bash", "ls -la\ncd /tmp\n")).unwrap(),
+            "ls -la\ncd /tmp\n"
+        );
+        assert_eq!(
+            shell_command(&block("", "$ ls\n\n$ pwd\n")).unwrap(),
+            "ls\npwd"
+        );
+        assert_eq!(
+            shell_command(&block("console", "$ ls\nfile.txt\n$ pwd\n/tmp\n")).unwrap(),
+            "ls\npwd"
+        );
+        // Only a transcript has output lines to drop
+        assert_eq!(
+            shell_command(&block("sh", "echo '$ not a prompt'\n$ ls\n")).unwrap(),
+            "echo '$ not a prompt'\n$ ls\n"
+        );
+        assert_eq!(shell_command(&block("python", "print(1)\n")), None);
+    }
+
+    #[test]
+    fn test_parse_number() {
+        assert_eq!(parse_number("2").unwrap(), 2);
+        assert!(parse_number("0").is_err());
+        assert!(parse_number("two").is_err());
+    }
+}
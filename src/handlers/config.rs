@@ -1,35 +1,64 @@
-use crate::config::{get_config_path, Config};
-use crate::error::Result;
+use crate::config::{get_config_path, Config, Profile};
+use crate::context;
+use crate::credentials::{get_credentials_path, Credentials, KeyOwner, KeySource};
+use crate::error::{AskError, Result};
+use crate::handlers::{models, profile};
+use crate::keyring::Keyring;
+use crate::output::Output;
+use crate::prompts::{template_path, PromptKind};
 use crate::providers::{is_known_provider_url, ProviderType};
+use crate::usage::Price;
 use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::io::{self, Write};
 
 /// Sensitive config keys that should not be passed on the command line
 const SENSITIVE_KEYS: &[&str] = &["api_key"];
 
-pub fn handle(args: &str, config: &mut Config) -> Result<()> {
+/// JSON document for `config show`
+#[derive(Serialize)]
+struct ConfigDocument<'a> {
+    config_file: String,
+    settings: BTreeMap<&'static str, Option<String>>,
+    effective: Effective,
+    /// Where each provider's key comes from, e.g. "stored" or its env var
+    api_keys: BTreeMap<String, String>,
+    /// Price overrides in USD per million tokens
+    prices: &'a BTreeMap<String, Price>,
+    /// Paths of the prompt template files in use
+    prompt_templates: Vec<String>,
+    /// What `{context}` in a system prompt expands to
+    context: String,
+}
+
+#[derive(Serialize)]
+struct Effective {
+    profile: Option<String>,
+    model: String,
+    api_url: String,
+}
+
+pub async fn handle(args: &str, config: &mut Config, output: &Output) -> Result<()> {
     let args = args.trim();
 
     if args.is_empty() || args == "show" {
+        if output.json {
+            return print_json(config, output);
+        }
+
         println!("Current configuration:");
         println!("  Config file: {}", get_config_path().display());
+        if let Some(name) = &config.active_profile {
+            println!("  Profile: {}", name.green());
+        }
         println!();
         for key in Config::valid_keys() {
-            let value = config.get(key);
-            let display_value = match key {
-                &"api_key" => {
-                    if let Some(ref v) = value {
-                        if !v.is_empty() {
-                            format!("{}...(hidden)", &v[..v.len().min(8)])
-                        } else {
-                            "(not set)".to_string()
-                        }
-                    } else {
-                        "(not set)".to_string()
-                    }
-                }
-                &"model" | &"api_url" => value.unwrap_or_else(|| "(default)".to_string()),
-                _ => value.unwrap_or_default(),
+            let display_value = match (*key, display_value(config, key)) {
+                ("api_key", None) => "(not set)".to_string(),
+                ("model" | "api_url" | "system_prompt", None) => "(default)".to_string(),
+                ("fallback_providers" | "default_profile", None) => "(none)".to_string(),
+                (_, value) => value.unwrap_or_default(),
             };
             println!("  {} = {}", key, display_value);
         }
@@ -40,16 +69,61 @@ pub fn handle(args: &str, config: &mut Config) -> Result<()> {
         println!("  model (effective) = {}", config.effective_model());
         println!("  api_url (effective) = {}", config.effective_api_url());
 
+        println!();
+        println!("Environment context sent with prompts:");
+        println!("  {}", context::describe(&config.context));
+
+        println!();
+        let sources = key_sources(config);
+        if sources.is_empty() {
+            println!("API keys: (none)");
+        } else {
+            let keys: Vec<String> = sources
+                .iter()
+                .map(|(provider, source)| format!("{} ({})", provider, source))
+                .collect();
+            println!("API keys: {}", keys.join(", "));
+        }
+
+        if !config.prices.is_empty() {
+            println!();
+            println!("Prices (USD per million tokens, input/output):");
+            for (model, price) in &config.prices {
+                println!("  {} = {}/{}", model, price.input, price.output);
+            }
+        }
+
+        let templates = prompt_templates();
+        if !templates.is_empty() {
+            println!();
+            println!("Prompt templates:");
+            for path in templates {
+                println!("  {}", path);
+            }
+        }
+
+        if !config.profiles.is_empty() {
+            println!();
+            let names: Vec<&str> = config.profiles.keys().map(String::as_str).collect();
+            println!("Profiles: {}", names.join(", "));
+        }
+
         return Ok(());
     }
 
+    if let Some(rest) = args.strip_prefix("profile") {
+        if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+            return profile::handle(rest.trim(), config);
+        }
+    }
+
     if args == "path" {
         println!("{}", get_config_path().display());
         return Ok(());
     }
 
     if args == "init" {
-        config.save()?;
+        Config::load_file()?.save()?;
         println!("Config saved to {}", get_config_path().display());
         return Ok(());
     }
@@ -58,6 +132,10 @@ pub fn handle(args: &str, config: &mut Config) -> Result<()> {
         let key = key.trim();
         let value = value.trim();
 
+        if key == "api_key_cmd" {
+            return set_key_command(config, value);
+        }
+
         // Warn about sensitive values in shell history
         if SENSITIVE_KEYS.contains(&key) {
             print_history_warning();
@@ -76,17 +154,32 @@ pub fn handle(args: &str, config: &mut Config) -> Result<()> {
             _ => false,
         };
 
-        if needs_confirmation {
-            if !confirm_custom_provider(key, value)? {
-                println!("{}", "Configuration cancelled.".yellow());
-                return Ok(());
-            }
-            // Mark custom provider as confirmed
-            config.custom_provider_confirmed = true;
+        if needs_confirmation && !confirm_custom_provider(key, value)? {
+            println!("{}", "Configuration cancelled.".yellow());
+            return Ok(());
         }
 
-        config.set(key, value)?;
-        config.save()?;
+        if key == "api_key" {
+            return store_api_key(config, value);
+        }
+        let previous_provider = config.provider;
+        update_file(config, |c| {
+            let provider = c.provider;
+            c.set(key, value)?;
+            if c.provider != provider {
+                // The previous model belongs to the old provider
+                c.model = None;
+            }
+            if needs_confirmation {
+                // Mark custom provider as confirmed
+                c.custom_provider_confirmed = true;
+            }
+            Ok(())
+        })?;
+        if config.provider != previous_provider {
+            // So is the previous key
+            config.use_provider_key();
+        }
         println!(
             "Set {} = {}",
             key.green(),
@@ -96,46 +189,55 @@ pub fn handle(args: &str, config: &mut Config) -> Result<()> {
                 value.to_string()
             }
         );
+        if key == "provider" && config.provider.requires_api_key() && !config.has_api_key() {
+            println!(
+                "No API key stored for {}. Set one with: {}",
+                config.provider,
+                "ask config api_key".green()
+            );
+        }
+        if key == "model" {
+            models::check_model(config, value).await;
+        }
+        if let Some(name) = &config.active_profile {
+            if Profile::valid_keys().contains(&key) {
+                println!(
+                    "Note: profile '{}' is active and may override this. Change it with: ask config profile add {} {}={}",
+                    name, name, key, value
+                );
+            }
+        }
         return Ok(());
     }
 
     // Check if this is a sensitive key without a value - show current and prompt to change
     if SENSITIVE_KEYS.contains(&args) {
         let current = config.get(args).unwrap_or_default();
+        let owner = key_owner(config);
 
-        if current.is_empty() {
-            // No value set, prompt for new value
-            let value = prompt_secret(args)?;
-            if value.is_empty() {
-                println!("{}", "No value entered, config unchanged.".yellow());
-                return Ok(());
-            }
-            config.set(args, &value)?;
-            config.save()?;
-            println!("Set {} = (hidden)", args.green());
-        } else {
+        if !current.is_empty() {
             // Value exists, show truncated and ask if user wants to change
             let truncated = format!("{}...", &current[..current.len().min(8)]);
-            println!("{} is currently set to: {}", args, truncated);
+            println!("API key for {} is currently set to: {}", owner, truncated);
             print!("Do you want to change it? [y/N]: ");
             io::stdout().flush()?;
 
             let mut response = String::new();
             io::stdin().read_line(&mut response)?;
 
-            if response.trim().eq_ignore_ascii_case("y") {
-                let value = prompt_secret(args)?;
-                if value.is_empty() {
-                    println!("{}", "No value entered, config unchanged.".yellow());
-                    return Ok(());
-                }
-                config.set(args, &value)?;
-                config.save()?;
-                println!("Set {} = (hidden)", args.green());
-            } else {
+            if !response.trim().eq_ignore_ascii_case("y") {
                 println!("Config unchanged.");
+                return Ok(());
             }
         }
+        return set_key_interactively(config, &owner);
+    }
+
+    if args == "api_key_cmd" {
+        let credentials = Credentials::load()?;
+        if let Some(KeySource::Command { command }) = credentials.source(&key_owner(config)) {
+            println!("{}", command);
+        }
         return Ok(());
     }
 
@@ -144,14 +246,250 @@ pub fn handle(args: &str, config: &mut Config) -> Result<()> {
         println!("{}", value);
         Ok(())
     } else {
-        println!("{}: {}", "Unknown config key".red(), args);
-        println!("Available keys: {}", Config::valid_keys().join(", "));
-        std::process::exit(1);
+        Err(AskError::Generic(format!(
+            "Unknown config key: {}\nAvailable keys: {}",
+            args,
+            Config::valid_keys().join(", ")
+        )))
+    }
+}
+
+/// Apply `change` to the config file and to the settings in use
+fn update_file(config: &mut Config, change: impl Fn(&mut Config) -> Result<()>) -> Result<()> {
+    let mut file = Config::load_file()?;
+    file.migrate_api_key()?;
+    change(&mut file)?;
+    file.save()?;
+    change(config)
+}
+
+/// Keys set with `ask config` belong to the active profile, or to the
+/// provider in use if no profile is active
+fn key_owner(config: &Config) -> KeyOwner {
+    match &config.active_profile {
+        Some(name) => KeyOwner::Profile(name.clone()),
+        None => KeyOwner::Provider(config.provider),
+    }
+}
+
+fn store_api_key(config: &mut Config, value: &str) -> Result<()> {
+    let owner = key_owner(config);
+    store_key_source(config, &owner, KeySource::Stored(value.to_string()), value)
+}
+
+/// Places `ask config api_key` can keep a key
+enum Storage {
+    File,
+    Keyring(Keyring),
+    Command,
+}
+
+/// Ask where to keep the key for `owner`, then read the key (or the command
+/// that prints it) and store it there
+fn set_key_interactively(config: &mut Config, owner: &KeyOwner) -> Result<()> {
+    let mut choices = vec![(
+        Storage::File,
+        format!(
+            "credentials file ({}, readable only by you)",
+            get_credentials_path().display()
+        ),
+    )];
+    for keyring in Keyring::ALL {
+        if keyring.is_available() {
+            let description = match keyring {
+                Keyring::SecretService => "Secret Service (GNOME Keyring, KWallet)",
+                Keyring::Kernel => "kernel keyring (kept until logout or reboot)",
+            };
+            choices.push((Storage::Keyring(keyring), description.to_string()));
+        }
+    }
+    choices.push((
+        Storage::Command,
+        "a command that prints the key, e.g. pass show <name> (api_key_cmd)".to_string(),
+    ));
+
+    println!("Where should the API key for {} be kept?", owner);
+    for (i, (_, description)) in choices.iter().enumerate() {
+        println!("  {}) {}", i + 1, description);
+    }
+    print!("Choice [1]: ");
+    io::stdout().flush()?;
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    let (storage, _) = match response.trim() {
+        "" => &choices[0],
+        answer => answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| choices.get(n.wrapping_sub(1)))
+            .ok_or_else(|| AskError::Generic(format!("Invalid choice: {}", answer)))?,
+    };
+
+    if let Storage::Command = storage {
+        print!("Command: ");
+        io::stdout().flush()?;
+        let mut command = String::new();
+        io::stdin().read_line(&mut command)?;
+        if command.trim().is_empty() {
+            println!("{}", "No command entered, config unchanged.".yellow());
+            return Ok(());
+        }
+        return set_key_command(config, command.trim());
     }
+
+    let key = prompt_secret(&format!("API key for {}", owner))?;
+    if key.is_empty() {
+        println!("{}", "No value entered, config unchanged.".yellow());
+        return Ok(());
+    }
+    let source = match storage {
+        Storage::Keyring(keyring) => {
+            keyring.store(&owner.account(), &key)?;
+            KeySource::Keyring { keyring: *keyring }
+        }
+        _ => KeySource::Stored(key.clone()),
+    };
+    store_key_source(config, owner, source, &key)
+}
+
+/// Read the key for the active profile or provider from `command` from now
+/// on. An empty command removes a previously set one.
+fn set_key_command(config: &mut Config, command: &str) -> Result<()> {
+    let owner = key_owner(config);
+    if command.is_empty() {
+        let mut credentials = Credentials::load()?;
+        if let Some(KeySource::Command { .. }) = credentials.source(&owner) {
+            credentials.set_source(&owner, None);
+            credentials.save()?;
+            if let KeyOwner::Provider(provider) = owner {
+                config.api_keys.remove(&provider);
+            }
+        }
+        println!("Cleared {} for {}", "api_key_cmd".green(), owner);
+        return Ok(());
+    }
+
+    let source = KeySource::Command {
+        command: command.to_string(),
+    };
+    // Run it now so a typo shows up here rather than on the next question
+    let key = source.resolve(&owner)?;
+    store_key_source(config, &owner, source, &key)
+}
+
+/// Make `source` where the key of `owner` comes from; `key` is what it
+/// currently yields
+fn store_key_source(
+    config: &mut Config,
+    owner: &KeyOwner,
+    source: KeySource,
+    key: &str,
+) -> Result<()> {
+    // Move any key an older version left in the config file first
+    Config::load_file()?.save()?;
+
+    let mut credentials = Credentials::load()?;
+    if let Some(previous) = credentials.set_source(owner, Some(source.clone())) {
+        if previous != source {
+            previous.forget(owner);
+        }
+    }
+    credentials.save()?;
+
+    match &source {
+        KeySource::Stored(_) => println!("Set {} for {} = (hidden)", "api_key".green(), owner),
+        KeySource::Command { command } => {
+            println!("Set {} for {} = {}", "api_key_cmd".green(), owner, command)
+        }
+        KeySource::Keyring { keyring } => println!(
+            "Set {} for {} = (hidden, in the {})",
+            "api_key".green(),
+            owner,
+            keyring
+        ),
+    }
+    if let KeyOwner::Provider(provider) = owner {
+        config.api_keys.insert(*provider, source);
+    }
+    config.api_key = key.to_string();
+    Ok(())
+}
+
+/// Providers that have an API key, with where it comes from: its
+/// environment variable, or the source set up with `ask config api_key`
+fn key_sources(config: &Config) -> BTreeMap<String, String> {
+    let mut sources = BTreeMap::new();
+    for name in ProviderType::known_providers()
+        .iter()
+        .copied()
+        .chain(["custom"])
+    {
+        let Ok(provider) = name.parse::<ProviderType>() else {
+            continue;
+        };
+        let env_var = provider.env_var_name();
+        if !env_var.is_empty() && std::env::var(env_var).is_ok_and(|key| !key.is_empty()) {
+            sources.insert(name.to_string(), env_var.to_string());
+        } else if let Some(source) = config.api_keys.get(&provider) {
+            sources.insert(name.to_string(), source.to_string());
+        }
+    }
+    sources
+}
+
+/// Value of `key` for display, with secrets truncated. `None` if unset.
+fn display_value(config: &Config, key: &str) -> Option<String> {
+    // Reading the key could run a command or unlock a keyring
+    if key == "api_key" && config.api_key.is_empty() {
+        if let Some((_, source)) = config.key_source() {
+            if !matches!(source, KeySource::Stored(_)) {
+                return Some(format!("(from {})", source));
+            }
+        }
+    }
+    let value = config.get(key)?;
+    if SENSITIVE_KEYS.contains(&key) {
+        if value.is_empty() {
+            return None;
+        }
+        return Some(format!("{}...(hidden)", &value[..value.len().min(8)]));
+    }
+    Some(value)
+}
+
+fn print_json(config: &Config, output: &Output) -> Result<()> {
+    let settings = Config::valid_keys()
+        .iter()
+        .map(|key| (*key, display_value(config, key)))
+        .collect();
+    output.print_json(&ConfigDocument {
+        config_file: get_config_path().display().to_string(),
+        settings,
+        effective: Effective {
+            profile: config.active_profile.clone(),
+            model: config.effective_model(),
+            api_url: config.effective_api_url(),
+        },
+        api_keys: key_sources(config),
+        prices: &config.prices,
+        prompt_templates: prompt_templates(),
+        context: context::describe(&config.context),
+    })
+}
+
+/// Template files under the prompts directory that replace a built-in
+/// system prompt
+fn prompt_templates() -> Vec<String> {
+    PromptKind::ALL
+        .iter()
+        .map(|&kind| template_path(kind))
+        .filter(|path| path.is_file())
+        .map(|path| path.display().to_string())
+        .collect()
 }
 
 /// Prompt for a secret value without echoing to terminal
-fn prompt_secret(key: &str) -> Result<String> {
+pub(super) fn prompt_secret(key: &str) -> Result<String> {
     print!("Enter {}: ", key);
     io::stdout().flush()?;
     let value = rpassword::read_password()?;
@@ -159,7 +497,7 @@ fn prompt_secret(key: &str) -> Result<String> {
 }
 
 /// Print warning about sensitive data in shell history
-fn print_history_warning() {
+pub(super) fn print_history_warning() {
     eprintln!();
     eprintln!(
         "{}",
@@ -186,7 +524,7 @@ fn print_history_warning() {
 }
 
 /// Confirm custom provider or URL with security warning
-fn confirm_custom_provider(key: &str, value: &str) -> Result<bool> {
+pub(super) fn confirm_custom_provider(key: &str, value: &str) -> Result<bool> {
     eprintln!();
     eprintln!("{}", "SECURITY WARNING".red().bold());
     eprintln!();
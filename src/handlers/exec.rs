@@ -0,0 +1,191 @@
+use crate::config::Config;
+use crate::error::{AskError, Result};
+use crate::handlers::ai;
+use crate::prompts::{system_prompt, PromptKind};
+use crate::providers::streaming::collect;
+use crate::providers::Message;
+use crate::usage::Record;
+use colored::Colorize;
+use std::env;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// A shell command suggested by the AI, with a short explanation
+#[derive(Debug, PartialEq)]
+pub struct GeneratedCommand {
+    pub command: String,
+    pub explanation: String,
+}
+
+pub async fn handle(task: &str, config: &Config) -> Result<()> {
+    let task = task.trim();
+    if task.is_empty() {
+        return Err(AskError::Generic(
+            "Nothing to run. Usage: ask run <task>".to_string(),
+        ));
+    }
+
+    let generated = generate(task, config).await?;
+
+    println!("{} {}", "Command:".bold(), generated.command.green().bold());
+    if !generated.explanation.is_empty() {
+        println!("{}", generated.explanation);
+    }
+    println!();
+
+    if config.confirm_exec && !confirm("Run this command?")? {
+        println!("{}", "Command not run.".yellow());
+        return Ok(());
+    }
+
+    let status = run_in_shell(&generated.command)?;
+    if !status.success() {
+        return Err(AskError::CommandFailed(status));
+    }
+
+    Ok(())
+}
+
+/// `--print-command`: print only the command for `task`, without running
+/// it, for shell key bindings to put on the command line
+pub async fn print_command(task: &str, config: &Config) -> Result<()> {
+    let task = task.trim();
+    if task.is_empty() {
+        return Err(AskError::Generic(
+            "Nothing to do. Usage: ask --print-command <task>".to_string(),
+        ));
+    }
+    println!("{}", generate(task, config).await?.command);
+    Ok(())
+}
+
+/// Ask the configured provider for a single shell command that performs `task`
+pub async fn generate(task: &str, config: &Config) -> Result<GeneratedCommand> {
+    let messages = [Message::user(task)];
+    let prompt = system_prompt(PromptKind::Exec, config);
+    let (stream, answered_by) = ai::start(config, task, &messages, Some(&prompt)).await?;
+    let completion = collect(stream).await?;
+    ai::record_usage(&Record::new(&answered_by, &completion.usage));
+    let response = completion.text;
+
+    parse_response(&response).ok_or_else(|| {
+        AskError::Generic(format!(
+            "Could not extract a command from the response:\n{}",
+            response.trim()
+        ))
+    })
+}
+
+/// Extract the command and explanation from an AI response.
+///
+/// Expects `COMMAND:` / `EXPLANATION:` lines, but tolerates models that wrap
+/// the command in a code fence or reply with a bare command.
+fn parse_response(response: &str) -> Option<GeneratedCommand> {
+    let mut command = None;
+    let mut explanation = Vec::new();
+    let mut in_explanation = false;
+
+    for line in response.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = strip_label(trimmed, "COMMAND:") {
+            command = Some(strip_backticks(rest).to_string());
+            in_explanation = false;
+        } else if let Some(rest) = strip_label(trimmed, "EXPLANATION:") {
+            if !rest.is_empty() {
+                explanation.push(rest.to_string());
+            }
+            in_explanation = true;
+        } else if in_explanation && !trimmed.is_empty() {
+            explanation.push(trimmed.to_string());
+        }
+    }
+
+    // Fall back to the first non-fence line of the response
+    let command = command.or_else(|| {
+        response
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with("```"))
+            .map(|l| strip_backticks(l).to_string())
+    })?;
+
+    if command.is_empty() {
+        return None;
+    }
+
+    Some(GeneratedCommand {
+        command,
+        explanation: explanation.join(" "),
+    })
+}
+
+fn strip_label<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+    let head = line.get(..label.len())?;
+    if head.eq_ignore_ascii_case(label) {
+        Some(line[label.len()..].trim())
+    } else {
+        None
+    }
+}
+
+fn strip_backticks(s: &str) -> &str {
+    s.trim().trim_matches('`').trim()
+}
+
+/// Ask a yes/no `question` on the terminal; anything but yes is no
+pub fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N]: ", question);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let response = input.trim().to_lowercase();
+    Ok(response == "y" || response == "yes")
+}
+
+/// Run a command line in the user's shell, inheriting stdio
+pub fn run_in_shell(command: &str) -> Result<std::process::ExitStatus> {
+    let shell = env::var("SHELL")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/bin/sh".to_string());
+    let status = Command::new(&shell).arg("-c").arg(command).status()?;
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_labeled_response() {
+        let parsed = parse_response(
+            "COMMAND: find . -name '*.log' -mtime +7\nEXPLANATION: Lists log files older than a week.",
+        )
+        .unwrap();
+        assert_eq!(parsed.command, "find . -name '*.log' -mtime +7");
+        assert_eq!(parsed.explanation, "Lists log files older than a week.");
+    }
+
+    #[test]
+    fn test_parse_multiline_explanation() {
+        let parsed =
+            parse_response("command: `du -sh *`\nexplanation:\nShows the size\nof each entry.")
+                .unwrap();
+        assert_eq!(parsed.command, "du -sh *");
+        assert_eq!(parsed.explanation, "Shows the size of each entry.");
+    }
+
+    #[test]
+    fn test_parse_fenced_fallback() {
+        let parsed = parse_response("```bash\nls -la\n```").unwrap();
+        assert_eq!(parsed.command, "ls -la");
+        assert_eq!(parsed.explanation, "");
+    }
+
+    #[test]
+    fn test_parse_empty_response() {
+        assert_eq!(parse_response(""), None);
+        assert_eq!(parse_response("COMMAND:\n"), None);
+    }
+}
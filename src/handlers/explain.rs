@@ -1,12 +1,23 @@
 use crate::error::{AskError, Result};
+use crate::output::Output;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Serialize;
 use std::process::Command;
 
+/// JSON document with a command's help text
+#[derive(Serialize)]
+struct Explanation<'a> {
+    command: &'a str,
+    /// Where the text came from: "help" or "man"
+    source: &'static str,
+    text: String,
+}
+
 static EXPLAIN_PATTERN: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?i)(explain|describe)\s+(\w+)").unwrap());
 
-pub fn handle(query: &str) -> Result<()> {
+pub fn handle(query: &str, output: &Output) -> Result<()> {
     let caps = EXPLAIN_PATTERN
         .captures(query)
         .ok_or_else(|| AskError::Generic(format!("Couldn't parse command from: {}", query)))?;
@@ -22,10 +33,14 @@ pub fn handle(query: &str) -> Result<()> {
     }
 
     // Try --help first
-    let output = Command::new(cmd).arg("--help").output()?;
+    let help = Command::new(cmd).arg("--help").output()?;
 
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.json {
+        return print_json(cmd, &help, output);
+    }
+
+    if help.status.success() {
+        let stdout = String::from_utf8_lossy(&help.stdout);
         let lines: Vec<&str> = stdout.lines().take(20).collect();
         println!("{}", lines.join("\n"));
 
@@ -44,6 +59,29 @@ pub fn handle(query: &str) -> Result<()> {
     Ok(())
 }
 
+fn print_json(cmd: &str, help: &std::process::Output, output: &Output) -> Result<()> {
+    let (source, text) = if help.status.success() {
+        ("help", String::from_utf8_lossy(&help.stdout).to_string())
+    } else {
+        // Render the man page as plain text
+        let man = Command::new("man")
+            .arg(cmd)
+            .env("MANPAGER", "cat")
+            .env("MANWIDTH", "80")
+            .output()?;
+        if !man.status.success() {
+            return Err(AskError::Generic(format!("No help available for: {}", cmd)));
+        }
+        ("man", String::from_utf8_lossy(&man.stdout).to_string())
+    };
+
+    output.print_json(&Explanation {
+        command: cmd,
+        source,
+        text,
+    })
+}
+
 fn command_exists(cmd: &str) -> bool {
     Command::new("which")
         .arg(cmd)
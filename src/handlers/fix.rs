@@ -0,0 +1,134 @@
+use crate::attachment::Attachment;
+use crate::config::Config;
+use crate::error::{AskError, Result};
+use crate::handlers::ai;
+use crate::output::Output;
+use crate::prompts::{current_shell, system_prompt, PromptKind};
+use crate::session::Session;
+use std::env;
+
+const NO_HOOK: &str =
+    "No previous command recorded. Add the shell hook to your shell's startup file:
+  eval \"$(ask init bash)\"    # or zsh; for fish: ask init fish | source
+Or pipe in the output of the failing command: make 2>&1 | ask fix";
+
+/// The last command line and its exit status, as passed in by the `ask`
+/// function of `ask init`
+#[derive(Debug, PartialEq)]
+struct LastCommand {
+    command: String,
+    status: i32,
+}
+
+impl LastCommand {
+    fn from_env() -> Option<Self> {
+        let command = env::var("ASK_LAST_COMMAND")
+            .ok()
+            .filter(|c| !c.trim().is_empty())?;
+        let status = env::var("ASK_LAST_STATUS").ok()?.trim().parse().ok()?;
+        Some(LastCommand { command, status })
+    }
+}
+
+/// `ask fix` / `ask why`: diagnose the last failed command, or the failing
+/// output given in `attachments`, and suggest a corrected command
+pub async fn handle(
+    attachments: &[Attachment],
+    config: &Config,
+    session: Option<&mut Session>,
+    output: &Output,
+) -> Result<()> {
+    // Attached output may belong to any command, so the hook's is left out
+    let last = if attachments.is_empty() {
+        Some(LastCommand::from_env().ok_or_else(|| AskError::Generic(NO_HOOK.to_string()))?)
+    } else {
+        None
+    };
+    if let Some(last) = last.as_ref().filter(|last| last.status == 0) {
+        println!("The last command succeeded: {}", last.command);
+        println!(
+            "To ask about its output, pipe it in: {} 2>&1 | ask fix",
+            last.command
+        );
+        return Ok(());
+    }
+
+    let request = build_request(
+        last.as_ref(),
+        &current_shell().unwrap_or_else(|| "sh".to_string()),
+        &working_dir(),
+    );
+    ai::handle(
+        &request,
+        attachments,
+        Some(&system_prompt(PromptKind::Fix, config)),
+        config,
+        session,
+        output,
+    )
+    .await
+}
+
+/// The question sent for a failed command, with the environment it ran in
+fn build_request(last: Option<&LastCommand>, shell: &str, cwd: &str) -> String {
+    let mut request = match last {
+        Some(last) => format!(
+            "My last shell command failed.\nCommand: {}\nExit status: {}\n",
+            last.command,
+            describe_status(last.status)
+        ),
+        None => "A shell command failed with the output attached.\n".to_string(),
+    };
+    request.push_str(&format!("Shell: {}\nWorking directory: {}\n", shell, cwd));
+    request.push_str("Why did it fail, and what should I run instead?");
+    request
+}
+
+/// An exit status with what it usually means in a shell, e.g.
+/// "127 (command not found)"
+fn describe_status(status: i32) -> String {
+    let meaning = match status {
+        126 => Some("found but not executable".to_string()),
+        127 => Some("command not found".to_string()),
+        130 => Some("interrupted with Ctrl-C".to_string()),
+        129..=192 => Some(format!("killed by signal {}", status - 128)),
+        _ => None,
+    };
+    match meaning {
+        Some(meaning) => format!("{} ({})", status, meaning),
+        None => status.to_string(),
+    }
+}
+
+fn working_dir() -> String {
+    env::current_dir()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_request() {
+        let last = LastCommand {
+            command: "git pusj".to_string(),
+            status: 1,
+        };
+        assert_eq!(
+            build_request(Some(&last), "zsh", "/home/me/project"),
+            "My last shell command failed.\nCommand: git pusj\nExit status: 1\n\
+             Shell: zsh\nWorking directory: /home/me/project\n\
+             Why did it fail, and what should I run instead?"
+        );
+        assert!(build_request(None, "bash", "/tmp").starts_with("A shell command failed"));
+    }
+
+    #[test]
+    fn test_describe_status() {
+        assert_eq!(describe_status(2), "2");
+        assert_eq!(describe_status(127), "127 (command not found)");
+        assert_eq!(describe_status(137), "137 (killed by signal 9)");
+    }
+}
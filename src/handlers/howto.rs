@@ -1,111 +1,187 @@
 use crate::config::Config;
-use crate::error::Result;
+use crate::error::{AskError, Result};
 use crate::handlers::ai;
-use once_cell::sync::Lazy;
-use regex::Regex;
+use crate::output::Output;
+use crate::prompts::{system_prompt, PromptKind};
+use crate::recipes::{
+    self, missing_commands, rank, recipes_path, Platform, Ranked, Recipe, BUILT_IN,
+};
+use colored::Colorize;
+use serde::Serialize;
 
-struct Pattern {
-    regex: &'static Lazy<Regex>,
-    suggestion: &'static str,
+const USAGE: &str = "Usage: ask howto <task>\n       ask howto --list [name or tag]";
+
+/// Most suggestions shown for one question
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Score the best recipe needs for its suggestion to be trusted. Below it,
+/// the AI answers instead when it can.
+const CONFIDENT_SCORE: f64 = 0.5;
+
+/// Share of the best score that other recipes need to be shown with it
+const RELATIVE_SCORE: f64 = 0.6;
+
+/// JSON document listing local suggestions
+#[derive(Serialize)]
+struct Suggestions<'a> {
+    query: &'a str,
+    suggestions: Vec<&'a str>,
+    /// Names of the recipes the suggestions come from
+    recipes: Vec<&'a str>,
+    /// Score of the best recipe, from 0 to 1
+    confidence: f64,
+    /// Commands in the suggestions that are not installed
+    missing_commands: Vec<&'a str>,
 }
 
-// Define each pattern as a separate static
-static PATTERN_LIST: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)list.*(file|folder|dir|content)").unwrap());
-static PATTERN_COMPRESS: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)compress|zip|archive").unwrap());
-static PATTERN_FIND: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)find.*(file|folder|dir)").unwrap());
-static PATTERN_COUNT: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)(count|number).*(line|word)").unwrap());
-static PATTERN_SEARCH: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)search.*(text|content|inside)").unwrap());
-static PATTERN_RENAME: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)rename.*(file|multiple)").unwrap());
-static PATTERN_DELETE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)delete|remove").unwrap());
-static PATTERN_COPY: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)copy").unwrap());
-static PATTERN_PERMISSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)permission|chmod").unwrap());
-static PATTERN_PROCESS: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?i)process|running|kill").unwrap());
-static PATTERN_DISK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)disk.*(usage|space)").unwrap());
-static PATTERN_DOWNLOAD: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)download").unwrap());
+/// JSON document for `howto --list`
+#[derive(Serialize)]
+struct Catalog<'a> {
+    recipes: Vec<&'a Recipe>,
+}
 
-static PATTERNS: &[Pattern] = &[
-    Pattern {
-        regex: &PATTERN_LIST,
-        suggestion: "ls -la           # detailed list\nls -lah          # with human-readable sizes\nls -lt           # sorted by time",
-    },
-    Pattern {
-        regex: &PATTERN_COMPRESS,
-        suggestion: "tar -czvf archive.tar.gz folder/\nzip -r archive.zip folder/",
-    },
-    Pattern {
-        regex: &PATTERN_FIND,
-        suggestion: "find /path -name \"pattern\"\nlocate filename",
-    },
-    Pattern {
-        regex: &PATTERN_COUNT,
-        suggestion: "wc -l filename    # lines\nwc -w filename    # words",
-    },
-    Pattern {
-        regex: &PATTERN_SEARCH,
-        suggestion: "grep \"pattern\" file\ngrep -r \"pattern\" folder/",
-    },
-    Pattern {
-        regex: &PATTERN_RENAME,
-        suggestion: "mv oldname newname\nrename 's/old/new/' files*",
-    },
-    Pattern {
-        regex: &PATTERN_DELETE,
-        suggestion: "rm filename\nrm -r folder/\nfind . -name \"*.tmp\" -delete",
-    },
-    Pattern {
-        regex: &PATTERN_COPY,
-        suggestion: "cp source dest\ncp -r folder/ dest/\nrsync -av source/ dest/",
-    },
-    Pattern {
-        regex: &PATTERN_PERMISSION,
-        suggestion: "chmod +x script.sh\nchmod 755 file\nchmod -R 644 folder/",
-    },
-    Pattern {
-        regex: &PATTERN_PROCESS,
-        suggestion: "ps aux | grep name\npgrep name\nkill PID\npkill name",
-    },
-    Pattern {
-        regex: &PATTERN_DISK,
-        suggestion: "df -h\ndu -sh folder/\ndu -sh * | sort -h",
-    },
-    Pattern {
-        regex: &PATTERN_DOWNLOAD,
-        suggestion: "curl -O url\nwget url",
-    },
-];
+pub async fn handle(query: &str, config: &Config, output: &Output) -> Result<()> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(AskError::Generic(USAGE.to_string()));
+    }
+    if let Some(filter) = query.strip_prefix("--list") {
+        if filter.is_empty() || filter.starts_with(char::is_whitespace) {
+            return list(filter.trim(), output);
+        }
+    }
 
-pub async fn handle(query: &str, config: &Config) -> Result<()> {
-    let q = query.to_lowercase();
-    let mut suggestions: Vec<&str> = Vec::new();
+    let catalog: Vec<Recipe> = recipes::catalog()
+        .into_iter()
+        .filter(Recipe::applies_here)
+        .collect();
+    let ranked = rank(&catalog, query);
+    let confidence = ranked.first().map_or(0.0, |best| best.score);
+    let shown: Vec<&Ranked> = ranked
+        .iter()
+        .take_while(|ranked| ranked.score >= confidence * RELATIVE_SCORE)
+        .take(MAX_SUGGESTIONS)
+        .collect();
+    let ask_ai = confidence < CONFIDENT_SCORE && config.has_api_key();
+    let platform = Platform::default();
+    let suggestions: Vec<&str> = shown
+        .iter()
+        .map(|ranked| ranked.recipe.suggestion_for(&platform))
+        .collect();
 
-    for pattern in PATTERNS {
-        if pattern.regex.is_match(&q) {
-            suggestions.push(pattern.suggestion);
+    if output.json {
+        // Without a good local match the AI answer is the document
+        if ask_ai {
+            return ai::handle(
+                query,
+                &[],
+                Some(&system_prompt(PromptKind::Howto, config)),
+                config,
+                None,
+                output,
+            )
+            .await;
         }
+        let mut missing: Vec<&str> = Vec::new();
+        for command in suggestions.iter().flat_map(|s| missing_commands(s)) {
+            if !missing.contains(&command) {
+                missing.push(command);
+            }
+        }
+        return output.print_json(&Suggestions {
+            query,
+            suggestions,
+            recipes: shown.iter().map(|r| r.recipe.name.as_str()).collect(),
+            confidence: (confidence * 100.0).round() / 100.0,
+            missing_commands: missing,
+        });
     }
 
-    if !suggestions.is_empty() {
-        println!("Try:\n");
-        for suggestion in suggestions {
-            println!("{}", suggestion);
-            println!();
+    if ask_ai {
+        if shown.is_empty() {
+            println!("No local suggestions for: {}", query);
+        } else {
+            println!("No close local suggestions for: {}", query);
         }
-    } else {
+        println!("Asking AI for help...\n");
+        ai::handle(
+            query,
+            &[],
+            Some(&system_prompt(PromptKind::Howto, config)),
+            config,
+            None,
+            output,
+        )
+        .await?;
+    } else if shown.is_empty() {
         println!("No local suggestions for: {}", query);
-        if !config.api_key.is_empty() {
-            println!("Asking AI for help...\n");
-            ai::handle(query, config).await?;
+        println!("Tip: Try 'ask explain <command>' or configure an API key for AI assistance");
+    } else {
+        if confidence >= CONFIDENT_SCORE {
+            println!("Try:\n");
         } else {
-            println!("Tip: Try 'ask explain <command>' or configure an API key for AI assistance");
+            println!("Closest local suggestions:\n");
+        }
+        for suggestion in suggestions {
+            print_suggestion(suggestion, "");
+            println!();
         }
     }
 
     Ok(())
 }
+
+/// `howto --list [filter]`: the recipes for this OS, or those with the name
+/// or tag `filter`
+fn list(filter: &str, output: &Output) -> Result<()> {
+    let catalog = recipes::catalog();
+    let recipes: Vec<&Recipe> = catalog
+        .iter()
+        .filter(|recipe| recipe.applies_here())
+        .filter(|recipe| filter.is_empty() || recipe.is_selected_by(filter))
+        .collect();
+
+    if output.json {
+        return output.print_json(&Catalog { recipes });
+    }
+    if recipes.is_empty() {
+        println!("No recipes named or tagged: {}", filter);
+        return Ok(());
+    }
+    let platform = Platform::default();
+    for recipe in recipes {
+        let mut heading = recipe.name.bold().to_string();
+        if !recipe.tags.is_empty() {
+            heading.push_str(
+                &format!(" [{}]", recipe.tags.join(", "))
+                    .dimmed()
+                    .to_string(),
+            );
+        }
+        if recipe.source != BUILT_IN {
+            heading.push_str(&format!(" ({})", recipe.source).dimmed().to_string());
+        }
+        println!("{}", heading);
+        print_suggestion(recipe.suggestion_for(&platform), "  ");
+        println!();
+    }
+    println!(
+        "{}",
+        format!("Add your own recipes to {}", recipes_path().display()).dimmed()
+    );
+    Ok(())
+}
+
+/// Print the lines of a suggestion, pointing out commands that are not
+/// installed
+fn print_suggestion(suggestion: &str, indent: &str) {
+    for line in suggestion.lines() {
+        let missing = missing_commands(line);
+        if missing.is_empty() {
+            println!("{}{}", indent, line);
+        } else {
+            let note = format!("# not installed: {}", missing.join(", "));
+            println!("{}{}  {}", indent, line, note.yellow());
+        }
+    }
+}
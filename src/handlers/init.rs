@@ -0,0 +1,192 @@
+use crate::error::{AskError, Result};
+use crate::prompts::current_shell;
+
+const USAGE: &str = "Usage: ask init bash|zsh|fish";
+
+/// Ctrl-G sends the command line to `ask --print-command` and puts the
+/// command it prints in its place. The line is left alone if that fails.
+///
+/// After each command, a hook keeps it and its exit status in shell
+/// variables. They are not exported, so command lines never reach other
+/// programs: an `ask` function passes them to `ask fix` and `ask why` only,
+/// as `ASK_LAST_COMMAND` and `ASK_LAST_STATUS`. Runs of `ask fix` and
+/// `ask why` are not recorded, so they can be repeated.
+///
+/// What a command printed is not captured. That would mean redirecting the
+/// shell's own stderr, which line editing and prompts are drawn on, so
+/// output is piped in instead: `make 2>&1 | ask fix`.
+const BASH: &str = r#"# ask shell integration for bash. Add to ~/.bashrc:
+#   eval "$(ask init bash)"
+__ask_command_line() {
+    [ -n "$READLINE_LINE" ] || return
+    local command
+    command=$(ask --print-command -- "$READLINE_LINE" </dev/null) || return
+    READLINE_LINE=$command
+    READLINE_POINT=${#READLINE_LINE}
+}
+bind -m emacs -x '"\C-g": __ask_command_line'
+bind -m vi-insert -x '"\C-g": __ask_command_line'
+
+# The command line comes from history, so a command that is not added to
+# it cannot be named: one kept out by HISTCONTROL=ignorespace, HISTIGNORE or
+# set +o history leaves nothing recorded rather than the one before it
+__ask_history_number() {
+    local entry
+    entry=$(HISTTIMEFORMAT= builtin history 1)
+    entry=${entry%%[!0-9 ]*}
+    echo "${entry// /}"
+}
+__ask_record_status() {
+    local exit_status=$? number command
+    # Set by PS0, which blank lines skip
+    [[ -n $__ask_ran ]] || return $exit_status
+    __ask_ran=
+    number=$(__ask_history_number)
+    # A repeat dropped by ignoredups or erasedups is still the last entry
+    if [[ $number == "$__ask_history_number" ]] &&
+        [[ ! -o history || $HISTCONTROL == *ignorespace* ||
+            $HISTCONTROL == *ignoreboth* || -n $HISTIGNORE ]]; then
+        __ask_last_command= __ask_last_status=
+        return $exit_status
+    fi
+    __ask_history_number=$number
+    command=$(HISTTIMEFORMAT= builtin history 1)
+    command=${command#*[0-9]  }
+    case $command in
+        "ask fix"* | "ask why"*) ;;
+        *) __ask_last_command=$command __ask_last_status=$exit_status ;;
+    esac
+    return $exit_status
+}
+__ask_history_number=$(__ask_history_number)
+[[ $PS0 == *__ask_ran* ]] || PS0='${__ask_none[__ask_ran=1]}'$PS0
+# PROMPT_COMMAND may be an array since bash 5.1
+if [[ ${PROMPT_COMMAND[*]} != *__ask_record_status* ]]; then
+    if [[ $(declare -p PROMPT_COMMAND 2>/dev/null) == "declare -a"* ]]; then
+        PROMPT_COMMAND=(__ask_record_status "${PROMPT_COMMAND[@]}")
+    else
+        PROMPT_COMMAND="__ask_record_status${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+    fi
+fi
+
+ask() {
+    case $1 in
+        fix | why)
+            ASK_LAST_COMMAND=$__ask_last_command ASK_LAST_STATUS=$__ask_last_status \
+                command ask "$@" ;;
+        *) command ask "$@" ;;
+    esac
+}
+"#;
+
+const ZSH: &str = r#"# ask shell integration for zsh. Add to ~/.zshrc:
+#   eval "$(ask init zsh)"
+__ask_command_line() {
+    [[ -n $BUFFER ]] || return
+    local command
+    zle -R "ask: thinking..."
+    if command=$(ask --print-command -- "$BUFFER" </dev/null); then
+        BUFFER=$command
+        CURSOR=${#BUFFER}
+    fi
+    zle reset-prompt
+}
+zle -N __ask_command_line
+bindkey -M emacs '^G' __ask_command_line
+bindkey -M viins '^G' __ask_command_line
+
+__ask_preexec() {
+    __ask_command=$1
+}
+__ask_record_status() {
+    local exit_status=$?
+    if [[ -n $__ask_command && $__ask_command != (ask\ fix|ask\ why)* ]]; then
+        typeset -g __ask_last_command=$__ask_command __ask_last_status=$exit_status
+    fi
+    __ask_command=
+    return $exit_status
+}
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec __ask_preexec
+# First, so that it sees the command's status
+precmd_functions=(__ask_record_status ${precmd_functions:#__ask_record_status})
+
+ask() {
+    if [[ $1 == (fix|why) ]]; then
+        ASK_LAST_COMMAND=$__ask_last_command ASK_LAST_STATUS=$__ask_last_status \
+            command ask "$@"
+    else
+        command ask "$@"
+    fi
+}
+"#;
+
+const FISH: &str = r#"# ask shell integration for fish. Add to ~/.config/fish/config.fish:
+#   ask init fish | source
+function __ask_command_line
+    set -l line (commandline | string collect)
+    test -n "$line"; or return
+    set -l command (ask --print-command -- "$line" </dev/null)
+    and commandline --replace -- "$command"
+    commandline -f repaint
+end
+bind \cg __ask_command_line
+bind -M insert \cg __ask_command_line
+
+function __ask_record_status --on-event fish_postexec
+    set -l exit_status $status
+    string match -qr '^\s*$|^ask (fix|why)\b' -- "$argv"; and return
+    set -g __ask_last_command "$argv"
+    set -g __ask_last_status $exit_status
+end
+
+function ask
+    if contains -- "$argv[1]" fix why
+        env ASK_LAST_COMMAND="$__ask_last_command" ASK_LAST_STATUS="$__ask_last_status" \
+            ask $argv
+    else
+        command ask $argv
+    end
+end
+"#;
+
+/// `ask init [shell]`: print the key binding snippet for a shell, by
+/// default the one in `$SHELL`
+pub fn handle(args: &str) -> Result<()> {
+    let shell = match args.trim() {
+        "" => current_shell().unwrap_or_default(),
+        shell => shell.to_lowercase(),
+    };
+    print!("{}", snippet(&shell)?);
+    Ok(())
+}
+
+fn snippet(shell: &str) -> Result<&'static str> {
+    match shell {
+        "bash" => Ok(BASH),
+        "zsh" => Ok(ZSH),
+        "fish" => Ok(FISH),
+        "" => Err(AskError::Generic(USAGE.to_string())),
+        other => Err(AskError::Generic(format!(
+            "Unsupported shell: {}\n{}",
+            other, USAGE
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippets_use_print_command() {
+        for shell in ["bash", "zsh", "fish"] {
+            let snippet = snippet(shell).unwrap();
+            assert!(snippet.contains("ask --print-command -- "));
+            assert!(snippet.contains(&format!("ask init {}", shell)));
+            assert!(snippet.contains("ASK_LAST_STATUS"));
+            assert!(!snippet.contains("export") && !snippet.contains("set -gx"));
+        }
+        assert!(snippet("tcsh").is_err());
+    }
+}
@@ -1,6 +1,55 @@
 pub mod ai;
+pub mod code;
 pub mod config;
+pub mod exec;
 pub mod explain;
+pub mod fix;
 pub mod howto;
+pub mod init;
+pub mod models;
+pub mod profile;
 pub mod prompt;
+pub mod session;
 pub mod system;
+pub mod usage;
+
+use crate::attachment::Attachment;
+use crate::config::Config;
+use crate::error::Result;
+use crate::intent::{detect_intent, Intent};
+use crate::output::Output;
+use crate::session::Session;
+
+/// Detect the intent of `query` and route it to the matching handler.
+/// Questions with attachments always go to the AI, as context for the
+/// question or, for `ask fix`, as the output to diagnose.
+pub async fn dispatch(
+    query: &str,
+    attachments: &[Attachment],
+    config: &mut Config,
+    session: Option<&mut Session>,
+    output: &Output,
+) -> Result<()> {
+    let intent = detect_intent(query);
+    if !attachments.is_empty() && !matches!(intent, Intent::Fix) {
+        return ai::handle(query, attachments, None, config, session, output).await;
+    }
+
+    match intent {
+        Intent::Config(args) => config::handle(&args, config, output).await?,
+        Intent::Prompt(args) => prompt::handle(&args)?,
+        Intent::Session(args) => session::handle(&args)?,
+        Intent::Models(args) => models::handle(&args, config, output).await?,
+        Intent::Usage(args) => usage::handle(&args, output)?,
+        Intent::Init(args) => init::handle(&args)?,
+        Intent::System(args) => system::handle(&args, output)?,
+        Intent::SystemQuery(q) => system::handle_query(&q, output)?,
+        Intent::Howto(q) => howto::handle(&q, config, output).await?,
+        Intent::Explain(q) => explain::handle(&q, output)?,
+        Intent::Exec(task) => exec::handle(&task, config).await?,
+        Intent::Fix => fix::handle(attachments, config, session, output).await?,
+        Intent::Ai(q) => ai::handle(&q, &[], None, config, session, output).await?,
+    }
+
+    Ok(())
+}
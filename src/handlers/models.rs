@@ -0,0 +1,217 @@
+use crate::config::Config;
+use crate::error::{AskError, Result};
+use crate::handlers::ai;
+use crate::output::Output;
+use crate::providers::{create_provider, ProviderType};
+use colored::Colorize;
+use serde::Serialize;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: ask models [--provider <provider>]";
+
+/// How long `ask config model=...` waits for the model list
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// JSON document for `ask models`
+#[derive(Serialize)]
+struct ModelList<'a> {
+    provider: String,
+    current_model: String,
+    models: &'a [String],
+}
+
+/// `ask models [--provider X]`
+pub async fn handle(args: &str, config: &Config, output: &Output) -> Result<()> {
+    let provider = parse_provider(args)?;
+    let config = match provider {
+        Some(provider) if provider != config.provider => config.for_provider(provider),
+        _ => config.clone(),
+    };
+    // Listing models sends the key too
+    ai::check_provider(&config, None)?;
+
+    let models = fetch(&config).await?;
+    let current = config.effective_model();
+    if output.json {
+        return output.print_json(&ModelList {
+            provider: config.provider.to_string(),
+            current_model: current,
+            models: &models,
+        });
+    }
+
+    if models.is_empty() {
+        println!("{} lists no models.", config.provider);
+        return Ok(());
+    }
+    println!("Models for {}:", config.provider);
+    for model in &models {
+        if is_same_model(config.provider, model, &current) {
+            println!("  {} {}", model.green(), "(current)".dimmed());
+        } else {
+            println!("  {}", model);
+        }
+    }
+    Ok(())
+}
+
+/// After `ask config model=...`, warn if the provider does not list the
+/// model. The setting is kept either way: providers accept aliases they do
+/// not list, and the list may be unreachable.
+pub async fn check_model(config: &Config, model: &str) {
+    if model.is_empty() || ai::check_provider_quietly(config).is_err() {
+        return;
+    }
+    let Ok(Ok(models)) = tokio::time::timeout(CHECK_TIMEOUT, fetch(config)).await else {
+        return;
+    };
+    if models.is_empty()
+        || models
+            .iter()
+            .any(|m| is_same_model(config.provider, m, model))
+    {
+        return;
+    }
+
+    eprintln!(
+        "{} {} does not list a model named '{}'",
+        "Warning:".yellow(),
+        config.provider,
+        model
+    );
+    let close = suggestions(model, &models);
+    if !close.is_empty() {
+        eprintln!("Did you mean: {}", close.join(", "));
+    }
+    eprintln!("See all models with: {}", "ask models".green());
+}
+
+async fn fetch(config: &Config) -> Result<Vec<String>> {
+    let mut models = create_provider(config.provider)
+        .list_models(&config.provider_config(""))
+        .await?;
+    models.sort();
+    models.dedup();
+    Ok(models)
+}
+
+/// `--provider X`, `--provider=X` or a bare `X`
+fn parse_provider(args: &str) -> Result<Option<ProviderType>> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let name = match words.as_slice() {
+        [] => return Ok(None),
+        ["--provider" | "-p", name] => *name,
+        [word] => word.strip_prefix("--provider=").unwrap_or(word),
+        _ => return Err(AskError::Generic(USAGE.to_string())),
+    };
+    let provider: ProviderType = name
+        .parse()
+        .map_err(|_| AskError::Generic(format!("Unknown provider '{}'\n{}", name, USAGE)))?;
+    Ok(Some(provider))
+}
+
+/// Whether `listed` names `model`; Ollama fills in the `latest` tag
+fn is_same_model(provider: ProviderType, listed: &str, model: &str) -> bool {
+    listed == model || (provider == ProviderType::Ollama && listed == format!("{}:latest", model))
+}
+
+/// Up to three listed models that look like what was meant by `model`
+fn suggestions(model: &str, models: &[String]) -> Vec<String> {
+    let wanted = model.to_lowercase();
+    let max_distance = (wanted.chars().count() / 4).max(2);
+    let mut close: Vec<(usize, &String)> = models
+        .iter()
+        .filter_map(|m| {
+            let candidate = m.to_lowercase();
+            let distance = edit_distance(&wanted, &candidate);
+            if distance <= max_distance {
+                Some((distance, m))
+            } else if candidate.contains(&wanted) || wanted.contains(&candidate) {
+                // Rank containment after near-misses, shorter names first
+                Some((max_distance + candidate.len().abs_diff(wanted.len()), m))
+            } else {
+                None
+            }
+        })
+        .collect();
+    close.sort();
+    close.into_iter().take(3).map(|(_, m)| m.clone()).collect()
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let models = names(&["gpt-4o", "gpt-4o-mini", "gpt-4-turbo", "o1", "whisper-1"]);
+        assert_eq!(suggestions("gpt4o", &models)[0], "gpt-4o");
+        assert_eq!(suggestions("gpt-4o-mni", &models)[0], "gpt-4o-mini");
+        assert_eq!(suggestions("mini", &models), names(&["gpt-4o-mini"]));
+        assert!(suggestions("claude-3-opus", &models).is_empty());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("gpt-4o", "gpt-4o"), 0);
+    }
+
+    #[test]
+    fn test_ollama_latest_tag() {
+        assert!(is_same_model(
+            ProviderType::Ollama,
+            "llama3.2:latest",
+            "llama3.2"
+        ));
+        assert!(!is_same_model(
+            ProviderType::OpenAI,
+            "gpt-4o:latest",
+            "gpt-4o"
+        ));
+        assert!(!is_same_model(
+            ProviderType::Ollama,
+            "llama3.2:1b",
+            "llama3.2"
+        ));
+    }
+
+    #[test]
+    fn test_parse_provider() {
+        assert_eq!(parse_provider("").unwrap(), None);
+        assert_eq!(
+            parse_provider("--provider groq").unwrap(),
+            Some(ProviderType::Groq)
+        );
+        assert_eq!(
+            parse_provider("--provider=ollama").unwrap(),
+            Some(ProviderType::Ollama)
+        );
+        assert_eq!(
+            parse_provider("gemini").unwrap(),
+            Some(ProviderType::Gemini)
+        );
+        assert!(parse_provider("--provider").is_err());
+        assert!(parse_provider("nope").is_err());
+    }
+}
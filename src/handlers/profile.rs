@@ -0,0 +1,182 @@
+use crate::config::{validate_profile_name, Config, Profile};
+use crate::credentials::{Credentials, KeyOwner, KeySource};
+use crate::error::{AskError, Result};
+use crate::handlers::config::{confirm_custom_provider, print_history_warning, prompt_secret};
+use crate::providers::{is_known_provider_url, ProviderType};
+use colored::Colorize;
+use std::io::{self, IsTerminal};
+
+const USAGE: &str = "Usage: ask config profile list\n       \
+                     ask config profile add <name> [provider=<p>] [model=<m>] [api_url=<url>] [api_key]\n       \
+                     ask config profile remove <name>";
+
+/// `ask config profile ...`
+pub fn handle(args: &str, config: &Config) -> Result<()> {
+    let mut words = args.split_whitespace();
+    let command = words.next().unwrap_or("list");
+    let name = words.next();
+    let rest: Vec<&str> = words.collect();
+
+    match (command, name) {
+        ("list" | "ls", None) => list(config),
+        ("add" | "set", Some(name)) => add(name, &rest),
+        ("remove" | "rm" | "delete", Some(name)) if rest.is_empty() => remove(name),
+        _ => Err(AskError::Generic(USAGE.to_string())),
+    }
+}
+
+fn list(config: &Config) -> Result<()> {
+    if config.profiles.is_empty() {
+        println!("No profiles configured.");
+        println!("Add one with: ask config profile add <name> provider=<provider> model=<model>");
+        return Ok(());
+    }
+
+    let credentials = Credentials::load()?;
+    for (name, profile) in &config.profiles {
+        let mut tags = Vec::new();
+        if config.default_profile.as_deref() == Some(name.as_str()) {
+            tags.push("default");
+        }
+        if config.active_profile.as_deref() == Some(name.as_str()) {
+            tags.push("active");
+        }
+        if credentials
+            .source(&KeyOwner::Profile(name.clone()))
+            .is_some()
+        {
+            tags.push("key stored");
+        }
+
+        println!(
+            "  {:<16} {}{}",
+            name.green(),
+            describe(profile),
+            if tags.is_empty() {
+                String::new()
+            } else {
+                format!("  ({})", tags.join(", "))
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Create profile `name` or update its settings from `key=value` words.
+/// A bare `api_key` word prompts for the key.
+fn add(name: &str, assignments: &[&str]) -> Result<()> {
+    validate_profile_name(name)?;
+    let mut file = Config::load_file()?;
+    let is_new = !file.profiles.contains_key(name);
+    let mut profile = file.profiles.get(name).cloned().unwrap_or_default();
+    let mut api_key = None;
+    let mut prompt_for_key = false;
+
+    for assignment in assignments {
+        match assignment.split_once('=') {
+            Some(("api_key", value)) => {
+                print_history_warning();
+                api_key = Some(value.to_string());
+            }
+            Some((key, value)) => profile.set(key, value)?,
+            None if *assignment == "api_key" => prompt_for_key = true,
+            None => {
+                return Err(AskError::Generic(format!(
+                    "Expected key=value, got '{}'\n{}",
+                    assignment, USAGE
+                )))
+            }
+        }
+    }
+
+    let custom_url = profile
+        .api_url
+        .as_deref()
+        .filter(|url| !is_known_provider_url(url));
+    if profile.provider == Some(ProviderType::Custom) || custom_url.is_some() {
+        let (key, value) = match custom_url {
+            Some(url) => ("api_url", url.to_string()),
+            None => ("provider", "custom".to_string()),
+        };
+        if !confirm_custom_provider(key, &value)? {
+            println!("{}", "Configuration cancelled.".yellow());
+            return Ok(());
+        }
+        profile.custom_provider_confirmed = true;
+    }
+
+    let owner = KeyOwner::Profile(name.to_string());
+    let mut credentials = Credentials::load()?;
+    let needs_key = profile.provider.unwrap_or(file.provider).requires_api_key()
+        && credentials.source(&owner).is_none();
+    if api_key.is_none() && (prompt_for_key || (needs_key && io::stdin().is_terminal())) {
+        let env_var = profile.provider.unwrap_or(file.provider).env_var_name();
+        println!(
+            "API key for profile {} (leave empty to use {})",
+            name, env_var
+        );
+        api_key = Some(prompt_secret("api_key")?);
+    }
+
+    let summary = describe(&profile);
+    file.profiles.insert(name.to_string(), profile);
+    file.save()?;
+    if let Some(key) = api_key.filter(|key| !key.is_empty()) {
+        if let Some(previous) = credentials.set_source(&owner, Some(KeySource::Stored(key))) {
+            previous.forget(&owner);
+        }
+        credentials.save()?;
+    }
+
+    println!(
+        "{} profile {}: {}",
+        if is_new { "Added" } else { "Updated" },
+        name.green(),
+        summary
+    );
+    if file.default_profile.is_none() {
+        println!("Use it with: ask --profile {} <query>", name);
+        println!(
+            "Or make it the default: ask config default_profile={}",
+            name
+        );
+    }
+    Ok(())
+}
+
+fn remove(name: &str) -> Result<()> {
+    let mut file = Config::load_file()?;
+    if file.profiles.remove(name).is_none() {
+        return Err(AskError::ProfileNotFound(name.to_string()));
+    }
+    if file.default_profile.as_deref() == Some(name) {
+        file.default_profile = None;
+    }
+    file.save()?;
+
+    let owner = KeyOwner::Profile(name.to_string());
+    let mut credentials = Credentials::load()?;
+    if let Some(previous) = credentials.set_source(&owner, None) {
+        previous.forget(&owner);
+        credentials.save()?;
+    }
+
+    println!("Removed profile {}", name.green());
+    Ok(())
+}
+
+/// One-line summary such as "openai / gpt-4o"
+fn describe(profile: &Profile) -> String {
+    let provider = profile
+        .provider
+        .map(|p| p.to_string())
+        .unwrap_or_else(|| "(default provider)".to_string());
+    let mut summary = match &profile.model {
+        Some(model) => format!("{} / {}", provider, model),
+        None => provider,
+    };
+    if let Some(url) = &profile.api_url {
+        summary.push_str(&format!(" at {}", url));
+    }
+    summary
+}
@@ -1,6 +1,8 @@
-use crate::error::Result;
+use crate::error::{AskError, Result};
 use std::io::{self, Write};
 
+/// `ask prompt <question>`: succeed if the user answers yes, for scripts
+/// such as `ask prompt continue? && make install`
 pub fn handle(args: &str) -> Result<()> {
     print!("{} [y/N]: ", args);
     io::stdout().flush()?;
@@ -11,15 +13,15 @@ pub fn handle(args: &str) -> Result<()> {
             let response = input.trim().to_lowercase();
             if response == "y" || response == "yes" {
                 println!("Yes");
-                std::process::exit(0);
+                Ok(())
             } else {
                 println!("No");
-                std::process::exit(1);
+                Err(AskError::Declined)
             }
         }
         Err(_) => {
             println!("\nAborted");
-            std::process::exit(1);
+            Err(AskError::Declined)
         }
     }
 }
@@ -0,0 +1,55 @@
+use crate::error::{AskError, Result};
+use crate::providers::Role;
+use crate::session::{self, format_age, Session};
+use colored::Colorize;
+
+pub fn handle(args: &str) -> Result<()> {
+    let args = args.trim();
+    let (command, name) = match args.split_once(char::is_whitespace) {
+        Some((command, name)) => (command, name.trim()),
+        None => (args, ""),
+    };
+
+    match (command, name) {
+        ("" | "list" | "ls", _) => list(),
+        ("show", name) if !name.is_empty() => show(name),
+        ("delete" | "rm", name) if !name.is_empty() => {
+            session::delete(name)?;
+            println!("Deleted session {}", name.green());
+            Ok(())
+        }
+        _ => Err(AskError::Generic(
+            "Usage: ask session list | show <name> | delete <name>".to_string(),
+        )),
+    }
+}
+
+fn list() -> Result<()> {
+    let sessions = session::list()?;
+    if sessions.is_empty() {
+        println!("No saved sessions.");
+        println!("Start one with: ask --session <name> <query>");
+        return Ok(());
+    }
+
+    for s in sessions {
+        println!(
+            "  {:<20} {:>4} turns   {}",
+            s.name.green(),
+            s.turns(),
+            format_age(s.updated)
+        );
+    }
+    Ok(())
+}
+
+fn show(name: &str) -> Result<()> {
+    let session = Session::load(name)?;
+    for message in &session.messages {
+        match message.role {
+            Role::User => println!("{} {}", ">".cyan().bold(), message.content.bold()),
+            Role::Assistant => println!("{}\n", message.content),
+        }
+    }
+    Ok(())
+}
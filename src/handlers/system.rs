@@ -1,91 +1,226 @@
 use crate::error::{AskError, Result};
 use crate::intent::SystemResource;
+use crate::output::Output;
+#[cfg(target_os = "linux")]
+use crate::sysinfo::{self, Report};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Serialize;
 use std::process::Command;
 
+/// JSON document for a system query answered by an external command
+#[derive(Serialize)]
+struct CommandOutput<'a> {
+    query: &'a str,
+    command: String,
+    output: String,
+}
+
+/// JSON document for a system query answered natively
+#[cfg(target_os = "linux")]
+#[derive(Serialize)]
+struct SystemReport<'a, T> {
+    query: &'a str,
+    resource: &'a str,
+    data: &'a T,
+}
+
 static PORT_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)port\s*(\d+)").unwrap());
 
-pub fn handle(args: &str) -> Result<()> {
+pub fn handle(args: &str, output: &Output) -> Result<()> {
+    let run = Runner {
+        query: args.trim(),
+        output,
+    };
     let resource = SystemResource::from_str(args.trim())
         .ok_or_else(|| AskError::UnknownResource(args.to_string()))?;
 
     match resource {
-        SystemResource::Disk => run_command("df", &["-h"]),
+        SystemResource::Disk => {
+            #[cfg(target_os = "linux")]
+            if let Ok(filesystems) = sysinfo::filesystems() {
+                return run.report("disk", &filesystems);
+            }
+            run.command("df", &["-h"])
+        }
         SystemResource::Memory => {
             #[cfg(target_os = "macos")]
-            return run_command("vm_stat", &[]);
+            return run.command("vm_stat", &[]);
             #[cfg(target_os = "linux")]
-            return run_command("free", &["-h"]);
+            return match sysinfo::memory() {
+                Ok(memory) => run.report("memory", &memory),
+                Err(_) => run.command("free", &["-h"]),
+            };
             #[cfg(not(any(target_os = "macos", target_os = "linux")))]
             return Err(AskError::Generic("Unsupported platform".to_string()));
         }
         SystemResource::Cpu => {
             #[cfg(target_os = "macos")]
-            return run_command("sysctl", &["-n", "machdep.cpu.brand_string"]);
+            return run.command("sysctl", &["-n", "machdep.cpu.brand_string"]);
             #[cfg(target_os = "linux")]
-            return run_command("lscpu", &[]);
+            return match sysinfo::cpu() {
+                Ok(cpu) => run.report("cpu", &cpu),
+                Err(_) => run.command("lscpu", &[]),
+            };
             #[cfg(not(any(target_os = "macos", target_os = "linux")))]
             return Err(AskError::Generic("Unsupported platform".to_string()));
         }
         SystemResource::Ports => {
             #[cfg(target_os = "macos")]
-            return run_command("netstat", &["-an"]);
+            return run.command("netstat", &["-an"]);
             #[cfg(target_os = "linux")]
-            return run_command_or_fallback("ss", &["-tulpn"], "netstat", &["-an"]);
+            return match sysinfo::listening_sockets() {
+                Ok(sockets) => run.report("ports", &sockets),
+                Err(_) => run.command_or_fallback("ss", &["-tulpn"], "netstat", &["-an"]),
+            };
             #[cfg(not(any(target_os = "macos", target_os = "linux")))]
-            return run_command("netstat", &["-an"]);
+            return run.command("netstat", &["-an"]);
+        }
+        SystemResource::Uptime => {
+            #[cfg(target_os = "linux")]
+            if let Ok(uptime) = sysinfo::uptime() {
+                return run.report("uptime", &uptime);
+            }
+            run.command("uptime", &[])
+        }
+        SystemResource::Os => {
+            #[cfg(target_os = "linux")]
+            if let Ok(os) = sysinfo::os() {
+                return run.report("os", &os);
+            }
+            run.command("uname", &["-a"])
         }
-        SystemResource::Uptime => run_command("uptime", &[]),
-        SystemResource::Os => run_command("uname", &["-a"]),
     }
 }
 
-pub fn handle_query(query: &str) -> Result<()> {
+pub fn handle_query(query: &str, output: &Output) -> Result<()> {
+    let run = Runner { query, output };
     let q = query.to_lowercase();
 
     if q.contains("port") {
         if let Some(caps) = PORT_PATTERN.captures(&q) {
             let port = caps.get(1).map(|m| m.as_str()).unwrap_or("0");
+            #[cfg(target_os = "linux")]
+            if let Some(sockets) = port
+                .parse()
+                .ok()
+                .and_then(|port| sysinfo::sockets_on_port(port).ok())
+            {
+                return run.report("port", &sockets);
+            }
             #[cfg(target_os = "macos")]
-            return run_command("lsof", &["-i", &format!(":{}", port)]);
+            return run.command("lsof", &["-i", &format!(":{}", port)]);
             #[cfg(target_os = "linux")]
-            return run_command_or_fallback(
+            return run.command_or_fallback(
                 "lsof",
                 &["-i", &format!(":{}", port)],
                 "ss",
                 &["-tlpn", &format!("sport = :{}", port)],
             );
             #[cfg(not(any(target_os = "macos", target_os = "linux")))]
-            return run_command("netstat", &["-an"]);
+            return run.command("netstat", &["-an"]);
         } else {
-            return handle("ports");
+            return handle("ports", output);
         }
     }
 
     if q.contains("memory") || q.contains("ram") {
-        return handle("memory");
+        return handle("memory", output);
     }
 
     if q.contains("disk") || q.contains("space") {
-        return handle("disk");
+        return handle("disk", output);
     }
 
     if q.contains("cpu") {
         #[cfg(target_os = "macos")]
-        return run_command("ps", &["aux", "-r"]);
+        return run.command("ps", &["aux", "-r"]);
         #[cfg(target_os = "linux")]
-        return run_command("ps", &["aux", "--sort=-%cpu"]);
+        return run.command("ps", &["aux", "--sort=-%cpu"]);
         #[cfg(not(any(target_os = "macos", target_os = "linux")))]
-        return run_command("ps", &["aux"]);
+        return run.command("ps", &["aux"]);
     }
 
     Err(AskError::UnknownQuery(query.to_string()))
 }
 
-fn run_command(cmd: &str, args: &[&str]) -> Result<()> {
-    let status = Command::new(cmd).args(args).status()?;
+/// Presents system information, either straight to the terminal or as a
+/// JSON document
+struct Runner<'a> {
+    query: &'a str,
+    output: &'a Output,
+}
 
+impl Runner<'_> {
+    /// Show natively collected data as a table or JSON
+    #[cfg(target_os = "linux")]
+    fn report<T: Report>(&self, resource: &str, data: &T) -> Result<()> {
+        if self.output.json {
+            return self.output.print_json(&SystemReport {
+                query: self.query,
+                resource,
+                data,
+            });
+        }
+        print!("{}", data.table());
+        Ok(())
+    }
+
+    fn command(&self, cmd: &str, args: &[&str]) -> Result<()> {
+        if self.output.json {
+            let out = Command::new(cmd).args(args).output()?;
+            check_status(cmd, out.status)?;
+            return self.output.print_json(&CommandOutput {
+                query: self.query,
+                command: std::iter::once(cmd)
+                    .chain(args.iter().copied())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                output: String::from_utf8_lossy(&out.stdout).to_string(),
+            });
+        }
+
+        let status = Command::new(cmd).args(args).status()?;
+        check_status(cmd, status)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn command_or_fallback(
+        &self,
+        cmd1: &str,
+        args1: &[&str],
+        cmd2: &str,
+        args2: &[&str],
+    ) -> Result<()> {
+        use std::process::Stdio;
+
+        // Try first command
+        if Command::new(cmd1)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .is_ok()
+        {
+            self.command(cmd1, args1)
+        } else {
+            self.command(cmd2, args2)
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[allow(dead_code)]
+    fn command_or_fallback(
+        &self,
+        cmd1: &str,
+        args1: &[&str],
+        _cmd2: &str,
+        _args2: &[&str],
+    ) -> Result<()> {
+        self.command(cmd1, args1)
+    }
+}
+
+fn check_status(cmd: &str, status: std::process::ExitStatus) -> Result<()> {
     if !status.success() {
         return Err(AskError::Generic(format!(
             "Command {} exited with status {}",
@@ -95,26 +230,3 @@ fn run_command(cmd: &str, args: &[&str]) -> Result<()> {
     }
     Ok(())
 }
-
-#[cfg(target_os = "linux")]
-fn run_command_or_fallback(cmd1: &str, args1: &[&str], cmd2: &str, args2: &[&str]) -> Result<()> {
-    use std::process::Stdio;
-
-    // Try first command
-    if Command::new(cmd1)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .is_ok()
-    {
-        run_command(cmd1, args1)
-    } else {
-        run_command(cmd2, args2)
-    }
-}
-
-#[cfg(not(target_os = "linux"))]
-#[allow(dead_code)]
-fn run_command_or_fallback(cmd1: &str, args1: &[&str], _cmd2: &str, _args2: &[&str]) -> Result<()> {
-    run_command(cmd1, args1)
-}
@@ -0,0 +1,185 @@
+use crate::error::{AskError, Result};
+use crate::output::Output;
+use crate::session::now;
+use crate::usage::{self, format_cost, Record};
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const USAGE: &str = "Usage: ask usage [day|week|month|all]";
+
+/// Tokens and cost for one provider and model
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Totals {
+    requests: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    cost_usd: f64,
+    /// Answers whose model has no known price, left out of `cost_usd`
+    unpriced: u64,
+}
+
+#[derive(Serialize)]
+struct ModelTotals<'a> {
+    provider: String,
+    model: &'a str,
+    #[serde(flatten)]
+    totals: &'a Totals,
+}
+
+/// JSON document for `ask usage`
+#[derive(Serialize)]
+struct UsageReport<'a> {
+    period: &'a str,
+    /// Start of the period in seconds since the Unix epoch
+    since: Option<u64>,
+    total: Totals,
+    models: Vec<ModelTotals<'a>>,
+}
+
+impl Totals {
+    fn add(&mut self, record: &Record) {
+        self.requests += 1;
+        self.input_tokens += u64::from(record.input_tokens.unwrap_or(0));
+        self.output_tokens += u64::from(record.output_tokens.unwrap_or(0));
+        match record.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced += 1,
+        }
+    }
+
+    fn cost(&self) -> String {
+        match (self.unpriced, self.requests) {
+            (0, _) => format_cost(self.cost_usd),
+            (unpriced, requests) if unpriced == requests => "-".to_string(),
+            _ => format!("{}+", format_cost(self.cost_usd)),
+        }
+    }
+}
+
+/// `ask usage [period]`: tokens and estimated cost from the usage ledger
+pub fn handle(args: &str, output: &Output) -> Result<()> {
+    let period = match args.trim().to_lowercase().as_str() {
+        "" => "all".to_string(),
+        period @ ("day" | "week" | "month" | "all") => period.to_string(),
+        _ => return Err(AskError::Generic(USAGE.to_string())),
+    };
+    let since = match period.as_str() {
+        "day" => Some(now().saturating_sub(24 * 3600)),
+        "week" => Some(now().saturating_sub(7 * 24 * 3600)),
+        "month" => Some(now().saturating_sub(30 * 24 * 3600)),
+        _ => None,
+    };
+
+    let records: Vec<Record> = usage::load()?
+        .into_iter()
+        .filter(|r| since.map_or(true, |since| r.timestamp >= since))
+        .collect();
+    let mut total = Totals::default();
+    let mut by_model: BTreeMap<(String, &str), Totals> = BTreeMap::new();
+    for record in &records {
+        total.add(record);
+        by_model
+            .entry((record.provider.to_string(), record.model.as_str()))
+            .or_default()
+            .add(record);
+    }
+
+    if output.json {
+        return output.print_json(&UsageReport {
+            period: &period,
+            since,
+            models: by_model
+                .iter()
+                .map(|((provider, model), totals)| ModelTotals {
+                    provider: provider.clone(),
+                    model,
+                    totals,
+                })
+                .collect(),
+            total,
+        });
+    }
+
+    let heading = match period.as_str() {
+        "day" => "last 24 hours",
+        "week" => "last 7 days",
+        "month" => "last 30 days",
+        _ => "all time",
+    };
+    if records.is_empty() {
+        println!("No usage recorded ({}).", heading);
+        return Ok(());
+    }
+
+    println!("Usage ({}):", heading);
+    let width = by_model
+        .keys()
+        .map(|(provider, model)| provider.len() + model.len() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(5);
+    println!(
+        "  {:<width$}  {:>8}  {:>10}  {:>10}  {:>10}",
+        "model", "answers", "input", "output", "cost"
+    );
+    for ((provider, model), totals) in &by_model {
+        print_row(&format!("{}/{}", provider, model), totals, width);
+    }
+    if by_model.len() > 1 {
+        print_row("total", &total, width);
+    }
+    if total.unpriced > 0 {
+        println!();
+        println!(
+            "{} Some models have no known price; set one with: ask config price.<model>=<input>,<output>",
+            "Note:".yellow()
+        );
+    }
+    Ok(())
+}
+
+fn print_row(label: &str, totals: &Totals, width: usize) {
+    println!(
+        "  {:<width$}  {:>8}  {:>10}  {:>10}  {:>10}",
+        label,
+        totals.requests,
+        totals.input_tokens,
+        totals.output_tokens,
+        totals.cost()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ProviderType;
+
+    fn record(input: u32, cost: Option<f64>) -> Record {
+        Record {
+            timestamp: 0,
+            provider: ProviderType::OpenAI,
+            model: "gpt-4o".to_string(),
+            input_tokens: Some(input),
+            output_tokens: None,
+            cost_usd: cost,
+        }
+    }
+
+    #[test]
+    fn test_totals() {
+        let mut totals = Totals::default();
+        totals.add(&record(100, Some(0.5)));
+        totals.add(&record(50, Some(0.25)));
+        assert_eq!(totals.requests, 2);
+        assert_eq!(totals.input_tokens, 150);
+        assert_eq!(totals.cost(), "$0.7500");
+
+        totals.add(&record(10, None));
+        assert_eq!(totals.cost(), "$0.7500+");
+
+        let mut unpriced = Totals::default();
+        unpriced.add(&record(10, None));
+        assert_eq!(unpriced.cost(), "-");
+    }
+}
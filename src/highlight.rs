@@ -0,0 +1,312 @@
+//! Small syntax highlighter for code blocks in answers.
+//!
+//! Colors keywords, strings, numbers and comments for the languages models
+//! use most. It works one line at a time, so strings and block comments
+//! spanning several lines are not tracked. Unknown languages are left plain.
+
+use colored::Colorize;
+
+struct Syntax {
+    keywords: &'static [&'static str],
+    /// Markers that start a comment running to the end of the line
+    comments: &'static [&'static str],
+    quotes: &'static [char],
+    /// Whether keywords match regardless of case, as in SQL
+    fold_case: bool,
+}
+
+const SHELL: Syntax = Syntax {
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "in", "function", "return", "export", "local", "sudo",
+    ],
+    comments: &["#"],
+    quotes: &['"', '\''],
+    fold_case: false,
+};
+
+const PYTHON: Syntax = Syntax {
+    keywords: &[
+        "def", "class", "return", "if", "elif", "else", "for", "while", "in", "not", "and", "or",
+        "import", "from", "as", "with", "try", "except", "finally", "raise", "pass", "break",
+        "continue", "lambda", "yield", "async", "await", "None", "True", "False", "is",
+    ],
+    comments: &["#"],
+    quotes: &['"', '\''],
+    fold_case: false,
+};
+
+const RUST: Syntax = Syntax {
+    keywords: &[
+        "fn", "let", "mut", "pub", "struct", "enum", "impl", "trait", "use", "mod", "match", "if",
+        "else", "for", "while", "loop", "in", "return", "async", "await", "move", "ref", "self",
+        "Self", "const", "static", "where", "type", "crate", "true", "false", "Some", "None", "Ok",
+        "Err",
+    ],
+    comments: &["//"],
+    // Single quotes are mostly lifetimes
+    quotes: &['"'],
+    fold_case: false,
+};
+
+const C_LIKE: Syntax = Syntax {
+    keywords: &[
+        "function",
+        "const",
+        "let",
+        "var",
+        "return",
+        "if",
+        "else",
+        "for",
+        "while",
+        "do",
+        "switch",
+        "case",
+        "break",
+        "continue",
+        "new",
+        "class",
+        "extends",
+        "import",
+        "export",
+        "from",
+        "async",
+        "await",
+        "try",
+        "catch",
+        "finally",
+        "throw",
+        "typeof",
+        "instanceof",
+        "true",
+        "false",
+        "null",
+        "undefined",
+        "this",
+        "func",
+        "package",
+        "type",
+        "struct",
+        "interface",
+        "go",
+        "defer",
+        "range",
+        "public",
+        "private",
+        "static",
+        "void",
+        "int",
+        "char",
+        "include",
+        "fn",
+        "val",
+        "nil",
+    ],
+    comments: &["//"],
+    quotes: &['"', '\'', '`'],
+    fold_case: false,
+};
+
+const RUBY: Syntax = Syntax {
+    keywords: &[
+        "def", "end", "class", "module", "if", "elsif", "else", "unless", "while", "do", "return",
+        "require", "yield", "nil", "true", "false", "self", "begin", "rescue",
+    ],
+    comments: &["#"],
+    quotes: &['"', '\''],
+    fold_case: false,
+};
+
+const SQL: Syntax = Syntax {
+    keywords: &[
+        "select", "from", "where", "insert", "into", "values", "update", "set", "delete", "create",
+        "table", "drop", "alter", "join", "left", "right", "inner", "outer", "on", "group", "by",
+        "order", "having", "limit", "and", "or", "not", "null", "as", "distinct", "index",
+    ],
+    comments: &["--"],
+    quotes: &['\''],
+    fold_case: true,
+};
+
+/// JSON, YAML and TOML: only literals and comments
+const DATA: Syntax = Syntax {
+    keywords: &["true", "false", "null"],
+    comments: &["#"],
+    quotes: &['"', '\''],
+    fold_case: false,
+};
+
+fn syntax(language: &str) -> Option<&'static Syntax> {
+    Some(match language.to_lowercase().as_str() {
+        "sh" | "bash" | "zsh" | "shell" | "console" | "fish" | "ksh" => &SHELL,
+        "python" | "py" | "python3" => &PYTHON,
+        "rust" | "rs" => &RUST,
+        "js" | "javascript" | "jsx" | "ts" | "typescript" | "tsx" | "go" | "golang" | "c" | "h"
+        | "cpp" | "c++" | "java" | "kotlin" | "kt" | "cs" | "csharp" | "swift" => &C_LIKE,
+        "ruby" | "rb" => &RUBY,
+        "sql" => &SQL,
+        "json" | "yaml" | "yml" | "toml" => &DATA,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Plain,
+    Keyword,
+    String,
+    Number,
+    Comment,
+}
+
+/// Highlight one line of code written in `language`
+pub fn line(code: &str, language: &str) -> String {
+    let Some(syntax) = syntax(language) else {
+        return code.to_string();
+    };
+    tokens(code, syntax)
+        .into_iter()
+        .map(|(kind, text)| match kind {
+            Kind::Plain => text,
+            Kind::Keyword => text.magenta().to_string(),
+            Kind::String => text.green().to_string(),
+            Kind::Number => text.cyan().to_string(),
+            Kind::Comment => text.dimmed().to_string(),
+        })
+        .collect()
+}
+
+/// Split `code` into runs of one kind
+fn tokens(code: &str, syntax: &Syntax) -> Vec<(Kind, String)> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens: Vec<(Kind, String)> = Vec::new();
+    let mut push = |kind: Kind, text: &[char]| match tokens.last_mut() {
+        Some((last, run)) if *last == kind => run.extend(text),
+        _ => tokens.push((kind, text.iter().collect())),
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let at_word_start = i == 0 || chars[i - 1].is_whitespace();
+        let is_comment = syntax.comments.iter().any(|marker| {
+            let marker: Vec<char> = marker.chars().collect();
+            // "#" only starts a shell comment at the start of a word ($#, ${#x})
+            chars[i..].starts_with(&marker) && (marker != ['#'] || at_word_start)
+        });
+        if is_comment {
+            push(Kind::Comment, &chars[i..]);
+            break;
+        }
+
+        if syntax.quotes.contains(&c) {
+            let end = closing_quote(&chars, i).unwrap_or(chars.len() - 1);
+            push(Kind::String, &chars[i..=end]);
+            i = end + 1;
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' {
+            let in_word = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+            // Dots only belong to numbers
+            let end = i + chars[i..]
+                .iter()
+                .take_while(|&&x| {
+                    x.is_alphanumeric() || x == '_' || (x == '.' && c.is_ascii_digit())
+                })
+                .count();
+            let word: String = chars[i..end].iter().collect();
+            let is_keyword = syntax.keywords.iter().any(|k| {
+                if syntax.fold_case {
+                    k.eq_ignore_ascii_case(&word)
+                } else {
+                    *k == word
+                }
+            });
+            let kind = if in_word {
+                Kind::Plain
+            } else if c.is_ascii_digit() {
+                Kind::Number
+            } else if is_keyword {
+                Kind::Keyword
+            } else {
+                Kind::Plain
+            };
+            push(kind, &chars[i..end]);
+            i = end;
+            continue;
+        }
+
+        push(Kind::Plain, &chars[i..=i]);
+        i += 1;
+    }
+    tokens
+}
+
+/// Index of the quote closing the string opened at `start`, skipping
+/// backslash escapes
+fn closing_quote(chars: &[char], start: usize) -> Option<usize> {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_keeps_text() {
+        colored::control::set_override(false);
+        for (code, language) in [
+            ("for f in *.log; do echo \"$f\"; done # all logs", "bash"),
+            ("echo ${#files[@]} items", "sh"),
+            ("let x = 1.5; // note", "rust"),
+            ("SELECT name FROM users WHERE id = 'a\\'b'", "sql"),
+            ("def f(x): return x  # ok", "python"),
+            ("plain text", "unknown"),
+            ("unterminated \"string", "js"),
+        ] {
+            assert_eq!(line(code, language), code);
+        }
+    }
+
+    #[test]
+    fn test_tokens() {
+        let kinds = tokens;
+        assert_eq!(
+            kinds("if true; then echo 'hi'; fi # done", &SHELL),
+            vec![
+                (Kind::Keyword, "if".to_string()),
+                (Kind::Plain, " true; ".to_string()),
+                (Kind::Keyword, "then".to_string()),
+                (Kind::Plain, " echo ".to_string()),
+                (Kind::String, "'hi'".to_string()),
+                (Kind::Plain, "; ".to_string()),
+                (Kind::Keyword, "fi".to_string()),
+                (Kind::Plain, " ".to_string()),
+                (Kind::Comment, "# done".to_string()),
+            ]
+        );
+        // "$#" is not a comment
+        assert!(kinds("echo $# args", &SHELL)
+            .iter()
+            .all(|(kind, _)| *kind == Kind::Plain));
+        assert_eq!(
+            kinds("x = 1.5 + v2", &PYTHON)[1],
+            (Kind::Number, "1.5".to_string())
+        );
+        assert_eq!(
+            kinds("select * FROM t", &SQL)[2],
+            (Kind::Keyword, "FROM".to_string())
+        );
+    }
+}
@@ -29,12 +29,16 @@ impl SystemResource {
 pub enum Intent {
     Config(String),
     Prompt(String),
+    Session(String),
+    Models(String),
+    Usage(String),
+    Init(String),
     System(String),
     SystemQuery(String),
     Howto(String),
     Explain(String),
-    #[allow(dead_code)]
     Exec(String),
+    Fix,
     Ai(String),
 }
 
@@ -48,6 +52,25 @@ static SYSTEM_QUERY_PATTERN: Lazy<Regex> =
 static EXPLAIN_PATTERN: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?i)^(explain|describe) ").unwrap());
 
+// Only the exact forms, so questions like "usage of awk" still reach the AI
+static USAGE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^usage(\s+(day|week|month|all))?$").unwrap());
+
+// Only the bare words; "why is the sky blue" is a question
+static FIX_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^(fix|why)$").unwrap());
+
+// Likewise, "init a git repository" is a question
+static INIT_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^init(\s+(bash|zsh|fish))?$").unwrap());
+
+// "session cookies in express" is a question too
+static SESSION_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^session(\s+(list|ls|show|delete|rm)(\s+\S+)?)?$").unwrap());
+
+// At most a provider, so "models of distributed consensus" is a question
+static MODELS_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^models(\s+(--provider(=|\s+)\S+|-p\s+\S+|\S+))?$").unwrap());
+
 static AI_QUESTION_PATTERN: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"(?i)^(who|when|where|why|what) ").unwrap());
 
@@ -63,6 +86,24 @@ pub fn detect_intent(query: &str) -> Intent {
     if q_lower.starts_with("prompt ") {
         return Intent::Prompt(q.get(7..).unwrap_or("").to_string());
     }
+    if SESSION_PATTERN.is_match(&q_lower) {
+        return Intent::Session(q.get(7..).map(|s| s.trim()).unwrap_or("").to_string());
+    }
+    if MODELS_PATTERN.is_match(&q_lower) {
+        return Intent::Models(q.get(6..).map(|s| s.trim()).unwrap_or("").to_string());
+    }
+    if USAGE_PATTERN.is_match(&q_lower) {
+        return Intent::Usage(q.get(5..).map(|s| s.trim()).unwrap_or("").to_string());
+    }
+    if FIX_PATTERN.is_match(&q_lower) {
+        return Intent::Fix;
+    }
+    if INIT_PATTERN.is_match(&q_lower) {
+        return Intent::Init(q.get(4..).map(|s| s.trim()).unwrap_or("").to_string());
+    }
+    if q_lower == "howto" || q_lower.starts_with("howto ") {
+        return Intent::Howto(q.get(5..).map(|s| s.trim()).unwrap_or("").to_string());
+    }
     if q_lower.starts_with("system ") {
         return Intent::System(q.get(7..).unwrap_or("").to_string());
     }
@@ -107,6 +148,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_models_intent() {
+        match detect_intent("models --provider groq") {
+            Intent::Models(args) => assert_eq!(args, "--provider groq"),
+            _ => panic!("Expected Models intent"),
+        }
+        match detect_intent("models") {
+            Intent::Models(args) => assert_eq!(args, ""),
+            _ => panic!("Expected Models intent"),
+        }
+        assert!(matches!(
+            detect_intent("models -p ollama"),
+            Intent::Models(_)
+        ));
+        assert!(matches!(
+            detect_intent("models of distributed consensus"),
+            Intent::Ai(_)
+        ));
+    }
+
+    #[test]
+    fn test_fix_intent() {
+        assert!(matches!(detect_intent("fix"), Intent::Fix));
+        assert!(matches!(detect_intent("Why"), Intent::Fix));
+        assert!(matches!(
+            detect_intent("why is the sky blue"),
+            Intent::Ai(_)
+        ));
+        assert!(matches!(detect_intent("fix my bike"), Intent::Ai(_)));
+    }
+
+    #[test]
+    fn test_init_intent() {
+        match detect_intent("init zsh") {
+            Intent::Init(args) => assert_eq!(args, "zsh"),
+            _ => panic!("Expected Init intent"),
+        }
+        match detect_intent("init") {
+            Intent::Init(args) => assert_eq!(args, ""),
+            _ => panic!("Expected Init intent"),
+        }
+        assert!(matches!(
+            detect_intent("init a git repository"),
+            Intent::Ai(_)
+        ));
+    }
+
+    #[test]
+    fn test_usage_intent() {
+        match detect_intent("usage week") {
+            Intent::Usage(args) => assert_eq!(args, "week"),
+            _ => panic!("Expected Usage intent"),
+        }
+        assert!(matches!(detect_intent("usage"), Intent::Usage(_)));
+        assert!(matches!(detect_intent("usage of awk"), Intent::Ai(_)));
+    }
+
     #[test]
     fn test_prompt_intent() {
         match detect_intent("prompt continue?") {
@@ -115,6 +213,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_session_intent() {
+        match detect_intent("session show work") {
+            Intent::Session(args) => assert_eq!(args, "show work"),
+            _ => panic!("Expected Session intent"),
+        }
+        match detect_intent("session") {
+            Intent::Session(args) => assert_eq!(args, ""),
+            _ => panic!("Expected Session intent"),
+        }
+        assert!(matches!(
+            detect_intent("sessions vs cookies"),
+            Intent::Ai(_)
+        ));
+        assert!(matches!(
+            detect_intent("session cookies in express"),
+            Intent::Ai(_)
+        ));
+    }
+
     #[test]
     fn test_system_intent() {
         match detect_intent("system disk") {
@@ -123,6 +241,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_exec_intent() {
+        match detect_intent("run list the ten largest files") {
+            Intent::Exec(task) => assert_eq!(task, "list the ten largest files"),
+            _ => panic!("Expected Exec intent"),
+        }
+        match detect_intent("exec show my ip address") {
+            Intent::Exec(task) => assert_eq!(task, "show my ip address"),
+            _ => panic!("Expected Exec intent"),
+        }
+    }
+
     #[test]
     fn test_howto_intent() {
         match detect_intent("how do I compress a folder") {
@@ -133,6 +263,10 @@ mod tests {
             Intent::Howto(q) => assert!(q.contains("list")),
             _ => panic!("Expected Howto intent"),
         }
+        match detect_intent("howto --list") {
+            Intent::Howto(q) => assert_eq!(q, "--list"),
+            _ => panic!("Expected Howto intent"),
+        }
     }
 
     #[test]
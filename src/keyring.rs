@@ -0,0 +1,127 @@
+//! API keys kept in an OS keyring instead of the credentials file.
+//!
+//! Both backends go through their command-line tools so no keyring library
+//! has to be linked in: `secret-tool` for the freedesktop Secret Service
+//! (GNOME Keyring, KWallet) and `keyctl` for the Linux kernel keyring. Keys
+//! are always passed on stdin, never as arguments.
+
+use crate::error::{AskError, Result};
+use crate::util::find_in_path;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Attribute value identifying our entries in the Secret Service
+const SERVICE: &str = "ask-cmd";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Keyring {
+    /// Freedesktop Secret Service via `secret-tool`
+    SecretService,
+    /// Linux kernel user keyring via `keyctl`; cleared on logout or reboot
+    Kernel,
+}
+
+impl Keyring {
+    pub const ALL: [Keyring; 2] = [Keyring::SecretService, Keyring::Kernel];
+
+    fn program(self) -> &'static str {
+        match self {
+            Keyring::SecretService => "secret-tool",
+            Keyring::Kernel => "keyctl",
+        }
+    }
+
+    /// Whether the tool for this keyring is installed
+    pub fn is_available(self) -> bool {
+        find_in_path(self.program()).is_some()
+    }
+
+    /// Save `key` as the entry for `account`, replacing any previous one
+    pub fn store(self, account: &str, key: &str) -> Result<()> {
+        let mut command = Command::new(self.program());
+        match self {
+            Keyring::SecretService => command
+                .args(["store", "--label"])
+                .arg(format!("ask API key ({})", account))
+                .args(["service", SERVICE, "account", account]),
+            Keyring::Kernel => command.args(["padd", "user", &description(account), "@u"]),
+        };
+        run(&mut command, Some(key)).map(|_| ())
+    }
+
+    pub fn lookup(self, account: &str) -> Result<String> {
+        let mut command = Command::new(self.program());
+        match self {
+            Keyring::SecretService => {
+                command.args(["lookup", "service", SERVICE, "account", account])
+            }
+            Keyring::Kernel => command.args(["pipe", &format!("%user:{}", description(account))]),
+        };
+        run(&mut command, None)
+    }
+
+    pub fn delete(self, account: &str) -> Result<()> {
+        let mut command = Command::new(self.program());
+        match self {
+            Keyring::SecretService => {
+                command.args(["clear", "service", SERVICE, "account", account])
+            }
+            Keyring::Kernel => {
+                command.args(["unlink", &format!("%user:{}", description(account)), "@u"])
+            }
+        };
+        run(&mut command, None).map(|_| ())
+    }
+}
+
+impl fmt::Display for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Keyring::SecretService => write!(f, "Secret Service"),
+            Keyring::Kernel => write!(f, "kernel keyring"),
+        }
+    }
+}
+
+/// Kernel keyring description for `account`
+fn description(account: &str) -> String {
+    format!("ask:{}", account)
+}
+
+/// Run `command` with `input` on stdin and return its trimmed stdout.
+/// Password prompts such as pinentry still reach the terminal directly.
+pub fn run(command: &mut Command, input: Option<&str>) -> Result<String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        // Never hand over our own stdin, which may be input to attach
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AskError::Generic(format!("Cannot run {}: {}", program, e)))?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AskError::Generic(format!(
+            "{} failed ({}){}",
+            program,
+            output.status,
+            match stderr.trim() {
+                "" => String::new(),
+                message => format!(": {}", message),
+            }
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -1,23 +1,61 @@
+mod attachment;
 mod config;
+mod context;
+mod credentials;
 mod error;
 mod handlers;
+mod highlight;
 mod intent;
+mod keyring;
+mod markdown;
+mod output;
+mod prompts;
 mod providers;
+mod recipes;
+mod render;
+mod repl;
+mod session;
+#[cfg(target_os = "linux")]
+mod sysinfo;
+mod usage;
+mod util;
 
+use attachment::Attachment;
 use clap::Parser;
-use colored::Colorize;
 use config::Config;
-use error::{AskError, Result};
+use error::Result;
+use handlers::code::{self, CodeAction};
 use intent::{detect_intent, Intent};
-use std::io::{self, BufRead, IsTerminal, Write};
+use output::Output;
+use session::Session;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::PathBuf;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const HELP_TEXT: &str = r#"ask - A multi-purpose CLI query tool
 
 Usage: ask <query>
-       ask -i              (interactive mode - avoids shell escaping issues)
+       ask -i              (interactive chat - avoids shell escaping issues)
        echo "query" | ask  (pipe mode)
+       ask --session <name> <query>  (continue a named conversation)
+       ask --continue <query>        (continue the most recent conversation)
+       ask session list|show <name>|delete <name>
+       ask models [--provider <name>]  (list a provider's models)
+       ask --json <query>            (machine-readable output)
+       ask --stats <query>           (show tokens, timing and cost)
+       ask --raw <query>             (plain text, no Markdown rendering)
+       ask --copy|--run N [<query>]  (copy or run code block N of the answer)
+       ask --save N <file> [<query>] (save code block N of the answer)
+       ask --print-command <task>    (print a shell command, don't run it)
+       ask --system <text> <query>   (use a different system prompt)
+       ask init bash|zsh|fish        (Ctrl-G turns the command line into a command)
+       ask fix                       (why did the last command fail?)
+       ask howto --list [tag]        (list the recipes behind local suggestions)
+       ask usage [day|week|month|all]  (tokens and cost so far)
+       ask --profile <name> <query>  (use a named provider profile)
+       ask -f <file> <query>         (attach a file as context)
+       cat error.log | ask <query>   (attach stdin as context)
 
 Examples:
     ask how do I compress a folder
@@ -25,7 +63,8 @@ Examples:
     ask system disk
     ask prompt continue with install
     ask explain grep
-    ask -i                 # then type: what is 2 + 2?
+    ask run find files larger than 100MB
+    ask -i                 # then type: what is 2 + 2? (/help for commands)
 
 Note: For queries with special characters (? & ! *), use quotes or interactive mode:
     ask "what is the capital of france?"
@@ -35,27 +74,66 @@ Note: For queries with special characters (? & ! *), use quotes or interactive m
 #[derive(Parser)]
 #[command(name = "ask", version = VERSION, about = "A multi-purpose CLI query tool")]
 struct Cli {
-    /// Run in interactive mode
+    /// Start an interactive chat session
     #[arg(short, long)]
     interactive: bool,
 
+    /// Continue (or start) a named conversation session
+    #[arg(short, long, value_name = "NAME")]
+    session: Option<String>,
+
+    /// Continue the most recently used conversation session
+    #[arg(short = 'c', long = "continue", conflicts_with = "session")]
+    continue_session: bool,
+
+    /// Use the provider settings of a named profile
+    #[arg(short, long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Print results as a JSON document
+    #[arg(long)]
+    json: bool,
+
+    /// Show token usage, timing and estimated cost after the answer
+    #[arg(long)]
+    stats: bool,
+
+    /// Print answers as plain text instead of rendering Markdown
+    #[arg(long)]
+    raw: bool,
+
+    /// Copy code block N of the answer to the clipboard
+    #[arg(long, value_name = "N", conflicts_with_all = ["run", "save", "interactive"])]
+    copy: Option<String>,
+
+    /// Run code block N of the answer in the shell
+    #[arg(long, value_name = "N", conflicts_with_all = ["save", "interactive"])]
+    run: Option<String>,
+
+    /// Save code block N of the answer to FILE
+    #[arg(long, num_args = 2, value_names = ["N", "FILE"], conflicts_with = "interactive")]
+    save: Vec<String>,
+
+    /// Print only a shell command for the query, without running it
+    #[arg(long, conflicts_with_all = ["interactive", "json", "copy", "run", "save"])]
+    print_command: bool,
+
+    /// Use TEXT as the system prompt for this question
+    #[arg(long, value_name = "TEXT")]
+    system: Option<String>,
+
+    /// Attach a file as context for the question (repeatable)
+    #[arg(short, long = "file", value_name = "PATH")]
+    files: Vec<PathBuf>,
+
     /// Query to process
     #[arg(trailing_var_arg = true)]
     query: Vec<String>,
 }
 
 fn get_query(cli: &Cli) -> Result<Option<String>> {
-    // Interactive mode
-    if cli.interactive {
-        print!("ask> ");
-        io::stdout().flush()?;
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        return Ok(Some(input.trim().to_string()));
-    }
-
     // Pipe mode - read from stdin if no args and stdin is not a tty
-    if cli.query.is_empty() && !io::stdin().is_terminal() {
+    if cli.query.is_empty() && cli.files.is_empty() && !io::stdin().is_terminal() {
         let stdin = io::stdin();
         let mut lines = Vec::new();
         for line in stdin.lock().lines() {
@@ -73,23 +151,55 @@ fn get_query(cli: &Cli) -> Result<Option<String>> {
     Ok(None)
 }
 
+/// The `--copy`, `--run` or `--save` follow-up, if one was given
+fn get_code_action(cli: &Cli) -> Result<Option<CodeAction>> {
+    Ok(if let Some(n) = &cli.copy {
+        Some(CodeAction::Copy(code::parse_number(n)?))
+    } else if let Some(n) = &cli.run {
+        Some(CodeAction::Run(code::parse_number(n)?))
+    } else if let [n, file] = cli.save.as_slice() {
+        Some(CodeAction::Save(
+            code::parse_number(n)?,
+            PathBuf::from(file),
+        ))
+    } else {
+        None
+    })
+}
+
+/// Collect `--file` attachments, plus stdin when it is a pipe or file given
+/// alongside a question given as arguments. Commands that read stdin
+/// themselves, such as `prompt` and `run`, never have it attached.
+fn get_attachments(cli: &Cli, query: &str, config: &Config) -> Result<Vec<Attachment>> {
+    let limit = config.max_attachment_bytes;
+    let mut attachments = cli
+        .files
+        .iter()
+        .map(|path| Attachment::from_file(path, limit))
+        .collect::<Result<Vec<_>>>()?;
+
+    let is_question = matches!(
+        detect_intent(query),
+        Intent::Ai(_) | Intent::Howto(_) | Intent::Explain(_) | Intent::Fix
+    );
+    if is_question && !cli.query.is_empty() && attachment::stdin_is_piped() {
+        let stdin = Attachment::from_reader("stdin", io::stdin().lock(), limit)?;
+        if !stdin.content.trim().is_empty() {
+            attachments.push(stdin);
+        }
+    }
+
+    for attachment in &attachments {
+        attachment.warn_if_truncated();
+    }
+    Ok(attachments)
+}
+
 #[tokio::main]
 async fn main() {
     if let Err(e) = run().await {
-        match e {
-            AskError::MissingApiKey { .. } => {
-                // Already printed helpful message
-                std::process::exit(1);
-            }
-            AskError::CustomProviderNotConfirmed => {
-                // Already printed helpful message
-                std::process::exit(1);
-            }
-            _ => {
-                eprintln!("{}: {}", "Error".red(), e);
-                std::process::exit(1);
-            }
-        }
+        e.report();
+        std::process::exit(e.exit_code());
     }
 }
 
@@ -108,32 +218,63 @@ async fn run() -> Result<()> {
     let cli = Cli::parse();
 
     // Load config
-    let mut config = Config::load()?;
+    let mut config = Config::load(cli.profile.as_deref())?;
+    config.system_prompt_override = cli.system.clone();
+    if !config.color {
+        colored::control::set_override(false);
+    }
+
+    // Conversation session, if requested
+    let mut session = if let Some(name) = &cli.session {
+        Some(Session::open(name)?)
+    } else if cli.continue_session {
+        Some(Session::open_latest()?)
+    } else {
+        None
+    };
+
+    let output = Output {
+        json: cli.json,
+        stats: cli.stats,
+        raw: cli.raw,
+        interactive: false,
+    };
+
+    // Interactive mode
+    if cli.interactive {
+        return repl::run(&mut config, session, &output).await;
+    }
+
+    // Without a question, follow-ups act on the last answer
+    let code_action = get_code_action(&cli)?;
+    if let (Some(action), true) = (&code_action, cli.query.is_empty()) {
+        return code::act(action, &config);
+    }
 
     // Get query
     let query = match get_query(&cli)? {
         Some(q) if !q.is_empty() => q,
+        _ if !cli.files.is_empty() => {
+            return Err(error::AskError::Generic(
+                "Attached files need a question, e.g. ask -f error.log why is this failing"
+                    .to_string(),
+            ));
+        }
         _ => {
             print!("{}", HELP_TEXT);
             return Ok(());
         }
     };
-
-    // Detect intent and route
-    let intent = detect_intent(&query);
-
-    match intent {
-        Intent::Config(args) => handlers::config::handle(&args, &mut config)?,
-        Intent::Prompt(args) => handlers::prompt::handle(&args)?,
-        Intent::System(args) => handlers::system::handle(&args)?,
-        Intent::SystemQuery(q) => handlers::system::handle_query(&q)?,
-        Intent::Howto(q) => handlers::howto::handle(&q, &config).await?,
-        Intent::Explain(q) => handlers::explain::handle(&q)?,
-        Intent::Exec(_) => {
-            println!("Exec command generation is not yet implemented");
-        }
-        Intent::Ai(q) => handlers::ai::handle(&q, &config).await?,
+    if cli.print_command {
+        return handlers::exec::print_command(&query, &config).await;
     }
+    let attachments = get_attachments(&cli, &query, &config)?;
 
-    Ok(())
+    if let Some(action) = &code_action {
+        // Act on this answer's blocks, never on an older answer's
+        code::remember(&[])?;
+        handlers::dispatch(&query, &attachments, &mut config, session.as_mut(), &output).await?;
+        return code::act(action, &config);
+    }
+    handlers::dispatch(&query, &attachments, &mut config, session.as_mut(), &output).await
 }
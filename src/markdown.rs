@@ -0,0 +1,477 @@
+//! Incremental Markdown rendering for streamed answers.
+//!
+//! Text arrives in fragments that can end anywhere, even inside `**`, so it
+//! is buffered until a line is complete and each line is then rendered on
+//! its own: headers, lists, quotes, rules and fenced code blocks, plus bold,
+//! italic, code spans and links inside a line. Styles come from `colored`,
+//! so with colors off the Markdown markers are still removed but no escape
+//! codes are written.
+//!
+//! Code blocks are numbered in their frames, `── [1] bash ───`, in the same
+//! order [`code_blocks`] finds them, so `ask --run 1` and friends can refer
+//! to them.
+
+use crate::highlight;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// Width of horizontal rules and code block frames
+const RULE_WIDTH: usize = 40;
+
+/// Renders Markdown written to it in arbitrary pieces
+#[derive(Default)]
+pub struct MarkdownWriter {
+    /// Start of a line whose end has not arrived yet
+    pending: String,
+    /// The fenced code block being written, if any
+    fence: Option<Fence>,
+    /// Code blocks opened so far
+    blocks: usize,
+}
+
+struct Fence {
+    /// Fence character, '`' or '~'
+    marker: char,
+    /// Length of the opening fence; the closing one must be at least as long
+    length: usize,
+    language: String,
+    /// Columns of indentation before the opening fence, removed from the
+    /// code lines too
+    indent: usize,
+}
+
+/// A fenced code block in an answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeBlock {
+    /// Info string after the opening fence, e.g. "bash"; may be empty
+    pub language: String,
+    pub code: String,
+}
+
+impl MarkdownWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render each line of `text` that is now complete, keeping the rest
+    pub fn write(&mut self, text: &str, out: &mut dyn Write) -> io::Result<()> {
+        self.pending.push_str(text);
+        while let Some(end) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=end).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            writeln!(out, "{}", self.render_line(line))?;
+        }
+        out.flush()
+    }
+
+    /// Render what is left of the last line, which has no newline
+    pub fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let line = std::mem::take(&mut self.pending);
+        if !line.is_empty() {
+            write!(out, "{}", self.render_line(&line))?;
+        }
+        self.fence = None;
+        out.flush()
+    }
+
+    fn render_line(&mut self, line: &str) -> String {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+
+        if let Some(fence) = &self.fence {
+            if is_closing_fence(trimmed, fence) {
+                self.fence = None;
+                return format!("{}{}", indent, rule(""));
+            }
+            return highlight::line(line, &fence.language);
+        }
+        if let Some(fence) = opening_fence(line) {
+            self.blocks += 1;
+            let label = format!("[{}] {}", self.blocks, fence.language);
+            let frame = rule(label.trim_end());
+            self.fence = Some(fence);
+            return format!("{}{}", indent, frame);
+        }
+
+        if let Some((level, title)) = heading(trimmed) {
+            let title = inline(title);
+            return match level {
+                1 => title.bold().underline().to_string(),
+                2 => title.bold().to_string(),
+                _ => title.bold().italic().to_string(),
+            };
+        }
+        if is_rule(trimmed) {
+            return rule("");
+        }
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            let quote = quote.strip_prefix(' ').unwrap_or(quote);
+            return format!("{}{}{}", indent, "│ ".dimmed(), inline(quote).italic());
+        }
+        if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|bullet| trimmed.strip_prefix(bullet))
+        {
+            return format!("{}• {}", indent, inline(item));
+        }
+        if let Some((number, item)) = numbered_item(trimmed) {
+            return format!("{}{} {}", indent, number.bold(), inline(item));
+        }
+        format!("{}{}", indent, inline(trimmed))
+    }
+}
+
+/// A dimmed horizontal line, labelled for code blocks
+fn rule(label: &str) -> String {
+    let line = if label.is_empty() {
+        "─".repeat(RULE_WIDTH)
+    } else {
+        let rest = RULE_WIDTH.saturating_sub(label.chars().count() + 4);
+        format!("── {} {}", label, "─".repeat(rest))
+    };
+    line.dimmed().to_string()
+}
+
+/// The fenced code blocks in `text`, in order. A block the answer never
+/// closed runs to the end.
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(Fence, Vec<&str>)> = None;
+    for line in text.lines() {
+        match &mut current {
+            Some((fence, lines)) => {
+                if is_closing_fence(line.trim_start(), fence) {
+                    let (fence, lines) = current.take().expect("inside a block");
+                    blocks.push(code_block(fence, lines));
+                } else {
+                    lines.push(unindent(line, fence.indent));
+                }
+            }
+            None => current = opening_fence(line).map(|fence| (fence, Vec::new())),
+        }
+    }
+    if let Some((fence, lines)) = current {
+        blocks.push(code_block(fence, lines));
+    }
+    blocks
+}
+
+fn code_block(fence: Fence, lines: Vec<&str>) -> CodeBlock {
+    let mut code = lines.join("\n");
+    if !code.is_empty() {
+        code.push('\n');
+    }
+    CodeBlock {
+        language: fence.language,
+        code,
+    }
+}
+
+/// `line` without up to `indent` leading spaces
+fn unindent(line: &str, indent: usize) -> &str {
+    let spaces = line.chars().take(indent).take_while(|&c| c == ' ').count();
+    &line[spaces..]
+}
+
+/// The fence opening a code block on `line`, if it is one
+fn opening_fence(line: &str) -> Option<Fence> {
+    let trimmed = line.trim_start_matches(' ');
+    let indent = line.len() - trimmed.len();
+    let line = trimmed;
+    let marker = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let length = line.chars().take_while(|&c| c == marker).count();
+    let info = &line[length..];
+    // "```js```" is an inline code span, not a fence
+    if length < 3 || (marker == '`' && info.contains('`')) {
+        return None;
+    }
+    Some(Fence {
+        marker,
+        length,
+        language: info.split_whitespace().next().unwrap_or("").to_string(),
+        indent,
+    })
+}
+
+fn is_closing_fence(line: &str, fence: &Fence) -> bool {
+    let length = line.chars().take_while(|&c| c == fence.marker).count();
+    length >= fence.length && line[length..].trim().is_empty()
+}
+
+/// `## Title` as (2, "Title"), with optional closing `#`s removed
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let title = line[level..].strip_prefix(' ')?;
+    Some((level, title.trim().trim_end_matches('#').trim_end()))
+}
+
+/// `---`, `***` or `___`, possibly with spaces in between
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|&c| c == marks[0])
+}
+
+/// `3. item` or `3) item` as ("3.", "item")
+fn numbered_item(line: &str) -> Option<(&str, &str)> {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let rest = &line[digits..];
+    if !(rest.starts_with(". ") || rest.starts_with(") ")) {
+        return None;
+    }
+    Some((&line[..digits + 1], &rest[2..]))
+}
+
+/// Style emphasis, code spans and links within one line
+fn inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                out.push(chars[i + 1]);
+                i += 2;
+            }
+            '`' => {
+                let n = run_length(&chars, i, '`');
+                match find_run(&chars, i + n, '`', n) {
+                    Some(end) => {
+                        let code: String = chars[i + n..end].iter().collect();
+                        out.push_str(&strip_padding(&code).yellow().to_string());
+                        i = end + n;
+                    }
+                    None => {
+                        out.extend(&chars[i..i + n]);
+                        i += n;
+                    }
+                }
+            }
+            '*' | '_' | '~' => {
+                let n = run_length(&chars, i, c);
+                match emphasis_end(&chars, i, n) {
+                    Some(end) => {
+                        let inner = inline(&chars[i + n..end].iter().collect::<String>());
+                        let styled = match (c, n) {
+                            ('~', _) => inner.strikethrough(),
+                            (_, 1) => inner.italic(),
+                            (_, 2) => inner.bold(),
+                            _ => inner.bold().italic(),
+                        };
+                        out.push_str(&styled.to_string());
+                        i = end + n;
+                    }
+                    None => {
+                        out.extend(&chars[i..i + n]);
+                        i += n;
+                    }
+                }
+            }
+            '[' => match link(&chars, i) {
+                Some((label, url, end)) => {
+                    let label = inline(&label);
+                    if label == url {
+                        out.push_str(&url.underline().to_string());
+                    } else {
+                        out.push_str(&format!("{} ({})", label.underline(), url.dimmed()));
+                    }
+                    i = end;
+                }
+                None => {
+                    out.push(c);
+                    i += 1;
+                }
+            },
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+fn run_length(chars: &[char], start: usize, c: char) -> usize {
+    chars[start..].iter().take_while(|&&x| x == c).count()
+}
+
+/// Start of the next run of exactly `n` `c`s at or after `from`
+fn find_run(chars: &[char], from: usize, c: char, n: usize) -> Option<usize> {
+    let mut j = from;
+    while j < chars.len() {
+        if chars[j] == c {
+            let m = run_length(chars, j, c);
+            if m == n {
+                return Some(j);
+            }
+            j += m;
+        } else {
+            j += 1;
+        }
+    }
+    None
+}
+
+/// Where the emphasis opened by the `n` delimiters at `start` closes, if it
+/// is emphasis at all. `_` only counts at word boundaries, so snake_case
+/// names stay intact, and `~` needs two.
+fn emphasis_end(chars: &[char], start: usize, n: usize) -> Option<usize> {
+    let c = chars[start];
+    if n > 3 || (c == '~' && n != 2) {
+        return None;
+    }
+    let is_word = |i: Option<&char>| i.is_some_and(|c| c.is_alphanumeric());
+    if chars.get(start + n).map_or(true, |c| c.is_whitespace())
+        || (c == '_' && start > 0 && is_word(chars.get(start - 1)))
+    {
+        return None;
+    }
+
+    let mut from = start + n;
+    while let Some(end) = find_run(chars, from, c, n) {
+        let closes = !chars[end - 1].is_whitespace()
+            && end > start + n
+            && !(c == '_' && is_word(chars.get(end + n)));
+        if closes {
+            return Some(end);
+        }
+        from = end + n;
+    }
+    None
+}
+
+/// `[label](url)` starting at `start`: the label, the URL and the index
+/// after the closing parenthesis
+fn link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let close = start + chars[start..].iter().position(|&c| c == ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = close + 2 + chars[close + 2..].iter().position(|&c| c == ')')?;
+    let url: String = chars[close + 2..end].iter().collect();
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((chars[start + 1..close].iter().collect(), url, end + 1))
+}
+
+/// Code spans may be padded with one space on each side, e.g. "`` `x` ``"
+fn strip_padding(code: &str) -> &str {
+    match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+        Some(inner) if !inner.trim().is_empty() => inner,
+        _ => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Render `chunks` as if streamed, without colors
+    fn render(chunks: &[&str]) -> String {
+        colored::control::set_override(false);
+        let mut writer = MarkdownWriter::new();
+        let mut out = Vec::new();
+        for chunk in chunks {
+            writer.write(chunk, &mut out).unwrap();
+        }
+        writer.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_inline_markers() {
+        assert_eq!(
+            render(&["Use **bold**, *it", "alic* and `ls -la`"]),
+            "Use bold, italic and ls -la"
+        );
+        assert_eq!(render(&["keep snake_case_names"]), "keep snake_case_names");
+        assert_eq!(render(&["2 * 3 * 4"]), "2 * 3 * 4");
+        assert_eq!(render(&["an \\*escaped\\* star"]), "an *escaped* star");
+        assert_eq!(render(&["~~old~~ new"]), "old new");
+        assert_eq!(render(&["unclosed **bold"]), "unclosed **bold");
+    }
+
+    #[test]
+    fn test_links() {
+        assert_eq!(
+            render(&["See [the docs](https://example.com/docs)."]),
+            "See the docs (https://example.com/docs)."
+        );
+        assert_eq!(render(&["[x] done"]), "[x] done");
+    }
+
+    #[test]
+    fn test_blocks() {
+        let text = "# Title\n## Steps\n- first\n  * nested\n3. third\n> quoted\n---\n";
+        assert_eq!(
+            render(&[text]),
+            format!(
+                "Title\nSteps\n• first\n  • nested\n3. third\n│ quoted\n{}\n",
+                "─".repeat(RULE_WIDTH)
+            )
+        );
+    }
+
+    #[test]
+    fn test_code_block() {
+        let rendered = render(&["Run:\n``", "`bash\necho **not bold**\n```\nDone"]);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "Run:");
+        assert!(lines[1].starts_with("── [1] bash ──"));
+        assert_eq!(lines[2], "echo **not bold**");
+        assert_eq!(lines[3], "─".repeat(RULE_WIDTH));
+        assert_eq!(lines[4], "Done");
+    }
+
+    #[test]
+    fn test_code_blocks_are_numbered() {
+        let rendered = render(&["```\nls\n```\n~~~python\nprint(1)\n~~~\n"]);
+        assert!(rendered.starts_with("── [1] ──"));
+        assert!(rendered.contains("── [2] python ──"));
+    }
+
+    #[test]
+    fn test_extract_code_blocks() {
+        let text = "Try:\n```bash\nls -la\ncd /tmp\n```\n1. Then:\n   ```\n   make\n     install\n   ```\n````md\n```\n````\n";
+        assert_eq!(
+            code_blocks(text),
+            vec![
+                CodeBlock {
+                    language: "bash".to_string(),
+                    code: "ls -la\ncd /tmp\n".to_string(),
+                },
+                CodeBlock {
+                    language: String::new(),
+                    code: "make\n  install\n".to_string(),
+                },
+                CodeBlock {
+                    language: "md".to_string(),
+                    code: "```\n".to_string(),
+                },
+            ]
+        );
+        assert!(code_blocks("Use `ls` here\n").is_empty());
+        // An answer cut off inside a block still has it
+        assert_eq!(code_blocks("```sh\necho hi")[0].code, "echo hi\n");
+    }
+
+    #[test]
+    fn test_partial_lines_wait_for_newline() {
+        colored::control::set_override(false);
+        let mut writer = MarkdownWriter::new();
+        let mut out = Vec::new();
+        writer.write("**bo", &mut out).unwrap();
+        assert!(out.is_empty());
+        writer.write("ld**\nnext", &mut out).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out), "bold\n");
+    }
+}
@@ -0,0 +1,24 @@
+use crate::error::Result;
+use serde::Serialize;
+
+/// How results are presented to the user
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Output {
+    /// Emit a single JSON document instead of human-readable text
+    pub json: bool,
+    /// Report tokens, timing and estimated cost after each answer
+    pub stats: bool,
+    /// Print answers as they arrive instead of rendering their Markdown
+    pub raw: bool,
+    /// Answers are read in interactive mode, where follow-ups are slash
+    /// commands rather than options
+    pub interactive: bool,
+}
+
+impl Output {
+    /// Print `value` as a pretty JSON document on stdout
+    pub fn print_json<T: Serialize>(&self, value: &T) -> Result<()> {
+        println!("{}", serde_json::to_string_pretty(value)?);
+        Ok(())
+    }
+}
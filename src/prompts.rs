@@ -0,0 +1,231 @@
+//! System prompts for each kind of request.
+//!
+//! Every kind has a built-in prompt, which a template file in the `prompts`
+//! directory next to `config.json` replaces, e.g. `prompts/howto.txt`.
+//! Questions also take the `system_prompt` setting and, for a single run,
+//! `--system`. Prompts may use the placeholders `{os}`, `{arch}`, `{shell}`,
+//! `{cwd}`, `{date}` and `{context}`, the environment facts chosen by the
+//! `context.*` settings. `{cwd}` is the full path of the current directory,
+//! so the built-in prompts leave it out.
+
+use crate::config::{get_config_path, Config};
+use crate::context;
+use crate::session::now;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What a system prompt is for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptKind {
+    /// Questions answered by the AI
+    Ask,
+    /// `how do I ...` questions without a built-in answer
+    Howto,
+    /// Generating a shell command for `ask run` and `--print-command`
+    Exec,
+    /// Diagnosing a failed command for `ask fix`
+    Fix,
+}
+
+impl PromptKind {
+    pub const ALL: [PromptKind; 4] = [
+        PromptKind::Ask,
+        PromptKind::Howto,
+        PromptKind::Exec,
+        PromptKind::Fix,
+    ];
+
+    /// Name of the template file, without `.txt`
+    pub fn name(self) -> &'static str {
+        match self {
+            PromptKind::Ask => "ask",
+            PromptKind::Howto => "howto",
+            PromptKind::Exec => "exec",
+            PromptKind::Fix => "fix",
+        }
+    }
+}
+
+const ASK_PROMPT: &str = "You are a helpful assistant. Answer questions directly and concisely. \
+     Do not mention what you are designed for or add unnecessary caveats about \
+     the type of questions you can answer. {context}";
+
+const EXEC_PROMPT: &str = "You translate requests into a single shell command that works in the \
+     user's environment. {context} Reply in exactly this format and nothing else:\n\
+     COMMAND: <one-line shell command>\n\
+     EXPLANATION: <one or two sentences describing what the command does>\n\
+     Do not use Markdown or code fences. Prefer safe, non-destructive options \
+     where possible.";
+
+/// Added to the question prompt for `ask fix`, unless `prompts/fix.txt`
+/// replaces the whole prompt
+const FIX_INSTRUCTIONS: &str = "The user ran a shell command that failed. Explain the likely \
+     cause in a few sentences, then give the corrected command in a single fenced code block. \
+     If the information given is not enough to fix it, say what to check instead.";
+
+/// The system prompt for `kind` of request, with its placeholders filled in
+pub fn system_prompt(kind: PromptKind, config: &Config) -> String {
+    let override_given = config.system_prompt_override.is_some();
+    let prompt = match kind {
+        PromptKind::Ask => question_prompt(config),
+        PromptKind::Howto if !override_given => {
+            template(kind).unwrap_or_else(|| question_prompt(config))
+        }
+        PromptKind::Howto => question_prompt(config),
+        PromptKind::Exec => template(kind).unwrap_or_else(|| EXEC_PROMPT.to_string()),
+        PromptKind::Fix => match template(kind).filter(|_| !override_given) {
+            Some(template) => template,
+            None => format!("{} {}", question_prompt(config), FIX_INSTRUCTIONS),
+        },
+    };
+    expand(&prompt, &placeholders(&prompt, config))
+}
+
+/// `--system`, else the `system_prompt` setting, else `prompts/ask.txt`,
+/// else the built-in prompt
+fn question_prompt(config: &Config) -> String {
+    config
+        .system_prompt_override
+        .clone()
+        .or_else(|| config.system_prompt.clone())
+        .or_else(|| template(PromptKind::Ask))
+        .unwrap_or_else(|| ASK_PROMPT.to_string())
+}
+
+/// Directory of the prompt templates, next to `config.json`
+pub fn prompts_dir() -> PathBuf {
+    get_config_path().with_file_name("prompts")
+}
+
+/// Path of the template for `kind`
+pub fn template_path(kind: PromptKind) -> PathBuf {
+    prompts_dir().join(format!("{}.txt", kind.name()))
+}
+
+/// The user's template for `kind`, if there is one with any text in it
+fn template(kind: PromptKind) -> Option<String> {
+    let text = fs::read_to_string(template_path(kind)).ok()?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Values of the placeholders. `{context}` runs a few commands, so it is
+/// only gathered when `prompt` uses it.
+fn placeholders(prompt: &str, config: &Config) -> Vec<(&'static str, String)> {
+    let mut values = vec![
+        ("os", env::consts::OS.to_string()),
+        ("arch", env::consts::ARCH.to_string()),
+        ("shell", current_shell().unwrap_or_else(|| "sh".to_string())),
+        (
+            "cwd",
+            env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+        ),
+        ("date", date(now())),
+    ];
+    if prompt.contains("{context}") {
+        values.push(("context", context::describe(&config.context)));
+    }
+    values
+}
+
+/// Replace each `{name}` in `template` with its value. Other braces are
+/// left alone, so templates can contain code.
+fn expand(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let placeholder = values.iter().find(|(name, _)| {
+            after
+                .strip_prefix(name)
+                .is_some_and(|tail| tail.starts_with('}'))
+        });
+        match placeholder {
+            Some((name, value)) => {
+                out.push_str(value);
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Name of the user's shell from `$SHELL`, e.g. "zsh"
+pub fn current_shell() -> Option<String> {
+    let path = env::var("SHELL").ok()?;
+    Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// `YYYY-MM-DD` in UTC for a Unix timestamp
+fn date(timestamp: u64) -> String {
+    // Days to civil date, from Howard Hinnant's date algorithms
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Vec<(&'static str, String)> {
+        vec![("os", "linux".to_string()), ("shell", "zsh".to_string())]
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand("On {os} using {shell}.", &values()),
+            "On linux using zsh."
+        );
+        assert_eq!(
+            expand("Keep {braces}, {os and {} alone {os", &values()),
+            "Keep {braces}, {os and {} alone {os"
+        );
+        assert_eq!(expand("fn f() { {os} }", &values()), "fn f() { linux }");
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_700_000_000), "2023-11-14");
+    }
+
+    #[test]
+    fn test_system_override() {
+        let config = Config {
+            system_prompt: Some("Answer like a pirate.".to_string()),
+            system_prompt_override: Some("Be brief on {os}.".to_string()),
+            ..Config::default()
+        };
+        let brief = format!("Be brief on {}.", env::consts::OS);
+        assert_eq!(system_prompt(PromptKind::Ask, &config), brief);
+        assert_eq!(system_prompt(PromptKind::Howto, &config), brief);
+        assert!(system_prompt(PromptKind::Fix, &config)
+            .starts_with(&format!("{} The user ran a shell command", brief)));
+    }
+}
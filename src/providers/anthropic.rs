@@ -1,16 +1,16 @@
-use crate::error::{AskError, Result};
-use crate::providers::streaming::build_system_prompt;
-use crate::providers::{Provider, ProviderConfig};
+use crate::error::Result;
+use crate::providers::streaming::{
+    api_error, events_from_lines, sse_data, EventStream, StreamEvent, Usage,
+};
+use crate::providers::{endpoint_url, Message, Provider, ProviderConfig};
 use async_trait::async_trait;
-use futures::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
 
 const API_VERSION: &str = "2023-06-01";
 
 #[derive(Serialize)]
-struct Message {
+struct ApiMessage {
     role: &'static str,
     content: String,
 }
@@ -19,7 +19,7 @@ struct Message {
 struct ApiRequest {
     model: String,
     max_tokens: u32,
-    messages: Vec<Message>,
+    messages: Vec<ApiMessage>,
     stream: bool,
     system: String,
 }
@@ -29,6 +29,9 @@ struct SseEvent {
     #[serde(rename = "type")]
     event_type: String,
     delta: Option<Delta>,
+    message: Option<MessageStart>,
+    usage: Option<ApiUsage>,
+    error: Option<ApiError>,
 }
 
 #[derive(Deserialize)]
@@ -36,25 +39,54 @@ struct Delta {
     #[serde(rename = "type")]
     delta_type: Option<String>,
     text: Option<String>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MessageStart {
+    usage: Option<ApiUsage>,
+}
+
+#[derive(Deserialize)]
+struct ApiUsage {
+    input_tokens: Option<u32>,
+    output_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Deserialize)]
+struct ModelInfo {
+    id: String,
 }
 
 pub struct AnthropicProvider;
 
 #[async_trait]
 impl Provider for AnthropicProvider {
-    async fn stream_response(&self, config: &ProviderConfig, query: &str) -> Result<()> {
+    async fn stream(&self, config: &ProviderConfig, messages: &[Message]) -> Result<EventStream> {
         let client = Client::new();
-        let system_prompt = build_system_prompt();
 
         let request = ApiRequest {
             model: config.model.clone(),
             max_tokens: config.max_tokens,
-            messages: vec![Message {
-                role: "user",
-                content: query.to_string(),
-            }],
+            messages: messages
+                .iter()
+                .map(|m| ApiMessage {
+                    role: m.role.as_str(),
+                    content: m.content.clone(),
+                })
+                .collect(),
             stream: true,
-            system: system_prompt,
+            system: config.system_prompt.clone(),
         };
 
         let response = client
@@ -67,47 +99,133 @@ impl Provider for AnthropicProvider {
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().await.unwrap_or_default();
-            return Err(AskError::Api {
-                status,
-                message: body,
-            });
+            return Err(api_error(response).await);
         }
 
-        let mut stream = response.bytes_stream();
-        let mut buffer = String::new();
+        Ok(events_from_lines(response, parse_line))
+    }
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
+    async fn list_models(&self, config: &ProviderConfig) -> Result<Vec<String>> {
+        let response = Client::new()
+            .get(endpoint_url(&config.api_url, "/messages", "/models"))
+            .query(&[("limit", "1000")])
+            .header("x-api-key", &config.api_key)
+            .header("anthropic-version", API_VERSION)
+            .send()
+            .await?;
 
-            // Process complete lines
-            while let Some(pos) = buffer.find('\n') {
-                let line = buffer[..pos].trim().to_string();
-                buffer = buffer[pos + 1..].to_string();
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
 
-                if let Some(json_str) = line.strip_prefix("data: ") {
-                    if json_str == "[DONE]" {
-                        continue;
-                    }
-                    if let Ok(event) = serde_json::from_str::<SseEvent>(json_str) {
-                        if event.event_type == "content_block_delta" {
-                            if let Some(delta) = event.delta {
-                                if delta.delta_type.as_deref() == Some("text_delta") {
-                                    if let Some(text) = delta.text {
-                                        print!("{}", text);
-                                        io::stdout().flush()?;
-                                    }
-                                }
-                            }
-                        }
+        let list: ModelList = response.json().await?;
+        Ok(list.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+/// Parse one SSE line into stream events
+fn parse_line(line: &str) -> Vec<StreamEvent> {
+    let Some(json_str) = sse_data(line) else {
+        return Vec::new();
+    };
+    let Ok(event) = serde_json::from_str::<SseEvent>(json_str) else {
+        return Vec::new();
+    };
+
+    let mut events = Vec::new();
+    match event.event_type.as_str() {
+        "message_start" => {
+            if let Some(usage) = event.message.and_then(|m| m.usage) {
+                events.push(StreamEvent::Usage(usage.into()));
+            }
+        }
+        "content_block_delta" => {
+            if let Some(delta) = event.delta {
+                if delta.delta_type.as_deref() == Some("text_delta") {
+                    if let Some(text) = delta.text {
+                        events.push(StreamEvent::Text(text));
                     }
                 }
             }
         }
+        "message_delta" => {
+            if let Some(usage) = event.usage {
+                events.push(StreamEvent::Usage(usage.into()));
+            }
+            if let Some(reason) = event.delta.and_then(|d| d.stop_reason) {
+                events.push(StreamEvent::Stop(reason));
+            }
+        }
+        "error" => {
+            let message = event
+                .error
+                .map(|e| e.message)
+                .unwrap_or_else(|| "unknown error".to_string());
+            events.push(StreamEvent::Error(message));
+        }
+        _ => {}
+    }
+    events
+}
+
+impl From<ApiUsage> for Usage {
+    fn from(usage: ApiUsage) -> Self {
+        Usage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_delta() {
+        let events = parse_line(
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+        );
+        assert_eq!(events, vec![StreamEvent::Text("Hi".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_usage_and_stop() {
+        let start = parse_line(
+            r#"data: {"type":"message_start","message":{"id":"msg_1","usage":{"input_tokens":12,"output_tokens":1}}}"#,
+        );
+        assert_eq!(
+            start,
+            vec![StreamEvent::Usage(Usage {
+                input_tokens: Some(12),
+                output_tokens: Some(1),
+            })]
+        );
+
+        let delta = parse_line(
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":42}}"#,
+        );
+        assert_eq!(
+            delta,
+            vec![
+                StreamEvent::Usage(Usage {
+                    input_tokens: None,
+                    output_tokens: Some(42),
+                }),
+                StreamEvent::Stop("end_turn".to_string()),
+            ]
+        );
+    }
 
-        println!(); // Final newline
-        Ok(())
+    #[test]
+    fn test_parse_error_and_noise() {
+        assert_eq!(
+            parse_line(
+                r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+            ),
+            vec![StreamEvent::Error("Overloaded".to_string())]
+        );
+        assert!(parse_line("event: ping").is_empty());
+        assert!(parse_line("data: not json").is_empty());
     }
 }
@@ -0,0 +1,213 @@
+use crate::error::Result;
+use crate::providers::streaming::{api_error, events_from_lines, EventStream, StreamEvent, Usage};
+use crate::providers::{endpoint_url, Message, Provider, ProviderConfig, Role};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+struct ChatTurn {
+    role: &'static str,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct ApiRequest {
+    model: String,
+    /// The latest user turn; earlier turns go in `chat_history`
+    message: String,
+    preamble: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    chat_history: Vec<ChatTurn>,
+    max_tokens: u32,
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct StreamResponse {
+    event_type: String,
+    text: Option<String>,
+    finish_reason: Option<String>,
+    response: Option<FinalResponse>,
+}
+
+#[derive(Deserialize)]
+struct FinalResponse {
+    meta: Option<Meta>,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    tokens: Option<Tokens>,
+    billed_units: Option<Tokens>,
+}
+
+#[derive(Deserialize)]
+struct Tokens {
+    input_tokens: Option<f64>,
+    output_tokens: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+}
+
+#[derive(Deserialize)]
+struct ModelInfo {
+    name: String,
+}
+
+pub struct CohereProvider;
+
+#[async_trait]
+impl Provider for CohereProvider {
+    async fn stream(&self, config: &ProviderConfig, messages: &[Message]) -> Result<EventStream> {
+        let client = Client::new();
+
+        let (latest, history) = match messages.split_last() {
+            Some((latest, history)) => (latest.content.clone(), history),
+            None => (String::new(), messages),
+        };
+        let request = ApiRequest {
+            model: config.model.clone(),
+            message: latest,
+            preamble: config.system_prompt.clone(),
+            chat_history: history
+                .iter()
+                .map(|m| ChatTurn {
+                    // Cohere calls the assistant role "CHATBOT"
+                    role: match m.role {
+                        Role::User => "USER",
+                        Role::Assistant => "CHATBOT",
+                    },
+                    message: m.content.clone(),
+                })
+                .collect(),
+            max_tokens: config.max_tokens,
+            stream: true,
+        };
+
+        let response = client
+            .post(&config.api_url)
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        // Cohere streams NDJSON events rather than SSE
+        Ok(events_from_lines(response, parse_line))
+    }
+
+    async fn list_models(&self, config: &ProviderConfig) -> Result<Vec<String>> {
+        let response = Client::new()
+            .get(endpoint_url(&config.api_url, "/chat", "/models"))
+            .query(&[("endpoint", "chat"), ("page_size", "1000")])
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let list: ModelList = response.json().await?;
+        Ok(list.models.into_iter().map(|m| m.name).collect())
+    }
+}
+
+/// Parse one NDJSON line into stream events
+fn parse_line(line: &str) -> Vec<StreamEvent> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Vec::new();
+    }
+    let Ok(response) = serde_json::from_str::<StreamResponse>(line) else {
+        return Vec::new();
+    };
+
+    let mut events = Vec::new();
+    match response.event_type.as_str() {
+        "text-generation" => {
+            if let Some(text) = response.text.filter(|t| !t.is_empty()) {
+                events.push(StreamEvent::Text(text));
+            }
+        }
+        "stream-end" => {
+            let meta = response.response.and_then(|r| r.meta);
+            if let Some(tokens) = meta.and_then(|m| m.billed_units.or(m.tokens)) {
+                events.push(StreamEvent::Usage(Usage {
+                    input_tokens: tokens.input_tokens.map(|n| n as u32),
+                    output_tokens: tokens.output_tokens.map(|n| n as u32),
+                }));
+            }
+            let reason = response
+                .finish_reason
+                .unwrap_or_else(|| "COMPLETE".to_string());
+            if reason.starts_with("ERROR") {
+                events.push(StreamEvent::Error(format!(
+                    "Cohere stopped with {}",
+                    reason
+                )));
+            } else {
+                events.push(StreamEvent::Stop(reason));
+            }
+        }
+        // stream-start, search results, citations and tool events carry no text
+        _ => {}
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_generation() {
+        assert_eq!(
+            parse_line(r#"{"is_finished":false,"event_type":"text-generation","text":"Hi"}"#),
+            vec![StreamEvent::Text("Hi".to_string())]
+        );
+        assert!(parse_line(
+            r#"{"is_finished":false,"event_type":"stream-start","generation_id":"abc"}"#
+        )
+        .is_empty());
+        assert!(parse_line("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_stream_end() {
+        let events = parse_line(
+            r#"{"is_finished":true,"event_type":"stream-end","finish_reason":"COMPLETE","response":{"text":"Hi","meta":{"billed_units":{"input_tokens":12,"output_tokens":3},"tokens":{"input_tokens":80,"output_tokens":3}}}}"#,
+        );
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Usage(Usage {
+                    input_tokens: Some(12),
+                    output_tokens: Some(3),
+                }),
+                StreamEvent::Stop("COMPLETE".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_error_finish() {
+        assert_eq!(
+            parse_line(
+                r#"{"is_finished":true,"event_type":"stream-end","finish_reason":"ERROR_TOXIC","response":{}}"#
+            ),
+            vec![StreamEvent::Error(
+                "Cohere stopped with ERROR_TOXIC".to_string()
+            )]
+        );
+    }
+}
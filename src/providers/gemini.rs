@@ -1,16 +1,16 @@
-use crate::error::{AskError, Result};
-use crate::providers::streaming::build_system_prompt;
-use crate::providers::{Provider, ProviderConfig};
+use crate::error::Result;
+use crate::providers::streaming::{
+    api_error, events_from_lines, sse_data, EventStream, StreamEvent, Usage,
+};
+use crate::providers::{Message, Provider, ProviderConfig, Role};
 use async_trait::async_trait;
-use futures::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
 
 #[derive(Serialize)]
 struct Content {
     parts: Vec<Part>,
-    role: Option<String>,
+    role: Option<&'static str>,
 }
 
 #[derive(Serialize)]
@@ -39,13 +39,30 @@ struct ApiRequest {
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct StreamResponse {
     candidates: Option<Vec<Candidate>>,
+    usage_metadata: Option<UsageMetadata>,
+    error: Option<ApiError>,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct Candidate {
     content: Option<CandidateContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    prompt_token_count: Option<u32>,
+    candidates_token_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
 }
 
 #[derive(Deserialize)]
@@ -58,27 +75,48 @@ struct ContentPart {
     text: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelInfo {
+    /// "models/gemini-1.5-flash"
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
 pub struct GeminiProvider;
 
 #[async_trait]
 impl Provider for GeminiProvider {
-    async fn stream_response(&self, config: &ProviderConfig, query: &str) -> Result<()> {
+    async fn stream(&self, config: &ProviderConfig, messages: &[Message]) -> Result<EventStream> {
         let client = Client::new();
-        let system_prompt = build_system_prompt();
 
         let request = ApiRequest {
-            contents: vec![Content {
-                parts: vec![Part {
-                    text: query.to_string(),
-                }],
-                role: Some("user".to_string()),
-            }],
+            contents: messages
+                .iter()
+                .map(|m| Content {
+                    parts: vec![Part {
+                        text: m.content.clone(),
+                    }],
+                    // Gemini calls the assistant role "model"
+                    role: Some(match m.role {
+                        Role::User => "user",
+                        Role::Assistant => "model",
+                    }),
+                })
+                .collect(),
             generation_config: GenerationConfig {
                 max_output_tokens: config.max_tokens,
             },
             system_instruction: SystemInstruction {
                 parts: vec![Part {
-                    text: system_prompt,
+                    text: config.system_prompt.clone(),
                 }],
             },
         };
@@ -98,48 +136,114 @@ impl Provider for GeminiProvider {
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().await.unwrap_or_default();
-            return Err(AskError::Api {
-                status,
-                message: body,
-            });
+            return Err(api_error(response).await);
+        }
+
+        // Gemini uses SSE with "data: " prefix
+        Ok(events_from_lines(response, parse_line))
+    }
+
+    async fn list_models(&self, config: &ProviderConfig) -> Result<Vec<String>> {
+        // The API URL is already the models collection
+        let response = Client::new()
+            .get(&config.api_url)
+            .query(&[("key", config.api_key.as_str()), ("pageSize", "1000")])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
         }
 
-        let mut stream = response.bytes_stream();
-        let mut buffer = String::new();
-
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-            // Process complete lines - Gemini uses SSE with "data: " prefix
-            while let Some(pos) = buffer.find('\n') {
-                let line = buffer[..pos].trim().to_string();
-                buffer = buffer[pos + 1..].to_string();
-
-                if let Some(json_str) = line.strip_prefix("data: ") {
-                    if let Ok(response) = serde_json::from_str::<StreamResponse>(json_str) {
-                        if let Some(candidates) = response.candidates {
-                            if let Some(candidate) = candidates.first() {
-                                if let Some(content) = &candidate.content {
-                                    if let Some(parts) = &content.parts {
-                                        for part in parts {
-                                            if let Some(text) = &part.text {
-                                                print!("{}", text);
-                                                io::stdout().flush()?;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        // Embedding and other models cannot answer questions
+        let list: ModelList = response.json().await?;
+        Ok(list
+            .models
+            .into_iter()
+            .filter(|m| {
+                m.supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+            })
+            .map(|m| m.name.trim_start_matches("models/").to_string())
+            .collect())
+    }
+}
+
+/// Parse one SSE line into stream events
+fn parse_line(line: &str) -> Vec<StreamEvent> {
+    let Some(json_str) = sse_data(line) else {
+        return Vec::new();
+    };
+    let Ok(response) = serde_json::from_str::<StreamResponse>(json_str) else {
+        return Vec::new();
+    };
+
+    let mut events = Vec::new();
+    if let Some(error) = response.error {
+        events.push(StreamEvent::Error(error.message));
+    }
+    if let Some(candidate) = response.candidates.and_then(|c| c.into_iter().next()) {
+        let parts = candidate.content.and_then(|c| c.parts).unwrap_or_default();
+        for part in parts {
+            if let Some(text) = part.text {
+                events.push(StreamEvent::Text(text));
             }
         }
+        if let Some(reason) = candidate.finish_reason {
+            events.push(StreamEvent::Stop(reason));
+        }
+    }
+    if let Some(usage) = response.usage_metadata {
+        events.push(StreamEvent::Usage(Usage {
+            input_tokens: usage.prompt_token_count,
+            output_tokens: usage.candidates_token_count,
+        }));
+    }
+    events
+}
 
-        println!(); // Final newline
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text_parts() {
+        let events = parse_line(
+            r#"data: {"candidates":[{"content":{"parts":[{"text":"Hel"},{"text":"lo"}],"role":"model"}}]}"#,
+        );
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Text("Hel".to_string()),
+                StreamEvent::Text("lo".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_finish_and_usage() {
+        let events = parse_line(
+            r#"data: {"candidates":[{"content":{"parts":[{"text":"."}]},"finishReason":"STOP"}],"usageMetadata":{"promptTokenCount":5,"candidatesTokenCount":7,"totalTokenCount":12}}"#,
+        );
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Text(".".to_string()),
+                StreamEvent::Stop("STOP".to_string()),
+                StreamEvent::Usage(Usage {
+                    input_tokens: Some(5),
+                    output_tokens: Some(7),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            parse_line(r#"data: {"error":{"code":500,"message":"Internal error"}}"#),
+            vec![StreamEvent::Error("Internal error".to_string())]
+        );
     }
 }
@@ -1,14 +1,18 @@
 pub mod anthropic;
+pub mod cohere;
 pub mod gemini;
 pub mod ollama;
 pub mod openai;
+pub mod retry;
 pub mod streaming;
 
 use crate::error::Result;
 use async_trait::async_trait;
+use retry::{RetryPolicy, Retrying};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
+use streaming::EventStream;
 
 /// Configuration for a provider instance
 pub struct ProviderConfig {
@@ -16,10 +20,52 @@ pub struct ProviderConfig {
     pub api_url: String,
     pub model: String,
     pub max_tokens: u32,
+    pub system_prompt: String,
+    pub retry: RetryPolicy,
+}
+
+/// Author of a conversation turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+/// A single turn in a conversation sent to a provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn user(content: impl Into<String>) -> Self {
+        Message {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Message {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
 }
 
 /// Known LLM provider types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ProviderType {
     #[default]
@@ -151,12 +197,24 @@ impl FromStr for ProviderType {
 /// Trait for LLM providers
 #[async_trait]
 pub trait Provider: Send + Sync {
-    /// Stream a response from the provider
-    async fn stream_response(&self, config: &ProviderConfig, query: &str) -> Result<()>;
+    /// Send the conversation in `messages` (oldest first, ending with the
+    /// user's latest turn) and return a stream of response events. Failures
+    /// before streaming starts, such as connection or HTTP errors, are
+    /// returned directly.
+    async fn stream(&self, config: &ProviderConfig, messages: &[Message]) -> Result<EventStream>;
+
+    /// Names of the models the provider offers, from its list-models
+    /// endpoint. `config.model` is ignored.
+    async fn list_models(&self, config: &ProviderConfig) -> Result<Vec<String>>;
 }
 
-/// Create a provider instance for the given type
+/// Create a provider instance for the given type. Transient failures are
+/// retried according to [`ProviderConfig::retry`].
 pub fn create_provider(provider_type: ProviderType) -> Box<dyn Provider> {
+    Box::new(Retrying::new(create_base_provider(provider_type)))
+}
+
+fn create_base_provider(provider_type: ProviderType) -> Box<dyn Provider> {
     match provider_type {
         ProviderType::Anthropic => Box::new(anthropic::AnthropicProvider),
         ProviderType::OpenAI => Box::new(openai::OpenAIProvider::new(ProviderType::OpenAI)),
@@ -165,12 +223,20 @@ pub fn create_provider(provider_type: ProviderType) -> Box<dyn Provider> {
         ProviderType::Perplexity => Box::new(openai::OpenAIProvider::new(ProviderType::Perplexity)),
         ProviderType::Groq => Box::new(openai::OpenAIProvider::new(ProviderType::Groq)),
         ProviderType::Mistral => Box::new(openai::OpenAIProvider::new(ProviderType::Mistral)),
-        ProviderType::Cohere => Box::new(openai::OpenAIProvider::new(ProviderType::Cohere)),
+        ProviderType::Cohere => Box::new(cohere::CohereProvider),
         ProviderType::Together => Box::new(openai::OpenAIProvider::new(ProviderType::Together)),
         ProviderType::Custom => Box::new(openai::OpenAIProvider::new(ProviderType::Custom)),
     }
 }
 
+/// Turn a provider's chat URL into a sibling endpoint by swapping the final
+/// path segment(s) `from` for `to`, e.g. `/v1/messages` into `/v1/models`.
+/// URLs that do not end in `from` get `to` appended.
+pub fn endpoint_url(api_url: &str, from: &str, to: &str) -> String {
+    let base = api_url.trim_end_matches('/');
+    format!("{}{}", base.strip_suffix(from).unwrap_or(base), to)
+}
+
 /// Check if a URL is a known provider URL
 pub fn is_known_provider_url(url: &str) -> bool {
     let known_domains = [
@@ -183,6 +249,7 @@ pub fn is_known_provider_url(url: &str) -> bool {
         "api.groq.com",
         "api.mistral.ai",
         "api.cohere.ai",
+        "api.cohere.com",
         "api.together.xyz",
     ];
 
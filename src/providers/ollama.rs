@@ -1,22 +1,21 @@
 use crate::error::{AskError, Result};
-use crate::providers::streaming::build_system_prompt;
-use crate::providers::{Provider, ProviderConfig};
+use crate::providers::streaming::api_error;
+use crate::providers::streaming::{events_from_lines, EventStream, StreamEvent, Usage};
+use crate::providers::{endpoint_url, retry, Message, Provider, ProviderConfig};
 use async_trait::async_trait;
-use futures::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
 
 #[derive(Serialize)]
-struct Message {
-    role: String,
+struct ApiMessage {
+    role: &'static str,
     content: String,
 }
 
 #[derive(Serialize)]
 struct ApiRequest {
     model: String,
-    messages: Vec<Message>,
+    messages: Vec<ApiMessage>,
     stream: bool,
 }
 
@@ -24,6 +23,10 @@ struct ApiRequest {
 struct StreamResponse {
     message: Option<MessageResponse>,
     done: Option<bool>,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+    error: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -31,26 +34,28 @@ struct MessageResponse {
     content: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct TagList {
+    #[serde(default)]
+    models: Vec<LocalModel>,
+}
+
+#[derive(Deserialize)]
+struct LocalModel {
+    /// "llama3.2:latest"
+    name: String,
+}
+
 pub struct OllamaProvider;
 
 #[async_trait]
 impl Provider for OllamaProvider {
-    async fn stream_response(&self, config: &ProviderConfig, query: &str) -> Result<()> {
+    async fn stream(&self, config: &ProviderConfig, messages: &[Message]) -> Result<EventStream> {
         let client = Client::new();
-        let system_prompt = build_system_prompt();
 
         let request = ApiRequest {
             model: config.model.clone(),
-            messages: vec![
-                Message {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                Message {
-                    role: "user".to_string(),
-                    content: query.to_string(),
-                },
-            ],
+            messages: build_messages(&config.system_prompt, messages),
             stream: true,
         };
 
@@ -78,6 +83,7 @@ impl Provider for OllamaProvider {
 
         if !response.status().is_success() {
             let status = response.status().as_u16();
+            let retry_after = retry::retry_after(response.headers());
             let body = response.text().await.unwrap_or_default();
 
             // Check for model not found error
@@ -93,41 +99,147 @@ impl Provider for OllamaProvider {
             return Err(AskError::Api {
                 status,
                 message: body,
+                retry_after,
             });
         }
 
-        let mut stream = response.bytes_stream();
-        let mut buffer = String::new();
+        // Ollama uses NDJSON (newline-delimited JSON)
+        Ok(events_from_lines(response, parse_line))
+    }
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
+    /// Models pulled into the local Ollama, not the whole library
+    async fn list_models(&self, config: &ProviderConfig) -> Result<Vec<String>> {
+        let url = endpoint_url(&config.api_url, "/api/chat", "/api/tags");
+        let response = Client::new().get(&url).send().await.map_err(|e| {
+            if e.is_connect() {
+                AskError::Generic(format!(
+                    "Could not connect to Ollama at {}. Is Ollama running?",
+                    url
+                ))
+            } else {
+                e.into()
+            }
+        })?;
 
-            // Ollama uses NDJSON (newline-delimited JSON)
-            while let Some(pos) = buffer.find('\n') {
-                let line = buffer[..pos].trim().to_string();
-                buffer = buffer[pos + 1..].to_string();
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
 
-                if line.is_empty() {
-                    continue;
-                }
+        let tags: TagList = response.json().await?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+}
 
-                if let Ok(response) = serde_json::from_str::<StreamResponse>(&line) {
-                    if let Some(message) = response.message {
-                        if let Some(content) = message.content {
-                            print!("{}", content);
-                            io::stdout().flush()?;
-                        }
-                    }
-                    // Check if done
-                    if response.done == Some(true) {
-                        break;
-                    }
-                }
-            }
+/// Parse one NDJSON line into stream events
+fn parse_line(line: &str) -> Vec<StreamEvent> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Vec::new();
+    }
+    let Ok(response) = serde_json::from_str::<StreamResponse>(line) else {
+        return Vec::new();
+    };
+
+    let mut events = Vec::new();
+    if let Some(error) = response.error {
+        events.push(StreamEvent::Error(error));
+    }
+    if let Some(content) = response.message.and_then(|m| m.content) {
+        if !content.is_empty() {
+            events.push(StreamEvent::Text(content));
         }
+    }
+    if response.done == Some(true) {
+        events.push(StreamEvent::Usage(Usage {
+            input_tokens: response.prompt_eval_count,
+            output_tokens: response.eval_count,
+        }));
+        events.push(StreamEvent::Stop(
+            response.done_reason.unwrap_or_else(|| "stop".to_string()),
+        ));
+    }
+    events
+}
+
+/// Chat endpoint from Ollama's own `OLLAMA_HOST` setting, used when no
+/// `api_url` is configured
+pub fn url_from_env() -> Option<String> {
+    let host = std::env::var("OLLAMA_HOST").ok()?;
+    (!host.is_empty()).then(|| chat_url(&host))
+}
+
+/// Chat endpoint for an `OLLAMA_HOST` value such as `localhost:11434` or
+/// `http://gpu-box:11434`
+fn chat_url(host: &str) -> String {
+    let host = host.trim_end_matches('/');
+    if host.contains("://") {
+        format!("{}/api/chat", host)
+    } else {
+        format!("http://{}/api/chat", host)
+    }
+}
+
+fn build_messages(system_prompt: &str, messages: &[Message]) -> Vec<ApiMessage> {
+    let mut api_messages = vec![ApiMessage {
+        role: "system",
+        content: system_prompt.to_string(),
+    }];
+    api_messages.extend(messages.iter().map(|m| ApiMessage {
+        role: m.role.as_str(),
+        content: m.content.clone(),
+    }));
+    api_messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_url() {
+        assert_eq!(
+            chat_url("127.0.0.1:11434"),
+            "http://127.0.0.1:11434/api/chat"
+        );
+        assert_eq!(
+            chat_url("https://gpu-box:8443/"),
+            "https://gpu-box:8443/api/chat"
+        );
+    }
+
+    #[test]
+    fn test_parse_content() {
+        let events = parse_line(
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"Hi"},"done":false}"#,
+        );
+        assert_eq!(events, vec![StreamEvent::Text("Hi".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_done() {
+        let events = parse_line(
+            r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":26,"eval_count":298}"#,
+        );
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Usage(Usage {
+                    input_tokens: Some(26),
+                    output_tokens: Some(298),
+                }),
+                StreamEvent::Stop("stop".to_string()),
+            ]
+        );
+    }
 
-        println!(); // Final newline
-        Ok(())
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            parse_line(r#"{"error":"model runner has unexpectedly stopped"}"#),
+            vec![StreamEvent::Error(
+                "model runner has unexpectedly stopped".to_string()
+            )]
+        );
+        assert!(parse_line("").is_empty());
     }
 }
@@ -1,34 +1,55 @@
-use crate::error::{AskError, Result};
-use crate::providers::streaming::build_system_prompt;
-use crate::providers::{Provider, ProviderConfig, ProviderType};
+use crate::error::Result;
+use crate::providers::streaming::{
+    api_error, events_from_lines, sse_data, EventStream, StreamEvent, Usage,
+};
+use crate::providers::{endpoint_url, Message, Provider, ProviderConfig, ProviderType};
 use async_trait::async_trait;
-use futures::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::io::{self, Write};
 
 #[derive(Serialize)]
-struct Message {
-    role: String,
+struct ApiMessage {
+    role: &'static str,
     content: String,
 }
 
 #[derive(Serialize)]
 struct ApiRequest {
     model: String,
-    messages: Vec<Message>,
+    messages: Vec<ApiMessage>,
     stream: bool,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    include_usage: bool,
 }
 
 #[derive(Deserialize)]
 struct StreamResponse {
     choices: Option<Vec<Choice>>,
+    usage: Option<ApiUsage>,
+    error: Option<ApiError>,
 }
 
 #[derive(Deserialize)]
 struct Choice {
     delta: Option<DeltaContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiUsage {
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
 }
 
 #[derive(Deserialize)]
@@ -36,35 +57,54 @@ struct DeltaContent {
     content: Option<String>,
 }
 
-/// OpenAI-compatible provider that works with OpenAI, Perplexity, Groq, Mistral, Cohere, Together
-pub struct OpenAIProvider;
+/// OpenAI wraps the list in `data`; Together returns a bare array
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ModelList {
+    Wrapped { data: Vec<ModelInfo> },
+    Bare(Vec<ModelInfo>),
+}
+
+#[derive(Deserialize)]
+struct ModelInfo {
+    id: String,
+}
+
+/// OpenAI-compatible provider that works with OpenAI, Perplexity, Groq, Mistral, Together
+pub struct OpenAIProvider {
+    provider_type: ProviderType,
+}
 
 impl OpenAIProvider {
-    pub fn new(_provider_type: ProviderType) -> Self {
-        Self
+    pub fn new(provider_type: ProviderType) -> Self {
+        Self { provider_type }
+    }
+
+    /// OpenAI only reports usage in a stream when asked to. Mistral and
+    /// Perplexity always report it, and custom servers may reject the
+    /// option, so it is only sent where it is known to work.
+    fn stream_options(&self) -> Option<StreamOptions> {
+        matches!(
+            self.provider_type,
+            ProviderType::OpenAI | ProviderType::Groq | ProviderType::Together
+        )
+        .then_some(StreamOptions {
+            include_usage: true,
+        })
     }
 }
 
 #[async_trait]
 impl Provider for OpenAIProvider {
-    async fn stream_response(&self, config: &ProviderConfig, query: &str) -> Result<()> {
+    async fn stream(&self, config: &ProviderConfig, messages: &[Message]) -> Result<EventStream> {
         let client = Client::new();
-        let system_prompt = build_system_prompt();
 
         let request = ApiRequest {
             model: config.model.clone(),
-            messages: vec![
-                Message {
-                    role: "system".to_string(),
-                    content: system_prompt,
-                },
-                Message {
-                    role: "user".to_string(),
-                    content: query.to_string(),
-                },
-            ],
+            messages: build_messages(&config.system_prompt, messages),
             stream: true,
             max_tokens: config.max_tokens,
+            stream_options: self.stream_options(),
         };
 
         let response = client
@@ -76,47 +116,114 @@ impl Provider for OpenAIProvider {
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().await.unwrap_or_default();
-            return Err(AskError::Api {
-                status,
-                message: body,
-            });
+            return Err(api_error(response).await);
+        }
+
+        Ok(events_from_lines(response, parse_line))
+    }
+
+    async fn list_models(&self, config: &ProviderConfig) -> Result<Vec<String>> {
+        let response = Client::new()
+            .get(endpoint_url(
+                &config.api_url,
+                "/chat/completions",
+                "/models",
+            ))
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
         }
 
-        let mut stream = response.bytes_stream();
-        let mut buffer = String::new();
-
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-            // Process complete lines
-            while let Some(pos) = buffer.find('\n') {
-                let line = buffer[..pos].trim().to_string();
-                buffer = buffer[pos + 1..].to_string();
-
-                if let Some(json_str) = line.strip_prefix("data: ") {
-                    if json_str == "[DONE]" {
-                        continue;
-                    }
-                    if let Ok(response) = serde_json::from_str::<StreamResponse>(json_str) {
-                        if let Some(choices) = response.choices {
-                            if let Some(choice) = choices.first() {
-                                if let Some(delta) = &choice.delta {
-                                    if let Some(content) = &delta.content {
-                                        print!("{}", content);
-                                        io::stdout().flush()?;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+        let (ModelList::Wrapped { data: models } | ModelList::Bare(models)) =
+            response.json().await?;
+        Ok(models.into_iter().map(|m| m.id).collect())
+    }
+}
+
+/// Parse one SSE line into stream events
+fn parse_line(line: &str) -> Vec<StreamEvent> {
+    let Some(json_str) = sse_data(line) else {
+        return Vec::new();
+    };
+    let Ok(response) = serde_json::from_str::<StreamResponse>(json_str) else {
+        return Vec::new();
+    };
+
+    let mut events = Vec::new();
+    if let Some(error) = response.error {
+        events.push(StreamEvent::Error(error.message));
+    }
+    if let Some(choice) = response.choices.and_then(|c| c.into_iter().next()) {
+        if let Some(content) = choice.delta.and_then(|d| d.content) {
+            if !content.is_empty() {
+                events.push(StreamEvent::Text(content));
             }
         }
+        if let Some(reason) = choice.finish_reason {
+            events.push(StreamEvent::Stop(reason));
+        }
+    }
+    if let Some(usage) = response.usage {
+        events.push(StreamEvent::Usage(Usage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }));
+    }
+    events
+}
+
+fn build_messages(system_prompt: &str, messages: &[Message]) -> Vec<ApiMessage> {
+    let mut api_messages = vec![ApiMessage {
+        role: "system",
+        content: system_prompt.to_string(),
+    }];
+    api_messages.extend(messages.iter().map(|m| ApiMessage {
+        role: m.role.as_str(),
+        content: m.content.clone(),
+    }));
+    api_messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_content_delta() {
+        let events =
+            parse_line(r#"data: {"id":"1","choices":[{"index":0,"delta":{"content":"Hi"}}]}"#);
+        assert_eq!(events, vec![StreamEvent::Text("Hi".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_finish_and_usage() {
+        let events = parse_line(
+            r#"data: {"choices":[{"index":0,"delta":{},"finish_reason":"stop"}],"usage":null}"#,
+        );
+        assert_eq!(events, vec![StreamEvent::Stop("stop".to_string())]);
+
+        let events = parse_line(
+            r#"data: {"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":3,"total_tokens":12}}"#,
+        );
+        assert_eq!(
+            events,
+            vec![StreamEvent::Usage(Usage {
+                input_tokens: Some(9),
+                output_tokens: Some(3),
+            })]
+        );
+    }
 
-        println!(); // Final newline
-        Ok(())
+    #[test]
+    fn test_parse_error_and_done() {
+        assert_eq!(
+            parse_line(r#"data: {"error":{"message":"Rate limit reached"}}"#),
+            vec![StreamEvent::Error("Rate limit reached".to_string())]
+        );
+        assert!(parse_line("data: [DONE]").is_empty());
+        assert!(parse_line(": keep-alive").is_empty());
     }
 }
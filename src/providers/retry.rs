@@ -0,0 +1,316 @@
+//! Retries for transient provider failures.
+//!
+//! [`Retrying`] wraps any [`Provider`] and re-sends the request when it fails
+//! with a rate limit, a server error or a dropped connection. Retries only
+//! happen before the first text arrives, so nothing the user has already
+//! seen is ever repeated.
+
+use crate::error::{AskError, Result};
+use crate::providers::streaming::{EventStream, StreamEvent};
+use crate::providers::{Message, Provider, ProviderConfig};
+use async_trait::async_trait;
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::{Duration, SystemTime};
+
+/// Upper bound for the exponential backoff between attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A `Retry-After` longer than this is treated as a hard failure
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// How often and how patiently to retry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1000),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (starting at 0), or `None` if the
+    /// error should not be retried
+    fn delay(&self, attempt: u32, error: &AskError) -> Option<Duration> {
+        if attempt >= self.max_retries || !is_transient(error) {
+            return None;
+        }
+        if let AskError::Api {
+            retry_after: Some(retry_after),
+            ..
+        } = error
+        {
+            return (*retry_after <= MAX_RETRY_AFTER).then_some(*retry_after);
+        }
+
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_BACKOFF);
+        // Up to 25% jitter so parallel invocations do not retry in lockstep
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        Some(backoff + backoff.mul_f64(f64::from(nanos % 250) / 1000.0))
+    }
+}
+
+/// Rate limits, server errors and connection failures are worth retrying;
+/// anything else (bad key, unknown model, ...) will fail the same way again.
+fn is_transient(error: &AskError) -> bool {
+    match error {
+        AskError::Api { status, .. } => *status == 429 || *status >= 500,
+        AskError::Request(e) => e.is_connect() || e.is_timeout() || e.is_request() || e.is_body(),
+        _ => false,
+    }
+}
+
+/// Parse `Retry-After` (seconds) or the millisecond variant some
+/// OpenAI-compatible APIs send. HTTP dates are ignored in favour of backoff.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<f64>().ok()) {
+        return Some(Duration::from_secs_f64(ms.max(0.0) / 1000.0));
+    }
+    header(RETRY_AFTER.as_str())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .map(|secs| Duration::from_secs_f64(secs.max(0.0)))
+}
+
+/// A provider that retries transient failures of the wrapped provider
+pub struct Retrying {
+    inner: Box<dyn Provider>,
+}
+
+impl Retrying {
+    pub fn new(inner: Box<dyn Provider>) -> Self {
+        Retrying { inner }
+    }
+}
+
+#[async_trait]
+impl Provider for Retrying {
+    async fn stream(&self, config: &ProviderConfig, messages: &[Message]) -> Result<EventStream> {
+        let mut attempt = 0;
+        loop {
+            let result = match self.inner.stream(config, messages).await {
+                Ok(stream) => until_first_text(stream).await,
+                Err(e) => Err(e),
+            };
+            let error = match result {
+                Ok(stream) => return Ok(stream),
+                Err(e) => e,
+            };
+            let Some(delay) = config.retry.delay(attempt, &error) else {
+                return Err(error);
+            };
+
+            attempt += 1;
+            eprintln!(
+                "{} {}, retrying in {:.1}s ({}/{})…",
+                "Warning:".yellow(),
+                summary(&error),
+                delay.as_secs_f64(),
+                attempt,
+                config.retry.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn list_models(&self, config: &ProviderConfig) -> Result<Vec<String>> {
+        self.inner.list_models(config).await
+    }
+}
+
+/// Read events until the first text (or the end), so that failures before
+/// any output surface as `Err` and can be retried. The buffered events are
+/// replayed in front of the rest of the stream.
+async fn until_first_text(mut stream: EventStream) -> Result<EventStream> {
+    let mut buffered = Vec::new();
+    while let Some(event) = stream.next().await {
+        let event = event?;
+        let is_text = matches!(event, StreamEvent::Text(_));
+        buffered.push(Ok(event));
+        if is_text {
+            break;
+        }
+    }
+    Ok(Box::pin(stream::iter(buffered).chain(stream)))
+}
+
+/// Short description of an error for retry and fallback notices
+pub fn summary(error: &AskError) -> String {
+    match error {
+        AskError::Api { status, .. } => format!("API error ({})", status),
+        AskError::Request(e) if e.is_timeout() => "Request timed out".to_string(),
+        AskError::Request(_) => "Connection failed".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::streaming::collect;
+    use reqwest::header::HeaderValue;
+    use std::sync::Mutex;
+
+    fn api_error(status: u16, retry_after: Option<Duration>) -> AskError {
+        AskError::Api {
+            status,
+            message: String::new(),
+            retry_after,
+        }
+    }
+
+    fn config(max_retries: u32) -> ProviderConfig {
+        ProviderConfig {
+            api_key: String::new(),
+            api_url: String::new(),
+            model: String::new(),
+            max_tokens: 0,
+            system_prompt: String::new(),
+            retry: RetryPolicy {
+                max_retries,
+                base_delay: Duration::from_millis(1),
+            },
+        }
+    }
+
+    /// Replays a scripted outcome per attempt
+    struct Scripted {
+        attempts: Mutex<Vec<Result<Vec<Result<StreamEvent>>>>>,
+    }
+
+    impl Scripted {
+        fn new(mut attempts: Vec<Result<Vec<Result<StreamEvent>>>>) -> Self {
+            attempts.reverse();
+            Scripted {
+                attempts: Mutex::new(attempts),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for Scripted {
+        async fn stream(&self, _: &ProviderConfig, _: &[Message]) -> Result<EventStream> {
+            let events = self.attempts.lock().unwrap().pop().expect("attempt")?;
+            Ok(Box::pin(stream::iter(events)))
+        }
+
+        async fn list_models(&self, _: &ProviderConfig) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn text(s: &str) -> Result<StreamEvent> {
+        Ok(StreamEvent::Text(s.to_string()))
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&api_error(429, None)));
+        assert!(is_transient(&api_error(503, None)));
+        assert!(!is_transient(&api_error(401, None)));
+        assert!(!is_transient(&AskError::Stream("overloaded".to_string())));
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+        };
+        let first = policy.delay(0, &api_error(500, None)).unwrap();
+        assert!(first >= Duration::from_millis(100) && first <= Duration::from_millis(125));
+        let third = policy.delay(2, &api_error(500, None)).unwrap();
+        assert!(third >= Duration::from_millis(400) && third <= Duration::from_millis(500));
+
+        // Retry-After wins over backoff, unless it is unreasonably long
+        let hinted = api_error(429, Some(Duration::from_secs(3)));
+        assert_eq!(policy.delay(0, &hinted), Some(Duration::from_secs(3)));
+        let too_long = api_error(429, Some(Duration::from_secs(3600)));
+        assert_eq!(policy.delay(0, &too_long), None);
+
+        assert_eq!(policy.delay(3, &api_error(500, None)), None);
+        assert_eq!(policy.delay(0, &api_error(400, None)), None);
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.insert("retry-after-ms", HeaderValue::from_static("150"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(150)));
+        headers.remove("retry-after-ms");
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let provider = Retrying::new(Box::new(Scripted::new(vec![
+            Err(api_error(503, None)),
+            Err(api_error(429, Some(Duration::ZERO))),
+            Ok(vec![text("hello"), text(" world")]),
+        ])));
+        let stream = provider.stream(&config(2), &[]).await.unwrap();
+        assert_eq!(collect(stream).await.unwrap().text, "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let scripted = Scripted::new(vec![Err(api_error(500, None)), Err(api_error(502, None))]);
+        let provider = Retrying::new(Box::new(scripted));
+        let error = provider.stream(&config(1), &[]).await.err().unwrap();
+        assert!(matches!(error, AskError::Api { status: 502, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_for_permanent_errors() {
+        let provider = Retrying::new(Box::new(Scripted::new(vec![Err(api_error(401, None))])));
+        let error = provider.stream(&config(3), &[]).await.err().unwrap();
+        assert!(matches!(error, AskError::Api { status: 401, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_after_first_text() {
+        // The failure after "partial" reaches the caller instead of restarting
+        let provider = Retrying::new(Box::new(Scripted::new(vec![
+            Ok(vec![text("partial"), Err(api_error(500, None))]),
+            Ok(vec![text("unexpected second attempt")]),
+        ])));
+        let stream = provider.stream(&config(2), &[]).await.unwrap();
+        assert!(collect(stream).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_failure_before_first_text() {
+        let provider = Retrying::new(Box::new(Scripted::new(vec![
+            Ok(vec![
+                Ok(StreamEvent::Usage(Default::default())),
+                Err(api_error(500, None)),
+            ]),
+            Ok(vec![text("second")]),
+        ])));
+        let stream = provider.stream(&config(1), &[]).await.unwrap();
+        assert_eq!(collect(stream).await.unwrap().text, "second");
+    }
+}
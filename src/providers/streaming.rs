@@ -1,10 +1,251 @@
-/// Build the system prompt for all providers
-pub fn build_system_prompt() -> String {
-    format!(
-        "You are a helpful assistant. Answer questions directly and concisely. \
-         Do not mention what you are designed for or add unnecessary caveats about \
-         the type of questions you can answer. The user is on {} ({}).",
-        std::env::consts::OS,
-        std::env::consts::ARCH
-    )
+use crate::error::{AskError, Result};
+use crate::providers::retry;
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::Response;
+use serde::Serialize;
+use std::pin::Pin;
+use std::time::Instant;
+
+/// Token counts reported by a provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Usage {
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+}
+
+impl Usage {
+    /// Merge counts from a later event, keeping earlier values that are not repeated
+    pub fn merge(&mut self, other: Usage) {
+        if other.input_tokens.is_some() {
+            self.input_tokens = other.input_tokens;
+        }
+        if other.output_tokens.is_some() {
+            self.output_tokens = other.output_tokens;
+        }
+    }
+}
+
+/// A typed event parsed from a provider's response stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// A chunk of response text
+    Text(String),
+    /// Token usage (may arrive in several partial events)
+    Usage(Usage),
+    /// Why the model stopped generating
+    Stop(String),
+    /// An error reported inside the stream
+    Error(String),
+}
+
+/// Stream of events returned by [`Provider::stream`](super::Provider::stream)
+pub type EventStream = Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>;
+
+/// The accumulated result of a response stream
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Completion {
+    pub text: String,
+    pub usage: Usage,
+    pub stop_reason: Option<String>,
+    /// When the first text arrived, for time-to-first-token
+    pub first_text_at: Option<Instant>,
+}
+
+impl Completion {
+    /// Fold an event into the completion. In-stream errors become `Err`.
+    pub fn push(&mut self, event: StreamEvent) -> Result<()> {
+        match event {
+            StreamEvent::Text(text) => {
+                self.first_text_at.get_or_insert_with(Instant::now);
+                self.text.push_str(&text);
+            }
+            StreamEvent::Usage(usage) => self.usage.merge(usage),
+            StreamEvent::Stop(reason) => self.stop_reason = Some(reason),
+            StreamEvent::Error(message) => return Err(AskError::Stream(message)),
+        }
+        Ok(())
+    }
+}
+
+/// Consume a stream without rendering it
+pub async fn collect(mut stream: EventStream) -> Result<Completion> {
+    let mut completion = Completion::default();
+    while let Some(event) = stream.next().await {
+        completion.push(event?)?;
+    }
+    Ok(completion)
+}
+
+/// Turn a provider's parsed lines into an event stream.
+///
+/// `parse` maps one complete line (SSE `data:` line or NDJSON object) to the
+/// events it contains.
+pub fn events_from_lines<F>(response: Response, parse: F) -> EventStream
+where
+    F: Fn(&str) -> Vec<StreamEvent> + Send + 'static,
+{
+    let events = lines(response).flat_map(move |line| {
+        let events: Vec<Result<StreamEvent>> = match line {
+            Ok(line) => parse(&line).into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        };
+        stream::iter(events)
+    });
+    Box::pin(events)
+}
+
+/// Turn an unsuccessful HTTP response into an `AskError::Api`
+pub async fn api_error(response: Response) -> AskError {
+    let status = response.status().as_u16();
+    let retry_after = retry::retry_after(response.headers());
+    let message = response.text().await.unwrap_or_default();
+    AskError::Api {
+        status,
+        message,
+        retry_after,
+    }
+}
+
+/// Payload of an SSE `data:` line, if this is one
+pub fn sse_data(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("data:")
+        .map(str::trim_start)
+        .filter(|data| *data != "[DONE]")
+}
+
+/// Split a response body into lines as chunks arrive
+pub fn lines(response: Response) -> impl Stream<Item = Result<String>> + Send {
+    let state = (
+        response.bytes_stream().boxed(),
+        LineBuffer::default(),
+        false,
+    );
+    stream::unfold(state, |(mut bytes, mut buffer, mut finished)| async move {
+        loop {
+            if let Some(line) = buffer.next_line() {
+                return Some((Ok(line), (bytes, buffer, finished)));
+            }
+            if finished {
+                return buffer
+                    .take_remaining()
+                    .map(|line| (Ok(line), (bytes, buffer, true)));
+            }
+            match bytes.next().await {
+                Some(Ok(chunk)) => buffer.push(&chunk),
+                Some(Err(e)) => {
+                    buffer.clear();
+                    return Some((Err(e.into()), (bytes, buffer, true)));
+                }
+                None => finished = true,
+            }
+        }
+    })
+}
+
+/// Byte buffer that yields complete lines.
+///
+/// Lines are only decoded once complete, so multi-byte UTF-8 characters split
+/// across network chunks are reassembled correctly.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    buffer: Vec<u8>,
+}
+
+impl LineBuffer {
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Next complete line without its terminator, if any
+    pub fn next_line(&mut self) -> Option<String> {
+        let pos = self.buffer.iter().position(|&b| b == b'\n')?;
+        let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+        Some(decode_line(&line))
+    }
+
+    /// Whatever is left after the body ended without a final newline
+    pub fn take_remaining(&mut self) -> Option<String> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let rest = std::mem::take(&mut self.buffer);
+        Some(decode_line(&rest))
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches(['\n', '\r'])
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_split_utf8() {
+        let mut buffer = LineBuffer::default();
+        let line = "data: héllo ✓\n".as_bytes();
+        // Split inside the multi-byte check mark
+        let split = line.len() - 3;
+        buffer.push(&line[..split]);
+        assert_eq!(buffer.next_line(), None);
+        buffer.push(&line[split..]);
+        assert_eq!(buffer.next_line(), Some("data: héllo ✓".to_string()));
+        assert_eq!(buffer.next_line(), None);
+    }
+
+    #[test]
+    fn test_line_buffer_crlf_and_remaining() {
+        let mut buffer = LineBuffer::default();
+        buffer.push(b"one\r\ntwo\nthree");
+        assert_eq!(buffer.next_line(), Some("one".to_string()));
+        assert_eq!(buffer.next_line(), Some("two".to_string()));
+        assert_eq!(buffer.next_line(), None);
+        assert_eq!(buffer.take_remaining(), Some("three".to_string()));
+        assert_eq!(buffer.take_remaining(), None);
+    }
+
+    #[test]
+    fn test_sse_data() {
+        assert_eq!(sse_data("data: {\"a\":1}"), Some("{\"a\":1}"));
+        assert_eq!(sse_data("data:{}"), Some("{}"));
+        assert_eq!(sse_data("data: [DONE]"), None);
+        assert_eq!(sse_data("event: message_stop"), None);
+    }
+
+    #[test]
+    fn test_completion_push() {
+        let mut completion = Completion::default();
+        completion.push(StreamEvent::Text("Hel".into())).unwrap();
+        completion.push(StreamEvent::Text("lo".into())).unwrap();
+        completion
+            .push(StreamEvent::Usage(Usage {
+                input_tokens: Some(10),
+                output_tokens: None,
+            }))
+            .unwrap();
+        completion
+            .push(StreamEvent::Usage(Usage {
+                input_tokens: None,
+                output_tokens: Some(2),
+            }))
+            .unwrap();
+        completion
+            .push(StreamEvent::Stop("end_turn".into()))
+            .unwrap();
+        assert_eq!(completion.text, "Hello");
+        assert_eq!(completion.usage.input_tokens, Some(10));
+        assert_eq!(completion.usage.output_tokens, Some(2));
+        assert_eq!(completion.stop_reason.as_deref(), Some("end_turn"));
+        assert!(completion
+            .push(StreamEvent::Error("overloaded".into()))
+            .is_err());
+    }
 }
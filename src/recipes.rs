@@ -0,0 +1,771 @@
+//! Recipes behind the local `how do I ...` suggestions.
+//!
+//! The built-in recipes are merged with the user's own from `recipes.json`
+//! and from every `*.json` file in the `recipes` directory next to
+//! `config.json`, so a team can ship its runbook snippets as one file. A
+//! recipe with the name of an earlier one replaces it.
+//!
+//! Recipes are ranked by how much of the question their keywords cover.
+//! Both are reduced to terms first: stop words are dropped, words are cut
+//! to their stem and synonyms replaced by one word of their group, so "how
+//! do I tar a directory" finds the recipe for compressing folders. Terms
+//! that many recipes share, like "file", count for less.
+//!
+//! A recipe may have variants for some operating systems or flavors of
+//! tools, such as BSD `sed` or util-linux `rename`. Commands of the chosen
+//! suggestion that are not in `PATH` are pointed out.
+
+use crate::config::get_config_path;
+use crate::context::tool_flavors;
+use crate::util::find_in_path;
+use colored::Colorize;
+use once_cell::unsync::OnceCell;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where recipes without a file come from
+pub const BUILT_IN: &str = "built-in";
+
+/// A suggestion for a kind of task, and when to offer it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    /// Regular expressions matched against the query, ignoring case
+    #[serde(default, rename = "match", skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// Words or phrases of which any in the query selects the recipe
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    pub suggestion: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Operating systems the recipe is for, as in `std::env::consts::OS`,
+    /// or "unix" / "windows". Empty for all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    /// Suggestions for some platforms in place of `suggestion`; the first
+    /// that fits is used
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<Variant>,
+    /// "built-in" or the file the recipe was read from
+    #[serde(default, skip_deserializing)]
+    pub source: String,
+    #[serde(skip)]
+    regexes: Vec<Regex>,
+    /// Terms of each keyword
+    #[serde(skip)]
+    keyword_terms: Vec<Vec<String>>,
+}
+
+/// A recipe's suggestion for some platforms only
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    /// Operating systems, as for a recipe. Empty for all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    /// Flavor of the installed tools, one of [`FLAVORS`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flavor: Option<String>,
+    pub suggestion: String,
+}
+
+/// Flavors that variants can be for: the kind of core utilities, and which
+/// `rename` is installed
+pub const FLAVORS: &[&str] = &["gnu", "bsd", "busybox", "perl-rename", "util-linux-rename"];
+
+/// The platform suggestions are chosen for. Finding out the flavors of the
+/// installed tools runs some of them, so it is only done once a variant
+/// asks for a flavor.
+#[derive(Default)]
+pub struct Platform {
+    flavors: OnceCell<Vec<&'static str>>,
+}
+
+impl Platform {
+    fn has_flavor(&self, flavor: &str) -> bool {
+        self.flavors.get_or_init(tool_flavors).contains(&flavor)
+    }
+}
+
+/// A recipe that fits a question, with a score from 0 to 1
+#[derive(Debug)]
+pub struct Ranked<'a> {
+    pub recipe: &'a Recipe,
+    pub score: f64,
+}
+
+/// Layout of a recipe file
+#[derive(Deserialize)]
+struct RecipeFile {
+    recipes: Vec<Recipe>,
+}
+
+/// Name, keywords, suggestion and tags of each built-in recipe
+const BUILT_IN_RECIPES: &[(&str, &[&str], &str, &[&str])] = &[
+    (
+        "list-files",
+        &["list", "file", "directory", "content"],
+        "ls -la           # detailed list\nls -lah          # with human-readable sizes\nls -lt           # sorted by time",
+        &["files"],
+    ),
+    (
+        "compress",
+        &["compress", "file", "directory"],
+        "tar -czvf archive.tar.gz folder/\nzip -r archive.zip folder/",
+        &["files", "archive"],
+    ),
+    (
+        "find-files",
+        &["find", "file", "directory", "name"],
+        "find /path -name \"pattern\"\nlocate filename",
+        &["files", "search"],
+    ),
+    (
+        "replace-text",
+        &["replace", "text", "file"],
+        "sed -i 's/old/new/g' file\nfind . -name \"*.txt\" -exec sed -i 's/old/new/g' {} +",
+        &["text", "files"],
+    ),
+    (
+        "count",
+        &["count", "line", "word"],
+        "wc -l filename    # lines\nwc -w filename    # words",
+        &["text"],
+    ),
+    (
+        "search-text",
+        &["search", "text", "content", "inside", "pattern"],
+        "grep \"pattern\" file\ngrep -r \"pattern\" folder/",
+        &["text", "search"],
+    ),
+    (
+        "rename",
+        &["rename", "file", "multiple"],
+        "mv oldname newname\nfor f in *old*; do mv \"$f\" \"$(printf '%s' \"$f\" | sed 's/old/new/')\"; done",
+        &["files"],
+    ),
+    (
+        "delete",
+        &["delete", "file", "directory"],
+        "rm filename\nrm -r folder/\nfind . -name \"*.tmp\" -delete",
+        &["files"],
+    ),
+    (
+        "copy",
+        &["copy", "file", "directory"],
+        "cp source dest\ncp -r folder/ dest/\nrsync -av source/ dest/",
+        &["files"],
+    ),
+    (
+        "permissions",
+        &["permission", "owner"],
+        "chmod +x script.sh\nchmod 755 file\nchmod -R 644 folder/",
+        &["files"],
+    ),
+    (
+        "processes",
+        &["process", "running", "kill"],
+        "ps aux | grep name\npgrep name\nkill PID\npkill name",
+        &["system"],
+    ),
+    (
+        "disk-usage",
+        &["disk", "space", "usage", "size", "full"],
+        "df -h\ndu -sh folder/\ndu -sh * | sort -h",
+        &["system", "files"],
+    ),
+    (
+        "download",
+        &["download", "url"],
+        "curl -O url\nwget url",
+        &["network"],
+    ),
+];
+
+/// Recipe, operating systems, flavor and suggestion of each variant of a
+/// built-in recipe
+const BUILT_IN_VARIANTS: &[(&str, &[&str], &str, &str)] = &[
+    (
+        "find-files",
+        &["macos"],
+        "",
+        "find /path -name \"pattern\"\nmdfind -name filename    # Spotlight index",
+    ),
+    (
+        "replace-text",
+        &[],
+        "bsd",
+        "sed -i '' 's/old/new/g' file\nfind . -name \"*.txt\" -exec sed -i '' 's/old/new/g' {} +",
+    ),
+    (
+        "rename",
+        &[],
+        "perl-rename",
+        "mv oldname newname\nrename 's/old/new/' files*",
+    ),
+    (
+        "rename",
+        &[],
+        "util-linux-rename",
+        "mv oldname newname\nrename old new files*    # replaces the first \"old\" in each name",
+    ),
+    (
+        "disk-usage",
+        &[],
+        "gnu",
+        "df -h\ndu -sh folder/\ndu -h --max-depth=1 | sort -h",
+    ),
+    (
+        "disk-usage",
+        &[],
+        "bsd",
+        "df -h\ndu -sh folder/\ndu -h -d 1 | sort -h",
+    ),
+];
+
+/// Words that run the command after them
+const COMMAND_PREFIXES: &[&str] = &["sudo", "do", "then", "else", "time", "nohup", "exec", "env"];
+
+/// Shell keywords and builtins, which are not looked up in `PATH`
+const SHELL_WORDS: &[&str] = &[
+    "case", "cd", "done", "echo", "elif", "esac", "exit", "export", "false", "fi", "for", "if",
+    "kill", "read", "set", "source", "test", "true", "until", "while",
+];
+
+/// Words that say nothing about the task
+const STOP_WORDS: &[&str] = &[
+    "a", "all", "an", "and", "any", "are", "best", "can", "could", "do", "does", "for", "from",
+    "how", "i", "in", "into", "is", "it", "me", "my", "of", "on", "or", "please", "see", "should",
+    "some", "that", "the", "them", "there", "these", "this", "those", "to", "using", "way", "what",
+    "which", "with", "would", "you",
+];
+
+/// Words that mean the same in a question. The first of each group stands
+/// for the others.
+const SYNONYMS: &[&[&str]] = &[
+    &["delete", "remove", "rm", "erase", "unlink", "wipe"],
+    &["compress", "zip", "tar", "gzip", "archive", "pack"],
+    &["directory", "folder", "dir"],
+    &["find", "locate"],
+    &["search", "grep"],
+    &["list", "ls"],
+    &["copy", "cp", "duplicate"],
+    &["rename", "mv"],
+    &["kill", "terminate", "stop"],
+    &["process", "program"],
+    &["download", "fetch", "wget", "curl"],
+    &["disk", "drive", "storage"],
+    &["permission", "chmod", "executable"],
+    &["count", "number", "wc"],
+    &["replace", "substitute", "sed"],
+    &["size", "big", "large", "huge"],
+];
+
+impl Recipe {
+    /// Whether the recipe is meant for the operating system `ask` runs on
+    pub fn applies_here(&self) -> bool {
+        is_this_os(&self.os)
+    }
+
+    /// The suggestion for `platform`: the first variant for it whose
+    /// commands are all installed, else the first variant for it, else the
+    /// recipe's own suggestion
+    pub fn suggestion_for(&self, platform: &Platform) -> &str {
+        let fitting: Vec<&Variant> = self
+            .variants
+            .iter()
+            .filter(|variant| is_this_os(&variant.os))
+            .filter(|variant| match &variant.flavor {
+                Some(flavor) => platform.has_flavor(flavor),
+                None => true,
+            })
+            .collect();
+        fitting
+            .iter()
+            .find(|variant| missing_commands(&variant.suggestion).is_empty())
+            .or(fitting.first())
+            .map_or(&self.suggestion, |variant| &variant.suggestion)
+    }
+
+    /// Terms of the question that the recipe's keywords cover. A keyword
+    /// of several words only counts when all of them are there.
+    fn covered<'a>(&self, question: &'a [String]) -> Vec<&'a String> {
+        question
+            .iter()
+            .filter(|term| {
+                self.keyword_terms.iter().any(|keyword| {
+                    keyword.contains(term) && keyword.iter().all(|t| question.contains(t))
+                })
+            })
+            .collect()
+    }
+
+    fn has_term(&self, term: &str) -> bool {
+        self.keyword_terms
+            .iter()
+            .any(|keyword| keyword.iter().any(|t| t == term))
+    }
+
+    /// Whether `filter` is the recipe's name or one of its tags
+    pub fn is_selected_by(&self, filter: &str) -> bool {
+        self.name.eq_ignore_ascii_case(filter)
+            || self.tags.iter().any(|tag| tag.eq_ignore_ascii_case(filter))
+    }
+
+    /// Check the recipe and compile its patterns
+    fn prepare(mut self, source: &str) -> Result<Self, String> {
+        if self.name.trim().is_empty() {
+            return Err("no name".to_string());
+        }
+        if self.suggestion.trim().is_empty() {
+            return Err("no suggestion".to_string());
+        }
+        if self.patterns.is_empty() && self.keywords.is_empty() {
+            return Err("needs a \"match\" pattern or \"keywords\"".to_string());
+        }
+        for variant in &self.variants {
+            if variant.suggestion.trim().is_empty() {
+                return Err("a variant has no suggestion".to_string());
+            }
+            if let Some(flavor) = variant.flavor.as_deref() {
+                if !FLAVORS.contains(&flavor) {
+                    return Err(format!(
+                        "unknown flavor '{}', expected one of: {}",
+                        flavor,
+                        FLAVORS.join(", ")
+                    ));
+                }
+            }
+        }
+        self.regexes = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("invalid pattern: {}", e))
+            })
+            .collect::<Result<_, _>>()?;
+        self.keyword_terms = self
+            .keywords
+            .iter()
+            .map(|keyword| terms(keyword))
+            .filter(|terms| !terms.is_empty())
+            .collect();
+        self.source = source.to_string();
+        Ok(self)
+    }
+}
+
+/// Path of the user's recipe file, next to `config.json`
+pub fn recipes_path() -> PathBuf {
+    get_config_path().with_file_name("recipes.json")
+}
+
+/// Directory of further recipe files, e.g. a team's runbook
+pub fn recipes_dir() -> PathBuf {
+    get_config_path().with_file_name("recipes")
+}
+
+/// The built-in recipes with the user's merged in. Files that cannot be
+/// read and invalid recipes are skipped with a warning.
+pub fn catalog() -> Vec<Recipe> {
+    let mut recipes = built_in();
+    for path in recipe_files() {
+        let loaded = match read_file(&path) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                warn(&path, &format!("not read: {}", e));
+                continue;
+            }
+        };
+        for recipe in loaded {
+            let name = recipe.name.clone();
+            match recipe.prepare(&path.display().to_string()) {
+                Ok(recipe) => merge(&mut recipes, recipe),
+                Err(e) => warn(&path, &format!("skipping recipe '{}': {}", name, e)),
+            }
+        }
+    }
+    recipes
+}
+
+fn built_in() -> Vec<Recipe> {
+    BUILT_IN_RECIPES
+        .iter()
+        .map(|(name, keywords, suggestion, tags)| Recipe {
+            name: name.to_string(),
+            patterns: Vec::new(),
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            suggestion: suggestion.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            os: Vec::new(),
+            variants: BUILT_IN_VARIANTS
+                .iter()
+                .filter(|(recipe, ..)| recipe == name)
+                .map(|(_, os, flavor, suggestion)| Variant {
+                    os: os.iter().map(|os| os.to_string()).collect(),
+                    flavor: (!flavor.is_empty()).then(|| flavor.to_string()),
+                    suggestion: suggestion.to_string(),
+                })
+                .collect(),
+            source: String::new(),
+            regexes: Vec::new(),
+            keyword_terms: Vec::new(),
+        })
+        .map(|recipe| recipe.prepare(BUILT_IN).expect("built-in recipe"))
+        .collect()
+}
+
+/// `recipes` that fit `question`, best first. A recipe whose `match`
+/// pattern fits scores 1; otherwise the score is the share of the
+/// question's terms, by weight, that its keywords cover.
+pub fn rank<'a>(recipes: &'a [Recipe], question: &str) -> Vec<Ranked<'a>> {
+    let mut question_terms = terms(question);
+    question_terms.sort_unstable();
+    question_terms.dedup();
+    // A term of many recipes says little about which one is meant. Terms of
+    // none count fully, since the recipe is then missing part of the task.
+    let weight = |term: &String| match recipes.iter().filter(|r| r.has_term(term)).count() {
+        0 => 1.0,
+        n => 1.0 / n as f64,
+    };
+    let total: f64 = question_terms.iter().map(weight).sum();
+
+    let mut ranked: Vec<Ranked> = recipes
+        .iter()
+        .filter_map(|recipe| {
+            let score = if recipe.regexes.iter().any(|regex| regex.is_match(question)) {
+                1.0
+            } else if total > 0.0 {
+                recipe
+                    .covered(&question_terms)
+                    .into_iter()
+                    .map(weight)
+                    .sum::<f64>()
+                    / total
+            } else {
+                0.0
+            };
+            (score > 0.0).then_some(Ranked { recipe, score })
+        })
+        .collect();
+    // Stable, so equal scores keep the order of the catalog
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked
+}
+
+/// The terms of a question or keyword: its words without stop words, cut
+/// to their stem, with synonyms replaced by the first of their group
+fn terms(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !STOP_WORDS.contains(word))
+        .map(|word| {
+            let stem = stem(word);
+            SYNONYMS
+                .iter()
+                .find(|group| group.iter().any(|synonym| self::stem(synonym) == stem))
+                .map(|group| self::stem(group[0]))
+                .unwrap_or(stem)
+        })
+        .collect()
+}
+
+/// Cut common English endings, so that "files", "deleted" and "deleting"
+/// become "file", "delet" and "delet". The stems need not be words, only
+/// agree with each other.
+fn stem(word: &str) -> String {
+    let mut stem = word.to_string();
+    if stem.len() > 4 && stem.ends_with("sses") {
+        stem.truncate(stem.len() - 2);
+    } else if stem.len() > 4 && stem.ends_with("ies") {
+        stem.truncate(stem.len() - 3);
+        stem.push('y');
+    } else if stem.len() > 3
+        && stem.ends_with('s')
+        && !stem.ends_with("ss")
+        && !stem.ends_with("us")
+    {
+        stem.pop();
+    }
+    for suffix in ["ing", "ed"] {
+        if stem.len() > suffix.len() + 2 && stem.ends_with(suffix) {
+            stem.truncate(stem.len() - suffix.len());
+            // "running" -> "run", but "compressing" -> "compress"
+            let mut last = stem.chars().rev();
+            if let (Some(a), Some(b)) = (last.next(), last.next()) {
+                if a == b && !matches!(a, 'l' | 's' | 'z') && !"aeiou".contains(a) {
+                    stem.pop();
+                }
+            }
+            break;
+        }
+    }
+    if stem.len() > 4 && stem.ends_with('e') {
+        stem.pop();
+    }
+    stem
+}
+
+/// Commands of `suggestion` that are not in `PATH`
+pub fn missing_commands(suggestion: &str) -> Vec<&str> {
+    let mut missing = Vec::new();
+    for command in commands(suggestion) {
+        if !missing.contains(&command) && find_in_path(command).is_none() {
+            missing.push(command);
+        }
+    }
+    missing
+}
+
+/// The commands a suggestion runs: the first word of each line and of each
+/// command in a pipeline or list, without comments and shell builtins
+fn commands(suggestion: &str) -> Vec<&str> {
+    suggestion
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .map(|line| line.split(" #").next().unwrap_or_default())
+        .flat_map(|line| line.split(['|', ';', '&']))
+        .filter_map(|command| {
+            command
+                .split_whitespace()
+                .find(|word| !COMMAND_PREFIXES.contains(word) && !word.contains('='))
+        })
+        .filter(|word| {
+            !SHELL_WORDS.contains(word)
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c))
+        })
+        .collect()
+}
+
+/// Whether `os`, a list of operating systems, includes this one. An empty
+/// list includes all.
+fn is_this_os(os: &[String]) -> bool {
+    os.is_empty()
+        || os
+            .iter()
+            .any(|os| os.eq_ignore_ascii_case(env::consts::OS) || os == env::consts::FAMILY)
+}
+
+/// `recipes.json`, then the files in `recipes/` in name order
+fn recipe_files() -> Vec<PathBuf> {
+    let mut files = vec![recipes_path()];
+    if let Ok(entries) = fs::read_dir(recipes_dir()) {
+        let mut in_dir: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        in_dir.sort();
+        files.extend(in_dir);
+    }
+    files.retain(|path| path.is_file());
+    files
+}
+
+fn read_file(path: &Path) -> Result<Vec<Recipe>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    parse(&content)
+}
+
+fn parse(content: &str) -> Result<Vec<Recipe>, String> {
+    serde_json::from_str::<RecipeFile>(content)
+        .map(|file| file.recipes)
+        .map_err(|e| e.to_string())
+}
+
+/// Add `recipe`, replacing one of the same name
+fn merge(recipes: &mut Vec<Recipe>, recipe: Recipe) {
+    match recipes.iter_mut().find(|r| r.name == recipe.name) {
+        Some(existing) => *existing = recipe,
+        None => recipes.push(recipe),
+    }
+}
+
+fn warn(path: &Path, message: &str) {
+    eprintln!("{} {}: {}", "Warning:".yellow(), path.display(), message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(json: &str) -> Result<Recipe, String> {
+        parse(&format!("{{\"recipes\": [{}]}}", json))?
+            .remove(0)
+            .prepare("test.json")
+    }
+
+    #[test]
+    fn test_built_in_recipes() {
+        let recipes = built_in();
+        let compress = recipes.iter().find(|r| r.name == "compress").unwrap();
+        assert!(compress.is_selected_by("Archive"));
+        assert!(recipes.iter().all(|r| r.source == BUILT_IN));
+    }
+
+    #[test]
+    fn test_keywords() {
+        let recipe = recipe(
+            r#"{"name": "deploy", "keywords": ["rollback", "feature flag"],
+                "suggestion": "deployctl rollback api"}"#,
+        )
+        .unwrap();
+        let recipes = [recipe];
+        let score = |question: &str| rank(&recipes, question).first().map(|r| r.score);
+        assert_eq!(score("how do i rollback"), Some(1.0));
+        assert_eq!(score("how to turn off a feature flag"), Some(0.5));
+        // Both words of a phrase are needed
+        assert_eq!(score("how do I plant a flag"), None);
+        assert_eq!(recipes[0].source, "test.json");
+    }
+
+    #[test]
+    fn test_terms() {
+        assert_eq!(terms("How do I delete the files?"), ["delet", "file"]);
+        assert_eq!(terms("removing folders"), ["delet", "directory"]);
+        assert_eq!(terms("tar up a directory"), ["compress", "up", "directory"]);
+        assert_eq!(stem("running"), "run");
+        assert_eq!(stem("compressing"), "compress");
+        assert_eq!(stem("processes"), "process");
+        assert_eq!(stem("copies"), "copy");
+    }
+
+    #[test]
+    fn test_rank() {
+        let recipes = built_in();
+        let names = |question: &str| -> Vec<&str> {
+            rank(&recipes, question)
+                .iter()
+                .map(|ranked| ranked.recipe.name.as_str())
+                .collect()
+        };
+        assert_eq!(names("how do I zip")[0], "compress");
+        assert_eq!(names("how do I tar a directory")[0], "compress");
+        assert_eq!(
+            names("how do I remove a running process")[..2],
+            ["processes", "delete"]
+        );
+        assert_eq!(names("how to delete files")[0], "delete");
+        assert!(names("how do I set up a reverse proxy").is_empty());
+
+        // "file" is in many recipes, so it adds little
+        let ranked = rank(&recipes, "how to delete files");
+        assert_eq!(ranked[0].score, 1.0);
+        assert!(ranked[1].score < 0.2);
+
+        // A repeated term counts once, wherever it appears
+        let scores = |question: &str| -> Vec<f64> {
+            rank(&recipes, question)
+                .iter()
+                .map(|ranked| ranked.score)
+                .collect()
+        };
+        assert_eq!(
+            scores("find files in a folder by file name"),
+            scores("find files in a folder by name")
+        );
+    }
+
+    #[test]
+    fn test_invalid_recipes() {
+        assert!(recipe(r#"{"name": "x", "suggestion": "y"}"#)
+            .unwrap_err()
+            .contains("needs a \"match\""));
+        assert!(
+            recipe(r#"{"name": "x", "match": ["("], "suggestion": "y"}"#)
+                .unwrap_err()
+                .contains("invalid pattern")
+        );
+        assert!(parse(r#"{"recipes": [{"name": "x"}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_os_filter() {
+        let mut recipe = recipe(r#"{"name": "x", "match": ["x"], "suggestion": "y"}"#).unwrap();
+        assert!(recipe.applies_here());
+        recipe.os = vec![env::consts::FAMILY.to_string()];
+        assert!(recipe.applies_here());
+        recipe.os = vec!["plan9".to_string()];
+        assert!(!recipe.applies_here());
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(
+            commands("ps aux | grep name\nsudo kill PID\nls -la    # detailed list"),
+            ["ps", "grep", "ls"]
+        );
+        assert_eq!(
+            commands(
+                "for f in *old*; do mv \"$f\" \"$(printf '%s' \"$f\" | sed 's/old/new/')\"; done"
+            ),
+            ["mv", "sed"]
+        );
+        assert_eq!(commands("LC_ALL=C sort file && ./build.sh"), ["sort"]);
+        assert_eq!(
+            missing_commands("ls\nno-such-command-here --help"),
+            ["no-such-command-here"]
+        );
+    }
+
+    #[test]
+    fn test_variants() {
+        let sed = recipe(
+            r#"{"name": "sed", "keywords": ["sed"], "suggestion": "sed -i 's/a/b/' file",
+                "variants": [
+                    {"os": ["plan9"], "suggestion": "not here"},
+                    {"flavor": "bsd", "suggestion": "sed -i '' 's/a/b/' file"},
+                    {"suggestion": "no-such-command-here file"},
+                    {"suggestion": "ls"}
+                ]}"#,
+        )
+        .unwrap();
+        let bsd = Platform {
+            flavors: OnceCell::with_value(vec!["bsd"]),
+        };
+        assert_eq!(sed.suggestion_for(&bsd), "sed -i '' 's/a/b/' file");
+        // Variants whose commands are installed come first
+        let gnu = Platform {
+            flavors: OnceCell::with_value(vec!["gnu"]),
+        };
+        assert_eq!(sed.suggestion_for(&gnu), "ls");
+
+        assert!(recipe(
+            r#"{"name": "x", "keywords": ["x"], "suggestion": "y",
+                "variants": [{"flavor": "macports", "suggestion": "z"}]}"#
+        )
+        .unwrap_err()
+        .contains("unknown flavor"));
+    }
+
+    #[test]
+    fn test_match_patterns_score_fully() {
+        let recipes =
+            [
+                recipe(r#"{"name": "x", "match": ["^how do i deploy"], "suggestion": "y"}"#)
+                    .unwrap(),
+            ];
+        assert_eq!(rank(&recipes, "how do I deploy to staging")[0].score, 1.0);
+        assert!(rank(&recipes, "how do I undeploy").is_empty());
+    }
+
+    #[test]
+    fn test_merge_replaces_by_name() {
+        let mut recipes = built_in();
+        let count = recipes.len();
+        let mine =
+            recipe(r#"{"name": "download", "match": ["fetch"], "suggestion": "aria2c url"}"#)
+                .unwrap();
+        merge(&mut recipes, mine);
+        assert_eq!(recipes.len(), count);
+        let download = recipes.iter().find(|r| r.name == "download").unwrap();
+        assert_eq!(download.suggestion, "aria2c url");
+    }
+}
@@ -0,0 +1,47 @@
+use crate::error::Result;
+use crate::markdown::MarkdownWriter;
+use crate::providers::streaming::{Completion, EventStream, StreamEvent};
+use futures::StreamExt;
+use std::io::Write;
+
+/// Write response text to `out` as it streams in, returning the full
+/// completion. With `markdown`, each line is rendered as Markdown once it is
+/// complete; otherwise the text is written as it arrives.
+pub async fn render(
+    mut stream: EventStream,
+    out: &mut dyn Write,
+    markdown: bool,
+) -> Result<Completion> {
+    let mut completion = Completion::default();
+    let mut wrote_text = false;
+    let mut markdown = markdown.then(MarkdownWriter::new);
+
+    while let Some(event) = stream.next().await {
+        let event = event?;
+        if let StreamEvent::Text(text) = &event {
+            match &mut markdown {
+                Some(writer) => writer.write(text, out)?,
+                None => {
+                    write!(out, "{}", text)?;
+                    out.flush()?;
+                }
+            }
+            wrote_text = true;
+        }
+        if let Err(e) = completion.push(event) {
+            if let Some(writer) = &mut markdown {
+                writer.finish(out)?;
+            }
+            if wrote_text {
+                writeln!(out)?;
+            }
+            return Err(e);
+        }
+    }
+
+    if let Some(writer) = &mut markdown {
+        writer.finish(out)?;
+    }
+    writeln!(out)?; // Final newline
+    Ok(completion)
+}
@@ -0,0 +1,185 @@
+use crate::config::{get_data_dir, Config};
+use crate::error::Result;
+use crate::handlers;
+use crate::handlers::code::{self, CodeAction};
+use crate::output::Output;
+use crate::session::Session;
+use colored::Colorize;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::path::PathBuf;
+
+const PROMPT: &str = "ask> ";
+const CONTINUATION_PROMPT: &str = "...> ";
+
+const REPL_HELP: &str = "Commands:
+  /provider [name]  Show or switch the provider for this session
+  /model [name]     Show or switch the model for this session
+  /clear            Forget the conversation so far
+  /copy N           Copy code block N of the last answer to the clipboard
+  /run N            Run code block N of the last answer
+  /save N FILE      Save code block N of the last answer to FILE
+  /help             Show this help
+  /exit             Leave interactive mode (also Ctrl-D)
+
+End a line with \\ to continue typing on the next line.";
+
+/// Result of a slash command
+enum Flow {
+    Continue,
+    Exit,
+}
+
+/// Run the interactive loop until the user exits.
+///
+/// Every query goes through normal intent detection; AI questions share the
+/// conversation in `session`, which is only persisted if it was opened by name.
+pub async fn run(config: &mut Config, session: Option<Session>, output: &Output) -> Result<()> {
+    let mut session = session.unwrap_or_else(Session::transient);
+    let output = &Output {
+        interactive: true,
+        ..*output
+    };
+    let mut editor = DefaultEditor::new()?;
+    let history_path = history_path();
+    // A missing history file just means this is the first run
+    let _ = editor.load_history(&history_path);
+
+    println!(
+        "ask {} interactive mode ({} / {}). Type /help for commands.",
+        env!("CARGO_PKG_VERSION"),
+        config.provider,
+        config.effective_model()
+    );
+    if !session.is_transient() && !session.messages.is_empty() {
+        println!(
+            "Continuing session '{}' ({} turns)",
+            session.name,
+            session.turns()
+        );
+    }
+
+    loop {
+        let input = match read_input(&mut editor) {
+            Ok(Some(input)) => input,
+            Ok(None) => break,
+            Err(e) => {
+                e.report();
+                break;
+            }
+        };
+
+        let input = input.trim();
+        if input.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(input);
+
+        if let Some(command) = input.strip_prefix('/') {
+            match handle_command(command, config, &mut session) {
+                Ok(Flow::Continue) => continue,
+                Ok(Flow::Exit) => break,
+                Err(e) => {
+                    e.report();
+                    continue;
+                }
+            }
+        }
+
+        // Errors are reported and the loop keeps going
+        if let Err(e) = handlers::dispatch(input, &[], config, Some(&mut session), output).await {
+            e.report();
+        }
+    }
+
+    if let Some(parent) = history_path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = editor.save_history(&history_path);
+    Ok(())
+}
+
+/// Read one logical input, joining lines that end with a backslash.
+/// Returns `None` on end of input.
+fn read_input(editor: &mut DefaultEditor) -> Result<Option<String>> {
+    let mut input = String::new();
+    let mut prompt = PROMPT;
+
+    loop {
+        match editor.readline(prompt) {
+            Ok(line) => match line.strip_suffix('\\') {
+                Some(partial) => {
+                    input.push_str(partial);
+                    input.push('\n');
+                    prompt = CONTINUATION_PROMPT;
+                }
+                None => {
+                    input.push_str(&line);
+                    return Ok(Some(input));
+                }
+            },
+            // Ctrl-C discards the current input but keeps the REPL running
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                prompt = PROMPT;
+            }
+            Err(ReadlineError::Eof) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn handle_command(command: &str, config: &mut Config, session: &mut Session) -> Result<Flow> {
+    let (name, arg) = match command.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (command, ""),
+    };
+
+    match name {
+        "exit" | "quit" | "q" => return Ok(Flow::Exit),
+        "help" | "?" => println!("{}", REPL_HELP),
+        "clear" => {
+            session.clear();
+            session.save()?;
+            println!("Conversation cleared.");
+        }
+        "provider" => {
+            if !arg.is_empty() {
+                config.set("provider", arg)?;
+                // The previous model and key belong to the old provider
+                config.model = None;
+                config.use_provider_key();
+            }
+            println!(
+                "provider = {} (model {})",
+                config.provider.to_string().green(),
+                config.effective_model()
+            );
+        }
+        "model" => {
+            if !arg.is_empty() {
+                config.set("model", arg)?;
+            }
+            println!("model = {}", config.effective_model().green());
+        }
+        "copy" => code::act(&CodeAction::Copy(code::parse_number(arg)?), config)?,
+        "run" => code::act(&CodeAction::Run(code::parse_number(arg)?), config)?,
+        "save" => match arg.split_once(char::is_whitespace) {
+            Some((number, file)) => code::act(
+                &CodeAction::Save(code::parse_number(number)?, PathBuf::from(file.trim())),
+                config,
+            )?,
+            None => println!("Usage: /save N FILE"),
+        },
+        _ => {
+            println!("{}: /{}", "Unknown command".red(), name);
+            println!("Type /help for available commands.");
+        }
+    }
+
+    Ok(Flow::Continue)
+}
+
+fn history_path() -> PathBuf {
+    get_data_dir().join("history")
+}
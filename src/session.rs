@@ -0,0 +1,223 @@
+use crate::config::{get_data_dir, write_private};
+use crate::error::{AskError, Result};
+use crate::providers::{Message, Role};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name used by `--continue` when no session exists yet
+pub const DEFAULT_SESSION: &str = "default";
+
+/// A named conversation persisted between invocations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub name: String,
+    /// Creation time in seconds since the Unix epoch
+    pub created: u64,
+    /// Last update time in seconds since the Unix epoch
+    pub updated: u64,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+    /// In-memory sessions are never written to disk
+    #[serde(skip)]
+    transient: bool,
+}
+
+impl Session {
+    pub fn new(name: &str) -> Result<Self> {
+        validate_name(name)?;
+        let now = now();
+        Ok(Session {
+            name: name.to_string(),
+            created: now,
+            updated: now,
+            messages: Vec::new(),
+            transient: false,
+        })
+    }
+
+    /// A session that keeps context in memory only, e.g. for the REPL
+    pub fn transient() -> Self {
+        let now = now();
+        Session {
+            name: String::new(),
+            created: now,
+            updated: now,
+            messages: Vec::new(),
+            transient: true,
+        }
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+
+    /// Load a session by name, or start a new one if it doesn't exist
+    pub fn open(name: &str) -> Result<Self> {
+        match Self::load(name) {
+            Ok(session) => Ok(session),
+            Err(AskError::SessionNotFound(_)) => Self::new(name),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Open the most recently updated session, or the default one
+    pub fn open_latest() -> Result<Self> {
+        match list()?.into_iter().next() {
+            Some(latest) => Ok(latest),
+            None => Self::open(DEFAULT_SESSION),
+        }
+    }
+
+    pub fn load(name: &str) -> Result<Self> {
+        validate_name(name)?;
+        let path = session_path(name);
+        if !path.exists() {
+            return Err(AskError::SessionNotFound(name.to_string()));
+        }
+        let content = fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if self.transient {
+            return Ok(());
+        }
+        // Sessions hold whole conversations, attachments included
+        let content = serde_json::to_string_pretty(self)?;
+        write_private(&session_path(&self.name), &content)
+    }
+
+    /// Record a completed exchange
+    pub fn push_exchange(&mut self, query: &str, response: &str) {
+        self.messages.push(Message::user(query));
+        self.messages.push(Message::assistant(response));
+        self.updated = now();
+    }
+
+    /// Forget all previous turns
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.updated = now();
+    }
+
+    /// Number of user turns in the session
+    pub fn turns(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|m| m.role == Role::User)
+            .count()
+    }
+}
+
+/// All saved sessions, most recently updated first
+pub fn list() -> Result<Vec<Session>> {
+    let dir = sessions_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        // Skip unreadable files rather than failing the whole listing
+        if let Ok(content) = fs::read_to_string(&path) {
+            if let Ok(session) = serde_json::from_str::<Session>(&content) {
+                sessions.push(session);
+            }
+        }
+    }
+
+    sessions.sort_by(|a, b| b.updated.cmp(&a.updated).then(a.name.cmp(&b.name)));
+    Ok(sessions)
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    validate_name(name)?;
+    let path = session_path(name);
+    if !path.exists() {
+        return Err(AskError::SessionNotFound(name.to_string()));
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+pub fn sessions_dir() -> PathBuf {
+    get_data_dir().join("sessions")
+}
+
+fn session_path(name: &str) -> PathBuf {
+    sessions_dir().join(format!("{}.json", name))
+}
+
+/// Session names become file names, so keep them to a safe character set
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(AskError::InvalidSessionName(name.to_string()))
+    }
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Human-readable age of a timestamp, e.g. "5m ago"
+pub fn format_age(timestamp: u64) -> String {
+    let secs = now().saturating_sub(timestamp);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("work").is_ok());
+        assert!(validate_name("bug-123_v2.1").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../etc/passwd").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("a b").is_err());
+    }
+
+    #[test]
+    fn test_push_exchange() {
+        let mut session = Session::new("test").unwrap();
+        session.push_exchange("list files", "ls -la");
+        session.push_exchange("now recursively", "ls -laR");
+        assert_eq!(session.messages.len(), 4);
+        assert_eq!(session.turns(), 2);
+        assert_eq!(session.messages[1], Message::assistant("ls -la"));
+    }
+
+    #[test]
+    fn test_session_roundtrip() {
+        let mut session = Session::new("roundtrip").unwrap();
+        session.push_exchange("hello", "hi there");
+        let json = serde_json::to_string(&session).unwrap();
+        let loaded: Session = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.name, "roundtrip");
+        assert_eq!(loaded.messages, session.messages);
+    }
+}
@@ -0,0 +1,968 @@
+//! Native system information for Linux, read from `/proc` and `statvfs`.
+//!
+//! Collectors return plain data that is either rendered as a table or
+//! serialized for `--json`. Parsing is kept separate from file access so it
+//! can be tested without a real `/proc`.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Filesystem types that never hold user data
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "securityfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// Data that can be shown as a table or serialized to JSON
+pub trait Report: Serialize {
+    fn table(&self) -> Table;
+}
+
+/// Left-aligned text columns, optionally with a header row
+#[derive(Debug, Default)]
+pub struct Table {
+    header: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    /// Printed instead of the table when there are no rows
+    empty: &'static str,
+}
+
+impl Table {
+    fn with_header(header: &[&str]) -> Self {
+        Table {
+            header: Some(header.iter().map(|h| h.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// A two-column `Key: value` table
+    fn properties(pairs: Vec<(&str, String)>) -> Self {
+        Table {
+            rows: pairs
+                .into_iter()
+                .map(|(key, value)| vec![format!("{}:", key), value])
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.rows.is_empty() && !self.empty.is_empty() {
+            return writeln!(f, "{}", self.empty);
+        }
+
+        let all_rows = || self.header.iter().chain(self.rows.iter());
+        let columns = all_rows().map(|r| r.len()).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|i| {
+                all_rows()
+                    .filter_map(|r| r.get(i))
+                    .map(|c| c.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        for row in all_rows() {
+            let mut line = String::new();
+            for (i, cell) in row.iter().enumerate() {
+                if i + 1 < row.len() {
+                    line.push_str(&format!("{:<width$}  ", cell, width = widths[i]));
+                } else {
+                    line.push_str(cell);
+                }
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+// --- Memory ---------------------------------------------------------------
+
+/// Memory usage in bytes, computed the same way as `free`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Memory {
+    pub total: u64,
+    pub used: u64,
+    pub free: u64,
+    pub shared: u64,
+    pub buff_cache: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_used: u64,
+    pub swap_free: u64,
+}
+
+pub fn memory() -> io::Result<Memory> {
+    parse_meminfo(&fs::read_to_string("/proc/meminfo")?)
+}
+
+fn parse_meminfo(content: &str) -> io::Result<Memory> {
+    // Values are in kB
+    let fields: HashMap<&str, u64> = content
+        .lines()
+        .filter_map(|line| {
+            let (key, rest) = line.split_once(':')?;
+            let value: u64 = rest.split_whitespace().next()?.parse().ok()?;
+            Some((key, value * 1024))
+        })
+        .collect();
+    let get = |key: &str| fields.get(key).copied().unwrap_or(0);
+
+    let total = *fields
+        .get("MemTotal")
+        .ok_or_else(|| invalid_data("/proc/meminfo has no MemTotal"))?;
+    let free = get("MemFree");
+    let buff_cache = get("Buffers") + get("Cached") + get("SReclaimable");
+    let swap_total = get("SwapTotal");
+    let swap_free = get("SwapFree");
+
+    Ok(Memory {
+        total,
+        used: total.saturating_sub(free + buff_cache),
+        free,
+        shared: get("Shmem"),
+        buff_cache,
+        available: fields.get("MemAvailable").copied().unwrap_or(free),
+        swap_total,
+        swap_used: swap_total.saturating_sub(swap_free),
+        swap_free,
+    })
+}
+
+impl Report for Memory {
+    fn table(&self) -> Table {
+        let mut table = Table::with_header(&[
+            "",
+            "total",
+            "used",
+            "free",
+            "shared",
+            "buff/cache",
+            "available",
+        ]);
+        table.row(vec![
+            "Mem:".to_string(),
+            format_bytes(self.total),
+            format_bytes(self.used),
+            format_bytes(self.free),
+            format_bytes(self.shared),
+            format_bytes(self.buff_cache),
+            format_bytes(self.available),
+        ]);
+        table.row(vec![
+            "Swap:".to_string(),
+            format_bytes(self.swap_total),
+            format_bytes(self.swap_used),
+            format_bytes(self.swap_free),
+        ]);
+        table
+    }
+}
+
+// --- CPU and load ---------------------------------------------------------
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+    pub running_tasks: u32,
+    pub total_tasks: u32,
+}
+
+impl fmt::Display for LoadAverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}, {:.2}, {:.2}", self.one, self.five, self.fifteen)
+    }
+}
+
+fn load_average() -> io::Result<LoadAverage> {
+    parse_loadavg(&fs::read_to_string("/proc/loadavg")?)
+}
+
+fn parse_loadavg(content: &str) -> io::Result<LoadAverage> {
+    let invalid = || invalid_data("unexpected /proc/loadavg format");
+    let mut fields = content.split_whitespace();
+    let mut next_load = || -> io::Result<f64> {
+        fields
+            .next()
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let (one, five, fifteen) = (next_load()?, next_load()?, next_load()?);
+    let (running, total) = fields
+        .next()
+        .and_then(|f| f.split_once('/'))
+        .ok_or_else(invalid)?;
+
+    Ok(LoadAverage {
+        one,
+        five,
+        fifteen,
+        running_tasks: running.parse().map_err(|_| invalid())?,
+        total_tasks: total.parse().map_err(|_| invalid())?,
+    })
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Cpu {
+    pub model: Option<String>,
+    pub architecture: &'static str,
+    /// Logical processors, including hyperthreads
+    pub logical_cpus: usize,
+    /// Physical cores, when the kernel reports topology
+    pub physical_cores: Option<usize>,
+    pub sockets: usize,
+    pub mhz: Option<f64>,
+    pub load_average: LoadAverage,
+}
+
+pub fn cpu() -> io::Result<Cpu> {
+    let mut cpu = parse_cpuinfo(&fs::read_to_string("/proc/cpuinfo")?, load_average()?);
+    if cpu.logical_cpus == 0 {
+        // Some architectures omit "processor" entries
+        cpu.logical_cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    }
+    Ok(cpu)
+}
+
+fn parse_cpuinfo(content: &str, load_average: LoadAverage) -> Cpu {
+    let mut model = None;
+    let mut mhz = None;
+    let mut logical_cpus = 0;
+    let mut sockets = HashSet::new();
+    let mut cores = HashSet::new();
+    let mut physical_id = None;
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "processor" => logical_cpus += 1,
+            // x86 uses "model name"; other architectures name it differently
+            "model name" | "Model" | "cpu model" | "Hardware" if model.is_none() => {
+                model = Some(value.to_string())
+            }
+            "cpu MHz" if mhz.is_none() => mhz = value.parse().ok(),
+            "physical id" => {
+                sockets.insert(value.to_string());
+                physical_id = Some(value.to_string());
+            }
+            "core id" => {
+                cores.insert((physical_id.clone(), value.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    Cpu {
+        model,
+        architecture: std::env::consts::ARCH,
+        logical_cpus,
+        physical_cores: (!cores.is_empty()).then_some(cores.len()),
+        sockets: sockets.len().max(1),
+        mhz,
+        load_average,
+    }
+}
+
+impl Report for Cpu {
+    fn table(&self) -> Table {
+        let mut pairs = vec![
+            (
+                "Model",
+                self.model.clone().unwrap_or_else(|| "unknown".to_string()),
+            ),
+            ("Architecture", self.architecture.to_string()),
+            ("CPUs", self.logical_cpus.to_string()),
+        ];
+        if let Some(cores) = self.physical_cores {
+            pairs.push(("Cores", cores.to_string()));
+        }
+        pairs.push(("Sockets", self.sockets.to_string()));
+        if let Some(mhz) = self.mhz {
+            pairs.push(("MHz", format!("{:.0}", mhz)));
+        }
+        pairs.push(("Load average", self.load_average.to_string()));
+        Table::properties(pairs)
+    }
+}
+
+// --- Uptime ---------------------------------------------------------------
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Uptime {
+    pub seconds: u64,
+    pub load_average: LoadAverage,
+}
+
+pub fn uptime() -> io::Result<Uptime> {
+    Ok(Uptime {
+        seconds: parse_uptime(&fs::read_to_string("/proc/uptime")?)?,
+        load_average: load_average()?,
+    })
+}
+
+fn parse_uptime(content: &str) -> io::Result<u64> {
+    content
+        .split_whitespace()
+        .next()
+        .and_then(|s| s.parse::<f64>().ok())
+        .map(|s| s as u64)
+        .ok_or_else(|| invalid_data("unexpected /proc/uptime format"))
+}
+
+/// Format a duration the way `uptime` does, e.g. "3 days, 4:05" or "12 min"
+fn format_uptime(seconds: u64) -> String {
+    let days = seconds / 86_400;
+    let hours = seconds % 86_400 / 3600;
+    let minutes = seconds % 3600 / 60;
+
+    let mut parts = Vec::new();
+    if days > 0 {
+        parts.push(format!("{} day{}", days, if days == 1 { "" } else { "s" }));
+    }
+    if hours > 0 {
+        parts.push(format!("{}:{:02}", hours, minutes));
+    } else {
+        parts.push(format!("{} min", minutes));
+    }
+    parts.join(", ")
+}
+
+impl Report for Uptime {
+    /// A single line in the style of `uptime`
+    fn table(&self) -> Table {
+        Table {
+            rows: vec![vec![format!(
+                "up {}, {} tasks ({} running), load average: {}",
+                format_uptime(self.seconds),
+                self.load_average.total_tasks,
+                self.load_average.running_tasks,
+                self.load_average
+            )]],
+            ..Default::default()
+        }
+    }
+}
+
+// --- Operating system -----------------------------------------------------
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Os {
+    /// Distribution name from os-release, if available
+    pub name: Option<String>,
+    pub kernel: String,
+    pub release: String,
+    pub version: String,
+    pub hostname: String,
+    pub architecture: &'static str,
+}
+
+pub fn os() -> io::Result<Os> {
+    let kernel_value = |name: &str| -> io::Result<String> {
+        Ok(fs::read_to_string(format!("/proc/sys/kernel/{}", name))?
+            .trim()
+            .to_string())
+    };
+    Ok(Os {
+        name: distribution(),
+        kernel: kernel_value("ostype")?,
+        release: kernel_value("osrelease")?,
+        version: kernel_value("version")?,
+        hostname: kernel_value("hostname")?,
+        architecture: std::env::consts::ARCH,
+    })
+}
+
+/// Name of the distribution from os-release, e.g. "Fedora Linux 40"
+pub fn distribution() -> Option<String> {
+    fs::read_to_string("/etc/os-release")
+        .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+        .ok()
+        .and_then(|content| parse_os_release(&content))
+}
+
+fn parse_os_release(content: &str) -> Option<String> {
+    let value = |key: &str| {
+        content.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_string())
+        })
+    };
+    value("PRETTY_NAME").or_else(|| value("NAME"))
+}
+
+impl Report for Os {
+    fn table(&self) -> Table {
+        let mut pairs = Vec::new();
+        if let Some(name) = &self.name {
+            pairs.push(("OS", name.clone()));
+        }
+        pairs.extend([
+            ("Kernel", format!("{} {}", self.kernel, self.release)),
+            ("Build", self.version.clone()),
+            ("Hostname", self.hostname.clone()),
+            ("Architecture", self.architecture.to_string()),
+        ]);
+        Table::properties(pairs)
+    }
+}
+
+// --- Disks ----------------------------------------------------------------
+
+/// A mounted filesystem with usage in bytes, like a row of `df`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Filesystem {
+    pub source: String,
+    pub mount_point: String,
+    pub fs_type: String,
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+}
+
+impl Filesystem {
+    /// Percentage of space in use, rounded up like `df`
+    fn use_percent(&self) -> u64 {
+        let usable = self.used + self.available;
+        let used = self.used * 100;
+        match used.checked_div(usable) {
+            Some(percent) if used % usable != 0 => percent + 1,
+            Some(percent) => percent,
+            None => 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Mount {
+    source: String,
+    mount_point: String,
+    fs_type: String,
+}
+
+pub fn filesystems() -> io::Result<Vec<Filesystem>> {
+    let mounts = parse_mounts(&fs::read_to_string("/proc/self/mounts")?);
+    Ok(mounts
+        .into_iter()
+        .filter_map(|mount| {
+            // Mounts we cannot stat (permissions, stale network shares) are skipped
+            let stat = statvfs(&mount.mount_point).ok()?;
+            let block = stat.f_frsize as u64;
+            let total = stat.f_blocks as u64 * block;
+            if total == 0 {
+                return None;
+            }
+            Some(Filesystem {
+                source: mount.source,
+                mount_point: mount.mount_point,
+                fs_type: mount.fs_type,
+                total,
+                used: (stat.f_blocks as u64).saturating_sub(stat.f_bfree as u64) * block,
+                available: stat.f_bavail as u64 * block,
+            })
+        })
+        .collect())
+}
+
+fn statvfs(path: &str) -> io::Result<libc::statvfs> {
+    let path = CString::new(path).map_err(|_| invalid_data("mount point contains NUL"))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat)
+}
+
+/// Parse `/proc/self/mounts`, dropping pseudo filesystems and mounts that
+/// are shadowed by a later mount on the same point
+fn parse_mounts(content: &str) -> Vec<Mount> {
+    let mut mounts: Vec<Mount> = Vec::new();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(source), Some(mount_point), Some(fs_type)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if PSEUDO_FILESYSTEMS.contains(&fs_type) {
+            continue;
+        }
+        let mount = Mount {
+            source: unescape_mount_field(source),
+            mount_point: unescape_mount_field(mount_point),
+            fs_type: fs_type.to_string(),
+        };
+        mounts.retain(|m| m.mount_point != mount.mount_point);
+        mounts.push(mount);
+    }
+    mounts
+}
+
+/// Undo the octal escapes (`\040` for a space) used in mount fields
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            if let Some(value) = field
+                .get(i + 1..i + 4)
+                .and_then(|octal| u8::from_str_radix(octal, 8).ok())
+            {
+                out.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+impl Report for Vec<Filesystem> {
+    fn table(&self) -> Table {
+        let mut table = Table::with_header(&[
+            "Filesystem",
+            "Size",
+            "Used",
+            "Avail",
+            "Use%",
+            "Type",
+            "Mounted on",
+        ]);
+        table.empty = "No filesystems found";
+        for fs in self {
+            table.row(vec![
+                fs.source.clone(),
+                format_bytes(fs.total),
+                format_bytes(fs.used),
+                format_bytes(fs.available),
+                format!("{}%", fs.use_percent()),
+                fs.fs_type.clone(),
+                fs.mount_point.clone(),
+            ]);
+        }
+        table
+    }
+}
+
+// --- Sockets --------------------------------------------------------------
+
+/// An open TCP or UDP socket from `/proc/net`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Socket {
+    pub protocol: &'static str,
+    pub local_address: String,
+    pub local_port: u16,
+    pub remote_address: String,
+    pub remote_port: u16,
+    pub state: &'static str,
+    #[serde(skip)]
+    inode: u64,
+    /// Owning process as "name (pid)"; only visible for our own processes
+    /// unless running as root
+    pub process: Option<String>,
+}
+
+impl Socket {
+    fn is_listening(&self) -> bool {
+        matches!(self.state, "LISTEN" | "UNCONN")
+    }
+
+    fn local(&self) -> String {
+        format_endpoint(&self.local_address, self.local_port)
+    }
+
+    fn remote(&self) -> String {
+        // Listening sockets have no peer yet
+        if self.remote_port == 0 {
+            return "*".to_string();
+        }
+        format_endpoint(&self.remote_address, self.remote_port)
+    }
+}
+
+/// Sockets accepting connections, like `ss -tuln`
+pub fn listening_sockets() -> io::Result<Vec<Socket>> {
+    let mut sockets: Vec<Socket> = all_sockets()?
+        .into_iter()
+        .filter(Socket::is_listening)
+        .collect();
+    sockets.sort_by(|a, b| (a.local_port, a.protocol).cmp(&(b.local_port, b.protocol)));
+    Ok(sockets)
+}
+
+/// Every socket bound to local `port`
+pub fn sockets_on_port(port: u16) -> io::Result<Vec<Socket>> {
+    Ok(all_sockets()?
+        .into_iter()
+        .filter(|s| s.local_port == port)
+        .collect())
+}
+
+fn all_sockets() -> io::Result<Vec<Socket>> {
+    let mut sockets = Vec::new();
+    let mut found = false;
+    for protocol in ["tcp", "tcp6", "udp", "udp6"] {
+        // IPv6 tables are missing when IPv6 is disabled
+        if let Ok(content) = fs::read_to_string(format!("/proc/net/{}", protocol)) {
+            found = true;
+            sockets.extend(parse_net(&content, protocol));
+        }
+    }
+    if !found {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "/proc/net is not available",
+        ));
+    }
+
+    let owners = socket_owners();
+    for socket in &mut sockets {
+        socket.process = owners.get(&socket.inode).cloned();
+    }
+    Ok(sockets)
+}
+
+fn parse_net(content: &str, protocol: &'static str) -> Vec<Socket> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 {
+                return None;
+            }
+            let (local_address, local_port) = parse_endpoint(fields[1])?;
+            let (remote_address, remote_port) = parse_endpoint(fields[2])?;
+            let state = u8::from_str_radix(fields[3], 16).ok()?;
+            Some(Socket {
+                protocol,
+                local_address,
+                local_port,
+                remote_address,
+                remote_port,
+                state: socket_state(protocol, state),
+                inode: fields[9].parse().ok()?,
+                process: None,
+            })
+        })
+        .collect()
+}
+
+/// Decode "0100007F:1F90" into ("127.0.0.1", 8080). Addresses are printed as
+/// 32-bit words in host byte order.
+fn parse_endpoint(field: &str) -> Option<(String, u16)> {
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    let mut bytes = Vec::with_capacity(16);
+    for i in (0..address.len()).step_by(8) {
+        let word = u32::from_str_radix(address.get(i..i + 8)?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    let address = match bytes.len() {
+        4 => Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).to_string(),
+        16 => {
+            let octets: [u8; 16] = bytes.try_into().ok()?;
+            let ip = Ipv6Addr::from(octets);
+            // Show IPv4-mapped addresses the familiar way
+            match ip.to_ipv4_mapped() {
+                Some(v4) => v4.to_string(),
+                None => ip.to_string(),
+            }
+        }
+        _ => return None,
+    };
+    Some((address, port))
+}
+
+fn format_endpoint(address: &str, port: u16) -> String {
+    if address.contains(':') {
+        format!("[{}]:{}", address, port)
+    } else {
+        format!("{}:{}", address, port)
+    }
+}
+
+fn socket_state(protocol: &str, state: u8) -> &'static str {
+    match state {
+        0x01 => "ESTABLISHED",
+        0x02 => "SYN_SENT",
+        0x03 => "SYN_RECV",
+        0x04 => "FIN_WAIT1",
+        0x05 => "FIN_WAIT2",
+        0x06 => "TIME_WAIT",
+        // An unconnected UDP socket is how UDP "listens"
+        0x07 if protocol.starts_with("udp") => "UNCONN",
+        0x07 => "CLOSE",
+        0x08 => "CLOSE_WAIT",
+        0x09 => "LAST_ACK",
+        0x0A => "LISTEN",
+        0x0B => "CLOSING",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Map socket inodes to "name (pid)" by scanning `/proc/<pid>/fd`. Processes
+/// we are not allowed to inspect are skipped.
+fn socket_owners() -> HashMap<u64, String> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return owners;
+    };
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(pid) = file_name.to_str().filter(|p| p.parse::<u32>().is_ok()) else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let name = fs::read_to_string(entry.path().join("comm"))
+            .map(|c| c.trim().to_string())
+            .unwrap_or_default();
+
+        for fd in fds.flatten() {
+            let Ok(target) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            let inode = target
+                .to_str()
+                .and_then(|t| t.strip_prefix("socket:["))
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.parse::<u64>().ok());
+            if let Some(inode) = inode {
+                owners
+                    .entry(inode)
+                    .or_insert_with(|| format!("{} ({})", name, pid));
+            }
+        }
+    }
+    owners
+}
+
+impl Report for Vec<Socket> {
+    fn table(&self) -> Table {
+        let mut table =
+            Table::with_header(&["Proto", "State", "Local Address", "Peer Address", "Process"]);
+        table.empty = "No sockets found";
+        for socket in self {
+            table.row(vec![
+                socket.protocol.to_string(),
+                socket.state.to_string(),
+                socket.local(),
+                socket.remote(),
+                socket.process.clone().unwrap_or_else(|| "-".to_string()),
+            ]);
+        }
+        table
+    }
+}
+
+// --- Helpers --------------------------------------------------------------
+
+/// Human-readable size with binary units, like `df -h`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "K", "M", "G", "T", "P"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load() -> LoadAverage {
+        parse_loadavg("0.06 0.31 0.33 2/72 13885\n").unwrap()
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        let content = "MemTotal:       16000000 kB\n\
+                       MemFree:         2000000 kB\n\
+                       MemAvailable:    9000000 kB\n\
+                       Buffers:          500000 kB\n\
+                       Cached:          6000000 kB\n\
+                       SwapTotal:       4000000 kB\n\
+                       SwapFree:        3000000 kB\n\
+                       Shmem:            300000 kB\n\
+                       SReclaimable:     500000 kB\n";
+        let memory = parse_meminfo(content).unwrap();
+        assert_eq!(memory.total, 16_000_000 * 1024);
+        assert_eq!(memory.buff_cache, 7_000_000 * 1024);
+        assert_eq!(memory.used, 7_000_000 * 1024);
+        assert_eq!(memory.available, 9_000_000 * 1024);
+        assert_eq!(memory.swap_used, 1_000_000 * 1024);
+        assert_eq!(memory.shared, 300_000 * 1024);
+
+        assert!(parse_meminfo("MemFree: 1 kB\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_loadavg() {
+        let load = load();
+        assert_eq!(load.one, 0.06);
+        assert_eq!(load.fifteen, 0.33);
+        assert_eq!(load.running_tasks, 2);
+        assert_eq!(load.total_tasks, 72);
+        assert_eq!(load.to_string(), "0.06, 0.31, 0.33");
+
+        assert!(parse_loadavg("garbage").is_err());
+    }
+
+    #[test]
+    fn test_parse_cpuinfo() {
+        let content = "processor\t: 0\nmodel name\t: Example CPU @ 2.00GHz\n\
+                       cpu MHz\t\t: 2000.000\nphysical id\t: 0\ncore id\t\t: 0\n\n\
+                       processor\t: 1\nmodel name\t: Example CPU @ 2.00GHz\n\
+                       cpu MHz\t\t: 1800.000\nphysical id\t: 0\ncore id\t\t: 0\n\n\
+                       processor\t: 2\nphysical id\t: 0\ncore id\t\t: 1\n";
+        let cpu = parse_cpuinfo(content, load());
+        assert_eq!(cpu.model.as_deref(), Some("Example CPU @ 2.00GHz"));
+        assert_eq!(cpu.logical_cpus, 3);
+        assert_eq!(cpu.physical_cores, Some(2));
+        assert_eq!(cpu.sockets, 1);
+        assert_eq!(cpu.mhz, Some(2000.0));
+
+        // No topology information, as in many VMs and on ARM
+        let cpu = parse_cpuinfo("processor : 0\nprocessor : 1\n", load());
+        assert_eq!(cpu.logical_cpus, 2);
+        assert_eq!(cpu.physical_cores, None);
+        assert_eq!(cpu.model, None);
+    }
+
+    #[test]
+    fn test_uptime() {
+        assert_eq!(parse_uptime("2682.12 2139.77\n").unwrap(), 2682);
+        assert_eq!(format_uptime(2682), "44 min");
+        assert_eq!(
+            format_uptime(3 * 86_400 + 4 * 3600 + 5 * 60),
+            "3 days, 4:05"
+        );
+        assert_eq!(format_uptime(86_400 + 59), "1 day, 0 min");
+    }
+
+    #[test]
+    fn test_parse_os_release() {
+        let content = "NAME=\"Debian GNU/Linux\"\nPRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\n";
+        assert_eq!(
+            parse_os_release(content).as_deref(),
+            Some("Debian GNU/Linux 12 (bookworm)")
+        );
+        assert_eq!(
+            parse_os_release("NAME=Alpine Linux\n").as_deref(),
+            Some("Alpine Linux")
+        );
+        assert_eq!(parse_os_release("ID=x\n"), None);
+    }
+
+    #[test]
+    fn test_parse_mounts() {
+        let content = "proc /proc proc rw 0 0\n\
+                       /dev/sda1 / ext4 rw 0 0\n\
+                       tmpfs /run tmpfs rw 0 0\n\
+                       /dev/sdb1 /mnt/My\\040Disk vfat rw 0 0\n\
+                       overlay /run tmpfs rw 0 0\n";
+        let mounts = parse_mounts(content);
+        let points: Vec<&str> = mounts.iter().map(|m| m.mount_point.as_str()).collect();
+        assert_eq!(points, vec!["/", "/mnt/My Disk", "/run"]);
+        assert_eq!(mounts[2].source, "overlay");
+    }
+
+    #[test]
+    fn test_parse_net() {
+        let tcp = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+                   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 969 1 0 100 0 0 10 0\n\
+                   1: 0100007F:DEA8 0100007F:1F90 01 00000000:00000000 02:00000A6E 00000000     0        0 20107 2 0 20 4 4 18 -1\n";
+        let sockets = parse_net(tcp, "tcp");
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[0].local(), "127.0.0.1:8080");
+        assert_eq!(sockets[0].state, "LISTEN");
+        assert_eq!(sockets[0].inode, 969);
+        assert!(sockets[0].is_listening());
+        assert_eq!(sockets[1].remote(), "127.0.0.1:8080");
+        assert_eq!(sockets[1].state, "ESTABLISHED");
+
+        let tcp6 = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+                    0: 00000000000000000000000000000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 662 1 0 100 0 0 10 0\n";
+        let sockets = parse_net(tcp6, "tcp6");
+        assert_eq!(sockets[0].local(), "[::]:22");
+
+        let udp = "  sl  local_address rem_address   st\n\
+                   0: 00000000:0044 00000000:0000 07 00000000:00000000 00:00000000 00000000     0        0 100 2 0 0\n";
+        let sockets = parse_net(udp, "udp");
+        assert_eq!(sockets[0].state, "UNCONN");
+        assert!(sockets[0].is_listening());
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(1536), "1.5K");
+        assert_eq!(format_bytes(20 * 1024 * 1024), "20M");
+        assert_eq!(format_bytes(4 * 1024 * 1024 * 1024), "4.0G");
+    }
+
+    #[test]
+    fn test_table_alignment() {
+        let mut table = Table::with_header(&["Name", "Size"]);
+        table.row(vec!["a".to_string(), "1".to_string()]);
+        table.row(vec!["longer".to_string(), "22".to_string()]);
+        assert_eq!(table.to_string(), "Name    Size\na       1\nlonger  22\n");
+
+        let mut empty = Table::with_header(&["Name"]);
+        empty.empty = "Nothing here";
+        assert_eq!(empty.to_string(), "Nothing here\n");
+    }
+}
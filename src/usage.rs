@@ -0,0 +1,234 @@
+//! Token accounting: estimated prices and the usage ledger.
+//!
+//! Every AI answer appends one line to `usage.jsonl` in the data directory
+//! with the provider, model, token counts and estimated cost. Only counts are
+//! kept, never the question or the answer.
+
+use crate::config::{get_data_dir, Config};
+use crate::error::Result;
+use crate::providers::streaming::Usage;
+use crate::providers::ProviderType;
+use crate::session::now;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Price of a model in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Price {
+    pub input: f64,
+    pub output: f64,
+}
+
+/// List prices by model name prefix, longest match wins. These drift; users
+/// override them with `ask config price.<model>=<input>,<output>`.
+const PRICES: &[(&str, f64, f64)] = &[
+    // Anthropic
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-opus", 15.0, 75.0),
+    // OpenAI
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("o1", 15.0, 60.0),
+    ("o1-mini", 1.1, 4.4),
+    ("o3-mini", 1.1, 4.4),
+    ("o4-mini", 1.1, 4.4),
+    // Gemini
+    ("gemini-1.5-flash", 0.075, 0.3),
+    ("gemini-1.5-pro", 1.25, 5.0),
+    ("gemini-2.0-flash", 0.1, 0.4),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    // Groq, Perplexity, Mistral, Cohere, Together
+    ("llama-3.3-70b-versatile", 0.59, 0.79),
+    ("llama-3.1-8b-instant", 0.05, 0.08),
+    ("llama-3.1-sonar-small-128k-online", 0.2, 0.2),
+    ("mistral-small", 0.2, 0.6),
+    ("mistral-large", 2.0, 6.0),
+    ("command-r-plus", 2.5, 10.0),
+    ("command-r", 0.15, 0.6),
+    ("meta-llama/Llama-3.3-70B-Instruct-Turbo", 0.88, 0.88),
+];
+
+impl Price {
+    /// Parse `<input>,<output>` as given to `ask config price.<model>=...`
+    pub fn parse(value: &str) -> Option<Price> {
+        let (input, output) = value.split_once(',')?;
+        let price = Price {
+            input: input.trim().parse().ok()?,
+            output: output.trim().parse().ok()?,
+        };
+        (price.input >= 0.0 && price.output >= 0.0).then_some(price)
+    }
+
+    /// Estimated cost of `usage` in USD, if any tokens were reported
+    pub fn cost(&self, usage: &Usage) -> Option<f64> {
+        if usage.input_tokens.is_none() && usage.output_tokens.is_none() {
+            return None;
+        }
+        let tokens = |count: Option<u32>| f64::from(count.unwrap_or(0));
+        Some(
+            (tokens(usage.input_tokens) * self.input + tokens(usage.output_tokens) * self.output)
+                / 1_000_000.0,
+        )
+    }
+}
+
+/// Price for `model`: the user's override, else the built-in list. Local
+/// Ollama models are free.
+pub fn price(config: &Config, provider: ProviderType, model: &str) -> Option<Price> {
+    if let Some(price) = config.prices.get(model) {
+        return Some(*price);
+    }
+    if provider == ProviderType::Ollama {
+        return Some(Price {
+            input: 0.0,
+            output: 0.0,
+        });
+    }
+    PRICES
+        .iter()
+        .filter(|(prefix, _, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|&(_, input, output)| Price { input, output })
+}
+
+/// One answer in the usage ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub provider: ProviderType,
+    pub model: String,
+    #[serde(default)]
+    pub input_tokens: Option<u32>,
+    #[serde(default)]
+    pub output_tokens: Option<u32>,
+    /// Estimated at the time of the answer, so later price changes do not
+    /// rewrite history
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+impl Record {
+    /// A ledger entry for an answer from `config`'s provider
+    pub fn new(config: &Config, usage: &Usage) -> Self {
+        let model = config.effective_model();
+        Record {
+            timestamp: now(),
+            provider: config.provider,
+            cost_usd: price(config, config.provider, &model).and_then(|p| p.cost(usage)),
+            model,
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+/// Dollar amount with enough digits for single answers, e.g. "$0.0007"
+pub fn format_cost(cost: f64) -> String {
+    if cost >= 1.0 {
+        format!("${:.2}", cost)
+    } else {
+        format!("${:.4}", cost)
+    }
+}
+
+pub fn ledger_path() -> PathBuf {
+    get_data_dir().join("usage.jsonl")
+}
+
+/// Append `record` to the ledger
+pub fn record(record: &Record) -> Result<()> {
+    let path = ledger_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+/// All records in the ledger, oldest first. Lines that cannot be parsed are
+/// skipped.
+pub fn load() -> Result<Vec<Record>> {
+    let path = ledger_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u32, output: u32) -> Usage {
+        Usage {
+            input_tokens: Some(input),
+            output_tokens: Some(output),
+        }
+    }
+
+    #[test]
+    fn test_builtin_prices() {
+        let config = Config::default();
+        let mini = price(&config, ProviderType::OpenAI, "gpt-4o-mini-2024-07-18").unwrap();
+        assert_eq!(mini.input, 0.15);
+        let gpt4o = price(&config, ProviderType::OpenAI, "gpt-4o").unwrap();
+        assert_eq!(gpt4o.output, 10.0);
+        assert_eq!(
+            price(&config, ProviderType::Ollama, "llama3.2").map(|p| p.input),
+            Some(0.0)
+        );
+        assert_eq!(price(&config, ProviderType::Custom, "my-finetune"), None);
+    }
+
+    #[test]
+    fn test_price_override() {
+        let mut config = Config::default();
+        config
+            .prices
+            .insert("my-finetune".to_string(), Price::parse("1, 2.5").unwrap());
+        let custom = price(&config, ProviderType::Custom, "my-finetune").unwrap();
+        assert_eq!(custom.output, 2.5);
+        assert_eq!(Price::parse("1"), None);
+        assert_eq!(Price::parse("-1,2"), None);
+    }
+
+    #[test]
+    fn test_cost() {
+        let sonnet = Price {
+            input: 3.0,
+            output: 15.0,
+        };
+        let cost = sonnet.cost(&usage(1_000, 2_000)).unwrap();
+        assert!((cost - 0.033).abs() < 1e-9);
+        assert_eq!(sonnet.cost(&Usage::default()), None);
+    }
+
+    #[test]
+    fn test_record_format() {
+        let record: Record = serde_json::from_str(
+            r#"{"timestamp":1700000000,"provider":"groq","model":"llama-3.3-70b-versatile","input_tokens":10}"#,
+        )
+        .unwrap();
+        assert_eq!(record.provider, ProviderType::Groq);
+        assert_eq!(record.output_tokens, None);
+        assert_eq!(record.cost_usd, None);
+    }
+}
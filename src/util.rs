@@ -0,0 +1,11 @@
+//! Small helpers shared by unrelated modules.
+
+use std::env;
+use std::path::PathBuf;
+
+/// Where `command` is installed, if it is in `PATH`
+pub fn find_in_path(command: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|path| path.is_file())
+}
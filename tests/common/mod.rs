@@ -0,0 +1,317 @@
+//! Offline stand-in for LLM provider APIs.
+//!
+//! `MockServer` listens on a local port and answers each request with the
+//! next canned `MockResponse`, so the `ask` binary can be pointed at it via
+//! `ASK_API_URL` and exercised without network access.
+
+#![allow(dead_code)]
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// A canned HTTP response
+#[derive(Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Body chunks, each written and flushed separately
+    pub chunks: Vec<Vec<u8>>,
+}
+
+impl MockResponse {
+    /// A streaming response replaying a file from `tests/fixtures`
+    pub fn fixture(name: &str) -> Self {
+        let content_type = if name.ends_with(".ndjson") {
+            "application/x-ndjson"
+        } else {
+            "text/event-stream"
+        };
+        MockResponse {
+            status: 200,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            chunks: vec![read_fixture(name)],
+        }
+    }
+
+    /// A non-streaming response with a JSON body and an error status
+    pub fn error(status: u16, body: &str) -> Self {
+        MockResponse {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            chunks: vec![body.as_bytes().to_vec()],
+        }
+    }
+
+    /// A successful non-streaming response with a JSON body
+    pub fn json(body: &str) -> Self {
+        MockResponse::error(200, body)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Re-split the body into chunks of `size` bytes. Small sizes cut through
+    /// multi-byte UTF-8 characters and SSE events.
+    pub fn chunked_every(mut self, size: usize) -> Self {
+        let body: Vec<u8> = self.chunks.concat();
+        self.chunks = body.chunks(size).map(|c| c.to_vec()).collect();
+        self
+    }
+
+    /// Re-split the body at the given byte offsets
+    pub fn split_at(mut self, offsets: &[usize]) -> Self {
+        let body: Vec<u8> = self.chunks.concat();
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for &offset in offsets {
+            chunks.push(body[start..offset].to_vec());
+            start = offset;
+        }
+        chunks.push(body[start..].to_vec());
+        self.chunks = chunks;
+        self
+    }
+}
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("request body is JSON")
+    }
+
+    /// Text of an OpenAI-style chat message; 0 is the system prompt
+    pub fn message(&self, index: usize) -> String {
+        self.json()["messages"][index]["content"]
+            .as_str()
+            .expect("message has text content")
+            .to_string()
+    }
+}
+
+pub struct MockServer {
+    port: u16,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    /// Serve `responses` in order, one per connection. Once they run out the
+    /// last response is repeated.
+    pub fn start(responses: Vec<MockResponse>) -> Self {
+        assert!(!responses.is_empty(), "MockServer needs a response");
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let Ok(stream) = stream else { continue };
+                let response = responses[i.min(responses.len() - 1)].clone();
+                let recorded = Arc::clone(&recorded);
+                thread::spawn(move || serve(stream, &response, &recorded));
+            }
+        });
+
+        MockServer { port, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://127.0.0.1:{}{}", self.port, path)
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn serve(stream: TcpStream, response: &MockResponse, recorded: &Mutex<Vec<RecordedRequest>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let Some(request) = read_request(&mut reader) else {
+        return;
+    };
+    recorded.lock().unwrap().push(request);
+
+    let mut stream = stream;
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status)
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+
+    for chunk in &response.chunks {
+        if chunk.is_empty() {
+            continue;
+        }
+        let mut framed = format!("{:x}\r\n", chunk.len()).into_bytes();
+        framed.extend_from_slice(chunk);
+        framed.extend_from_slice(b"\r\n");
+        if stream
+            .write_all(&framed)
+            .and_then(|_| stream.flush())
+            .is_err()
+        {
+            return;
+        }
+        // Give the client a chance to see each chunk on its own
+        thread::sleep(Duration::from_millis(2));
+    }
+    let _ = stream.write_all(b"0\r\n\r\n");
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> Option<RecordedRequest> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+pub fn read_fixture(name: &str) -> Vec<u8> {
+    std::fs::read(fixture_path(name)).expect("fixture exists")
+}
+
+/// A question that goes to the AI
+pub const QUERY: &[&str] = &["tell", "me", "something"];
+
+/// An `ask` command isolated from the user's config, data and environment
+pub struct TestEnv {
+    pub home: TempDir,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        TestEnv {
+            home: TempDir::new().unwrap(),
+        }
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.home.path().join("config").join("ask")
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.home.path().join("data").join("ask")
+    }
+
+    /// Write `config.json` for this environment
+    pub fn write_config(&self, config: serde_json::Value) {
+        std::fs::create_dir_all(self.config_dir()).unwrap();
+        std::fs::write(
+            self.config_dir().join("config.json"),
+            serde_json::to_string_pretty(&config).unwrap(),
+        )
+        .unwrap();
+    }
+
+    /// Write `name` under the config directory, e.g. `credentials.json` or
+    /// `prompts/exec.txt`, creating directories as needed
+    pub fn write_config_file(&self, name: &str, content: &str) {
+        let path = self.config_dir().join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    pub fn read_config_file(&self, name: &str) -> String {
+        std::fs::read_to_string(self.config_dir().join(name)).unwrap()
+    }
+
+    pub fn command(&self) -> Command {
+        let mut cmd = cargo_bin_cmd!("ask");
+        cmd.env_clear()
+            .env("PATH", std::env::var("PATH").unwrap_or_default())
+            .env("HOME", self.home.path())
+            .env("XDG_CONFIG_HOME", self.home.path().join("config"))
+            .env("XDG_DATA_HOME", self.home.path().join("data"))
+            .env("SHELL", "/bin/sh")
+            .env("NO_COLOR", "1");
+        cmd
+    }
+
+    /// A command talking to `provider` through the mock server
+    pub fn provider_command(&self, provider: &str, api_url: &str) -> Command {
+        let mut cmd = self.command();
+        cmd.env("ASK_PROVIDER", provider)
+            .env("ASK_API_URL", api_url)
+            .env("ASK_API_KEY", "test-key");
+        cmd
+    }
+}
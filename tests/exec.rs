@@ -0,0 +1,18 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv};
+use predicates::prelude::*;
+
+#[test]
+fn test_run_exits_with_command_status() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai_failing_command.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(["run", "fail", "on", "purpose"])
+        .write_stdin("y\n")
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("Command: exit 3"))
+        .stderr(predicate::str::contains("Command failed (exit status: 3)"));
+}
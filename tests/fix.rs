@@ -0,0 +1,66 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv};
+use predicates::prelude::*;
+
+#[test]
+fn test_fix_last_command() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai_code.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .arg("fix")
+        .env("ASK_LAST_COMMAND", "gti status")
+        .env("ASK_LAST_STATUS", "127")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("echo listed"));
+
+    let request = &server.requests()[0];
+    assert!(request.message(0).contains("shell command that failed"));
+    let question = request.message(1);
+    assert!(question.contains("Command: gti status\nExit status: 127 (command not found)"));
+    assert!(question.contains("Shell: sh\nWorking directory: "));
+}
+
+#[test]
+fn test_fix_piped_output() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai_code.sse")]);
+    let env = TestEnv::new();
+
+    // Piped output is diagnosed instead of the hook's command
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .arg("why")
+        .env("ASK_LAST_COMMAND", "gti status")
+        .env("ASK_LAST_STATUS", "127")
+        .write_stdin("error: linker `cc` not found\n")
+        .assert()
+        .success();
+
+    let question = server.requests()[0].message(1);
+    assert!(question.starts_with("A shell command failed with the output attached."));
+    assert!(question.contains("linker `cc` not found"));
+    assert!(!question.contains("gti status"));
+}
+
+#[test]
+fn test_fix_without_hook() {
+    let env = TestEnv::new();
+    env.command()
+        .arg("fix")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ask init bash"));
+}
+
+#[test]
+fn test_fix_after_success() {
+    let env = TestEnv::new();
+    env.command()
+        .arg("fix")
+        .env("ASK_LAST_COMMAND", "ls")
+        .env("ASK_LAST_STATUS", "0")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("The last command succeeded: ls"));
+}
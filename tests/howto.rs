@@ -0,0 +1,145 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv};
+use predicates::prelude::*;
+
+#[test]
+fn test_user_recipes() {
+    let env = TestEnv::new();
+    env.write_config_file(
+        "recipes.json",
+        r#"{"recipes": [
+            {"name": "download", "match": ["download|fetch"], "suggestion": "aria2c URL"},
+            {"name": "elsewhere", "keywords": ["rollback"], "suggestion": "not here",
+             "os": ["plan9"]}
+        ]}"#,
+    );
+    env.write_config_file(
+        "recipes/team.json",
+        r#"{"recipes": [
+            {"name": "rollback-api", "keywords": ["rollback", "roll back"],
+             "suggestion": "deployctl rollback api --to previous", "tags": ["runbook"]},
+            {"name": "broken", "match": ["("], "suggestion": "x"}
+        ]}"#,
+    );
+
+    env.command()
+        .args(["how", "do", "I", "roll", "back", "the", "api"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "deployctl rollback api --to previous",
+        ))
+        .stdout(predicate::str::contains("not here").not())
+        .stderr(predicate::str::contains("skipping recipe 'broken'"));
+
+    // A recipe with a built-in's name replaces it
+    env.command()
+        .args(["howto", "fetch", "a", "file"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("aria2c URL"))
+        .stdout(predicate::str::contains("curl -O").not());
+
+    env.command()
+        .args(["howto", "--list", "runbook"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rollback-api [runbook]"))
+        .stdout(predicate::str::contains("team.json"))
+        .stdout(predicate::str::contains("compress").not());
+
+    let output = env
+        .command()
+        .args(["--json", "howto", "--list"])
+        .output()
+        .unwrap();
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = doc["recipes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|recipe| recipe["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"compress"));
+    assert!(names.contains(&"rollback-api"));
+    assert!(!names.contains(&"elsewhere"));
+}
+
+#[test]
+fn test_howto_falls_through_to_ai() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    let url = server.url("/v1/chat/completions");
+
+    // A close local match needs no AI
+    env.provider_command("openai", &url)
+        .args(["how", "do", "I", "tar", "a", "directory"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Try:"))
+        .stdout(predicate::str::contains("tar -czvf"));
+    assert!(server.requests().is_empty());
+
+    // Copying is only a small part of this question
+    env.provider_command("openai", &url)
+        .args([
+            "how", "do", "I", "copy", "my", "ssh", "key", "to", "a", "server",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No close local suggestions"))
+        .stdout(predicate::str::contains("東京"));
+    assert_eq!(server.requests().len(), 1);
+
+    // Without a provider the closest suggestions are still shown
+    env.command()
+        .args([
+            "how", "do", "I", "copy", "my", "ssh", "key", "to", "a", "server",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Closest local suggestions"))
+        .stdout(predicate::str::contains("rsync -av"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_platform_variants() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new();
+    // A util-linux rename ahead of any other
+    let bin = env.home.path().join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let rename = bin.join("rename");
+    std::fs::write(&rename, "#!/bin/sh\necho 'rename from util-linux 2.39.3'\n").unwrap();
+    std::fs::set_permissions(&rename, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    env.command()
+        .env("PATH", &path)
+        .args(["how", "do", "I", "rename", "multiple", "files"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rename old new files*"))
+        .stdout(predicate::str::contains("s/old/new/").not());
+
+    env.write_config_file(
+        "recipes.json",
+        r#"{"recipes": [{"name": "deploy", "keywords": ["deploy"],
+            "suggestion": "no-such-deploy-tool --prod\nls"}]}"#,
+    );
+    env.command()
+        .args(["howto", "deploy"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "no-such-deploy-tool --prod  # not installed: no-such-deploy-tool",
+        ))
+        .stdout(predicate::str::contains("ls  #").not());
+}
@@ -1,9 +1,9 @@
-use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 
 #[test]
 fn test_version_short() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.arg("-v")
         .assert()
         .success()
@@ -12,7 +12,7 @@ fn test_version_short() {
 
 #[test]
 fn test_version_long() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.arg("--version")
         .assert()
         .success()
@@ -21,7 +21,7 @@ fn test_version_long() {
 
 #[test]
 fn test_help_short() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.arg("-h")
         .assert()
         .success()
@@ -30,7 +30,7 @@ fn test_help_short() {
 
 #[test]
 fn test_help_long() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.arg("--help")
         .assert()
         .success()
@@ -39,7 +39,7 @@ fn test_help_long() {
 
 #[test]
 fn test_no_args_shows_help() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("Usage: ask <query>"));
@@ -47,7 +47,7 @@ fn test_no_args_shows_help() {
 
 #[test]
 fn test_howto_compress() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["how", "do", "I", "compress", "a", "folder"])
         .assert()
         .success()
@@ -57,7 +57,7 @@ fn test_howto_compress() {
 
 #[test]
 fn test_howto_find() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["how", "to", "find", "a", "file"])
         .assert()
         .success()
@@ -66,7 +66,7 @@ fn test_howto_find() {
 
 #[test]
 fn test_howto_delete() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["how", "to", "delete", "files"])
         .assert()
         .success()
@@ -75,7 +75,7 @@ fn test_howto_delete() {
 
 #[test]
 fn test_system_disk() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["system", "disk"])
         .assert()
         .success()
@@ -84,7 +84,7 @@ fn test_system_disk() {
 
 #[test]
 fn test_system_uptime() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["system", "uptime"])
         .assert()
         .success()
@@ -93,7 +93,7 @@ fn test_system_uptime() {
 
 #[test]
 fn test_system_unknown() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["system", "unknown123"])
         .assert()
         .failure()
@@ -102,7 +102,7 @@ fn test_system_unknown() {
 
 #[test]
 fn test_explain_nonexistent_command() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["explain", "nonexistentcmd12345"])
         .assert()
         .failure()
@@ -111,7 +111,7 @@ fn test_explain_nonexistent_command() {
 
 #[test]
 fn test_config_show() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["config", "show"])
         .assert()
         .success()
@@ -120,7 +120,7 @@ fn test_config_show() {
 
 #[test]
 fn test_config_path() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.args(["config", "path"])
         .assert()
         .success()
@@ -129,9 +129,125 @@ fn test_config_path() {
 
 #[test]
 fn test_pipe_mode() {
-    let mut cmd = Command::cargo_bin("ask").unwrap();
+    let mut cmd = cargo_bin_cmd!("ask");
     cmd.write_stdin("how to copy files")
         .assert()
         .success()
         .stdout(predicate::str::contains("cp "));
 }
+
+#[test]
+fn test_json_howto() {
+    let mut cmd = cargo_bin_cmd!("ask");
+    let output = cmd
+        .args(["--json", "how", "do", "I", "compress", "a", "folder"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["query"], "how do I compress a folder");
+    assert!(doc["suggestions"][0]
+        .as_str()
+        .unwrap()
+        .contains("tar -czvf"));
+}
+
+#[test]
+fn test_json_system() {
+    let mut cmd = cargo_bin_cmd!("ask");
+    let output = cmd.args(["--json", "system", "os"]).output().unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["query"], "os");
+    if cfg!(target_os = "linux") {
+        assert_eq!(doc["resource"], "os");
+        assert!(doc["data"]["kernel"].is_string());
+    } else {
+        assert!(doc["output"].is_string());
+    }
+}
+
+#[test]
+fn test_json_config_show() {
+    let mut cmd = cargo_bin_cmd!("ask");
+    let output = cmd.args(["--json", "config", "show"]).output().unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(doc["config_file"]
+        .as_str()
+        .unwrap()
+        .ends_with("config.json"));
+    assert!(doc["settings"]["max_tokens"].is_string());
+    assert!(doc["effective"]["model"].is_string());
+}
+
+#[test]
+fn test_piped_stdin_not_attached_to_prompt() {
+    // `prompt` reads its answer from stdin, so it must not become AI context
+    let mut cmd = cargo_bin_cmd!("ask");
+    cmd.args(["prompt", "continue?"])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_init_shells() {
+    for (shell, binding) in [
+        ("bash", "bind -m emacs -x"),
+        ("zsh", "bindkey -M emacs '^G'"),
+        ("fish", "bind \\cg"),
+    ] {
+        let mut cmd = cargo_bin_cmd!("ask");
+        cmd.args(["init", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(binding))
+            .stdout(predicate::str::contains("ask --print-command"));
+    }
+
+    let mut cmd = cargo_bin_cmd!("ask");
+    cmd.args(["init"])
+        .env("SHELL", "/usr/bin/zsh")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("zle -N __ask_command_line"));
+}
+
+#[test]
+fn test_prompt_answer_sets_status() {
+    let mut cmd = cargo_bin_cmd!("ask");
+    cmd.args(["prompt", "continue?"])
+        .write_stdin("n\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("No"))
+        .stderr("");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_open_stdin_socket_not_read() {
+    use std::os::unix::net::UnixStream;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    // Like a runner that keeps stdin open but never writes to it
+    let (stdin, _writer) = UnixStream::pair().unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ask"))
+        .args(["explain", "nonexistentcmd12345"])
+        .stdin(Stdio::from(std::os::fd::OwnedFd::from(stdin)))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if started.elapsed() > Duration::from_secs(10) {
+            let _ = child.kill();
+            panic!("ask waited for stdin to close");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv, QUERY};
+use predicates::prelude::*;
+
+/// A config with an OpenAI "work" profile served by `server`, with its key
+/// in the credentials file
+fn write_work_profile(env: &TestEnv, server: &MockServer, default: bool) {
+    let mut config = serde_json::json!({
+        "provider": "anthropic",
+        "api_key": "anthropic-key",
+        "profiles": {
+            "work": {
+                "provider": "openai",
+                "model": "gpt-4o-mini",
+                "api_url": server.url("/v1/chat/completions")
+            }
+        }
+    });
+    if default {
+        config["default_profile"] = "work".into();
+    }
+    env.write_config(config);
+    env.write_config_file("credentials.json", r#"{"profiles": {"work": "sk-work"}}"#);
+}
+
+#[test]
+fn test_profile_flag() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    write_work_profile(&env, &server, false);
+
+    env.command()
+        .args(["--profile", "work"])
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Grüße aus 東京\n");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("authorization"), Some("Bearer sk-work"));
+    assert_eq!(request.json()["model"], "gpt-4o-mini");
+}
+
+#[test]
+fn test_profile_from_env_and_default() {
+    let server = MockServer::start(vec![
+        MockResponse::fixture("openai.sse"),
+        MockResponse::fixture("openai.sse"),
+    ]);
+    let env = TestEnv::new();
+    write_work_profile(&env, &server, true);
+
+    env.command().args(QUERY).assert().success();
+    env.command()
+        .env("ASK_PROFILE", "work")
+        .args(QUERY)
+        .assert()
+        .success();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests
+        .iter()
+        .all(|r| r.header("authorization") == Some("Bearer sk-work")));
+}
+
+#[test]
+fn test_unknown_profile() {
+    let env = TestEnv::new();
+    env.command()
+        .args(["--profile", "nope"])
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Profile not found: nope"));
+}
+
+#[test]
+fn test_profile_add_list_remove() {
+    let env = TestEnv::new();
+    env.command()
+        .args(["config", "profile", "add", "work"])
+        .args(["provider=openai", "model=gpt-4o", "api_key=sk-work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Added profile work: openai / gpt-4o",
+        ));
+
+    assert!(!env.read_config_file("config.json").contains("sk-work"));
+    assert!(env.read_config_file("credentials.json").contains("sk-work"));
+
+    env.command()
+        .args(["config", "default_profile=work"])
+        .assert()
+        .success();
+    env.command()
+        .args(["config", "profile", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("openai / gpt-4o"))
+        .stdout(predicate::str::contains("default, active, key stored"));
+
+    env.command()
+        .args(["config", "profile", "remove", "work"])
+        .assert()
+        .success();
+    env.command()
+        .args(["config", "profile", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No profiles configured"));
+}
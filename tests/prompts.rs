@@ -0,0 +1,106 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv, QUERY};
+use predicates::prelude::*;
+
+#[test]
+fn test_system_prompt_setting() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "system_prompt": "Answer as a {shell} expert." }));
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success();
+    assert_eq!(server.requests()[0].message(0), "Answer as a sh expert.");
+
+    env.command()
+        .args(["config", "show"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "system_prompt = Answer as a {shell} expert.",
+        ));
+}
+
+#[test]
+fn test_system_flag() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "system_prompt": "Answer as a {shell} expert." }));
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(["--system", "Reply in French."])
+        .args(QUERY)
+        .assert()
+        .success();
+    assert_eq!(server.requests()[0].message(0), "Reply in French.");
+}
+
+#[test]
+fn test_prompt_template_file() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai_command.sse")]);
+    let env = TestEnv::new();
+    env.write_config_file(
+        "prompts/exec.txt",
+        "On {os}, today {date}, in {cwd}: reply with COMMAND: and EXPLANATION: lines.\n",
+    );
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .current_dir(env.home.path())
+        .args(["--print-command", "show", "sizes"])
+        .assert()
+        .success()
+        .stdout("du -sh * | sort -h\n");
+    let exec = server.requests()[0].message(0);
+    assert!(exec.starts_with(&format!("On {}, today 20", std::env::consts::OS)));
+    assert!(exec.ends_with(&format!(
+        "in {}: reply with COMMAND: and EXPLANATION: lines.",
+        env.home.path().display()
+    )));
+
+    env.command()
+        .args(["config", "show"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("prompts/exec.txt"));
+}
+
+#[test]
+fn test_environment_context() {
+    let server = MockServer::start(vec![
+        MockResponse::fixture("openai.sse"),
+        MockResponse::fixture("openai.sse"),
+    ]);
+    let env = TestEnv::new();
+    std::fs::write(env.home.path().join("Cargo.toml"), "").unwrap();
+    let system = format!(
+        "The user runs {} on {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    // Only the OS and architecture until facts are turned on
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .current_dir(env.home.path())
+        .args(QUERY)
+        .assert()
+        .success();
+    assert!(server.requests()[0]
+        .message(0)
+        .ends_with(&format!("{}.", system)));
+
+    env.write_config(serde_json::json!({
+        "context": { "shell": true, "project": true }
+    }));
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .current_dir(env.home.path())
+        .args(QUERY)
+        .assert()
+        .success();
+    assert!(server.requests()[1].message(0).ends_with(&format!(
+        "{} with sh. The current directory holds a Rust (Cargo) project.",
+        system
+    )));
+}
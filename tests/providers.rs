@@ -0,0 +1,1021 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv, QUERY};
+use predicates::prelude::*;
+
+#[test]
+fn test_anthropic_stream() {
+    let server = MockServer::start(vec![MockResponse::fixture("anthropic.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Use `du -sh` — it's fast ✓\n");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/v1/messages");
+    assert_eq!(request.header("x-api-key"), Some("test-key"));
+    assert_eq!(request.header("anthropic-version"), Some("2023-06-01"));
+
+    let body = request.json();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["model"], "claude-sonnet-4-20250514");
+    assert_eq!(body["messages"][0]["role"], "user");
+    assert_eq!(body["messages"][0]["content"], "tell me something");
+}
+
+#[test]
+fn test_anthropic_stream_split_chunks() {
+    // Three-byte chunks cut through events, the em dash and the check mark
+    let server = MockServer::start(vec![MockResponse::fixture("anthropic.sse").chunked_every(3)]);
+    let env = TestEnv::new();
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Use `du -sh` — it's fast ✓\n");
+}
+
+#[test]
+fn test_anthropic_stream_error_event() {
+    let server = MockServer::start(vec![MockResponse::fixture("anthropic_error.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Partial"))
+        .stderr(predicate::str::contains("Stream error: Overloaded"));
+}
+
+#[test]
+fn test_openai_stream() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse").chunked_every(5)]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Grüße aus 東京\n");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("authorization"), Some("Bearer test-key"));
+    let body = request.json();
+    assert_eq!(body["model"], "gpt-4o");
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["content"], "tell me something");
+    assert_eq!(body["stream_options"]["include_usage"], true);
+}
+
+#[test]
+fn test_openai_compatible_provider() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("groq", &server.url("/openai/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Grüße aus 東京\n");
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["model"], "llama-3.3-70b-versatile");
+    assert_eq!(body["stream_options"]["include_usage"], true);
+}
+
+#[test]
+fn test_gemini_stream() {
+    let server = MockServer::start(vec![MockResponse::fixture("gemini.sse").chunked_every(7)]);
+    let env = TestEnv::new();
+
+    env.provider_command("gemini", &server.url("/v1beta/models"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Paris is the capital 🇫🇷\n");
+
+    let request = &server.requests()[0];
+    assert_eq!(
+        request.path,
+        "/v1beta/models/gemini-1.5-flash:streamGenerateContent?key=test-key&alt=sse"
+    );
+    let body = request.json();
+    assert_eq!(body["contents"][0]["role"], "user");
+    assert_eq!(body["contents"][0]["parts"][0]["text"], "tell me something");
+}
+
+#[test]
+fn test_cohere_stream() {
+    let server = MockServer::start(vec![MockResponse::fixture("cohere.ndjson").chunked_every(6)]);
+    let env = TestEnv::new();
+
+    env.provider_command("cohere", &server.url("/v1/chat"))
+        .args(["say", "hello"])
+        .assert()
+        .success()
+        .stdout("Bonjour à tous ☀\n");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/chat");
+    assert_eq!(request.header("authorization"), Some("Bearer test-key"));
+    let body = request.json();
+    assert_eq!(body["model"], "command-r-plus");
+    assert_eq!(body["message"], "say hello");
+    assert_eq!(body["stream"], true);
+    assert!(!body["preamble"].as_str().unwrap().is_empty());
+    assert!(body.get("chat_history").is_none());
+}
+
+#[test]
+fn test_cohere_chat_history() {
+    let server = MockServer::start(vec![
+        MockResponse::fixture("cohere.ndjson"),
+        MockResponse::fixture("cohere.ndjson"),
+    ]);
+    let env = TestEnv::new();
+
+    for query in ["say hello", "again"] {
+        env.provider_command("cohere", &server.url("/v1/chat"))
+            .args(["--session", "french", query])
+            .assert()
+            .success();
+    }
+
+    let body = server.requests()[1].json();
+    assert_eq!(body["message"], "again");
+    assert_eq!(
+        body["chat_history"],
+        serde_json::json!([
+            { "role": "USER", "message": "say hello" },
+            { "role": "CHATBOT", "message": "Bonjour à tous ☀" }
+        ])
+    );
+}
+
+#[test]
+fn test_ollama_stream() {
+    let server = MockServer::start(vec![MockResponse::fixture("ollama.ndjson").chunked_every(4)]);
+    let env = TestEnv::new();
+
+    env.provider_command("ollama", &server.url("/api/chat"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Hallo Welt – ünïcödé\n");
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["model"], "llama3.2");
+    assert_eq!(body["stream"], true);
+}
+
+#[test]
+fn test_ollama_stream_without_trailing_newline() {
+    let mut response = MockResponse::fixture("ollama.ndjson");
+    let body = String::from_utf8(response.chunks.concat()).unwrap();
+    response.chunks = vec![body.trim_end().as_bytes().to_vec()];
+    let server = MockServer::start(vec![response]);
+    let env = TestEnv::new();
+
+    env.provider_command("ollama", &server.url("/api/chat"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Hallo Welt – ünïcödé\n");
+}
+
+#[test]
+fn test_ollama_host() {
+    let server = MockServer::start(vec![MockResponse::fixture("ollama.ndjson")]);
+    let env = TestEnv::new();
+
+    env.command()
+        .env("ASK_PROVIDER", "ollama")
+        .env("OLLAMA_HOST", server.url("").trim_start_matches("http://"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Hallo Welt – ünïcödé\n");
+    assert_eq!(server.requests()[0].path, "/api/chat");
+
+    // A configured api_url comes first
+    env.write_config(serde_json::json!({ "provider": "ollama", "api_url": server.url("/custom") }));
+    env.command()
+        .env("OLLAMA_HOST", "unreachable.invalid:1")
+        .args(QUERY)
+        .assert()
+        .success();
+    assert_eq!(server.requests()[1].path, "/custom");
+}
+
+#[test]
+fn test_ollama_model_not_found() {
+    let server = MockServer::start(vec![MockResponse::error(
+        404,
+        r#"{"error":"model \"llama3.2\" not found, try pulling it first"}"#,
+    )]);
+    let env = TestEnv::new();
+
+    env.provider_command("ollama", &server.url("/api/chat"))
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Model 'llama3.2' not found in Ollama",
+        ));
+}
+
+#[test]
+fn test_api_error_bodies() {
+    for (status, body) in [
+        (401, r#"{"error":{"message":"invalid x-api-key"}}"#),
+        (429, r#"{"error":{"message":"rate limited"}}"#),
+        (500, r#"{"error":{"message":"internal"}}"#),
+    ] {
+        let server = MockServer::start(vec![MockResponse::error(status, body)]);
+        let env = TestEnv::new();
+        env.write_config(serde_json::json!({ "max_retries": 0 }));
+
+        env.provider_command("anthropic", &server.url("/v1/messages"))
+            .args(QUERY)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!("API error ({})", status)))
+            .stderr(predicate::str::contains(body));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
+
+#[test]
+fn test_retry_transient_errors() {
+    let server = MockServer::start(vec![
+        MockResponse::error(429, r#"{"error":{"message":"rate limited"}}"#)
+            .header("Retry-After", "0"),
+        MockResponse::error(503, r#"{"error":{"message":"overloaded"}}"#),
+        MockResponse::fixture("anthropic.sse"),
+    ]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "max_retries": 2, "retry_delay_ms": 10 }));
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Use `du -sh` — it's fast ✓\n")
+        .stderr(predicate::str::contains(
+            "API error (429), retrying in 0.0s (1/2)",
+        ))
+        .stderr(predicate::str::contains("API error (503), retrying in"));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn test_retry_gives_up() {
+    let server = MockServer::start(vec![MockResponse::error(500, "internal")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "max_retries": 1, "retry_delay_ms": 10 }));
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("(1/1)"))
+        .stderr(predicate::str::contains("Error: API error (500): internal"));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_no_retry_for_client_errors() {
+    let server = MockServer::start(vec![MockResponse::error(401, "bad key")]);
+    let env = TestEnv::new();
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("retrying").not());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_missing_api_key() {
+    let env = TestEnv::new();
+
+    env.command()
+        .env("ASK_PROVIDER", "openai")
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("openai requires an API key"));
+}
+
+#[test]
+fn test_json_answer() {
+    let server = MockServer::start(vec![MockResponse::fixture("anthropic.sse")]);
+    let env = TestEnv::new();
+
+    let output = env
+        .provider_command("anthropic", &server.url("/v1/messages"))
+        .arg("--json")
+        .args(QUERY)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["query"], "tell me something");
+    assert_eq!(doc["provider"], "anthropic");
+    assert_eq!(doc["model"], "claude-sonnet-4-20250514");
+    assert_eq!(doc["response"], "Use `du -sh` — it's fast ✓");
+    assert_eq!(doc["stop_reason"], "end_turn");
+    assert_eq!(doc["usage"]["input_tokens"], 25);
+    assert_eq!(doc["usage"]["output_tokens"], 12);
+    assert!(doc["timing"]["total_ms"].is_u64());
+    assert!(doc["timing"]["first_token_ms"].is_u64());
+}
+
+#[test]
+fn test_stdin_and_file_attachments() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    let notes = env.home.path().join("notes.txt");
+    std::fs::write(&notes, "disk is full\n").unwrap();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .arg("-f")
+        .arg(&notes)
+        .args(["why", "is", "this", "failing"])
+        .write_stdin("ERROR: write failed\n")
+        .assert()
+        .success();
+
+    let body = server.requests()[0].json();
+    let content = body["messages"][1]["content"].as_str().unwrap();
+    assert_eq!(
+        content,
+        format!(
+            "why is this failing\n\n[{0}]\ndisk is full\n[end of {0}]\n\n[stdin]\nERROR: write failed\n[end of stdin]",
+            notes.display()
+        )
+    );
+}
+
+#[test]
+fn test_attachment_truncated() {
+    let server = MockServer::start(vec![MockResponse::fixture("anthropic.sse")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "max_attachment_bytes": 5 }));
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .args(["summarize", "this"])
+        .write_stdin("0123456789")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "stdin is 10 bytes; only the first 5 bytes are sent",
+        ));
+
+    let body = server.requests()[0].json();
+    assert_eq!(
+        body["messages"][0]["content"],
+        "summarize this\n\n[stdin]\n01234\n[end of stdin, truncated: showing the first 5 of 10 bytes]"
+    );
+}
+
+#[test]
+fn test_fallback_provider_answers() {
+    let primary = MockServer::start(vec![MockResponse::error(503, "overloaded")]);
+    let fallback = MockServer::start(vec![MockResponse::fixture("ollama.ndjson")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({
+        "max_retries": 0,
+        "fallback_providers": ["ollama"]
+    }));
+
+    // OLLAMA_HOST is the one way to point a fallback provider at a server
+    env.provider_command("anthropic", &primary.url("/v1/messages"))
+        .env(
+            "OLLAMA_HOST",
+            fallback.url("").trim_start_matches("http://"),
+        )
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Hallo Welt – ünïcödé\n")
+        .stderr(predicate::str::contains(
+            "anthropic failed (API error (503)), trying ollama…",
+        ))
+        .stderr(predicate::str::contains("Answered by ollama (llama3.2)"));
+
+    assert_eq!(primary.requests().len(), 1);
+    let request = &fallback.requests()[0];
+    assert_eq!(request.path, "/api/chat");
+    assert_eq!(request.json()["model"], "llama3.2");
+}
+
+#[test]
+fn test_fallback_provider_in_json() {
+    let primary = MockServer::start(vec![MockResponse::error(500, "internal")]);
+    let fallback = MockServer::start(vec![MockResponse::fixture("ollama.ndjson")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({
+        "max_retries": 0,
+        "fallback_providers": ["anthropic", "ollama"]
+    }));
+
+    let output = env
+        .provider_command("anthropic", &primary.url("/v1/messages"))
+        .env("OLLAMA_HOST", fallback.url(""))
+        .arg("--json")
+        .args(QUERY)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["provider"], "ollama");
+    assert_eq!(doc["model"], "llama3.2");
+    assert_eq!(doc["response"], "Hallo Welt – ünïcödé");
+}
+
+#[test]
+fn test_fallback_when_provider_has_no_key() {
+    let fallback = MockServer::start(vec![MockResponse::fixture("ollama.ndjson")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({
+        "provider": "anthropic",
+        "fallback_providers": ["ollama"]
+    }));
+
+    env.command()
+        .env("OLLAMA_HOST", fallback.url(""))
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Hallo Welt – ünïcödé\n")
+        .stderr(predicate::str::contains(
+            "anthropic cannot be used (no API key), trying ollama…",
+        ));
+
+    // With no usable provider, the guidance is about the configured one
+    env.write_config(serde_json::json!({
+        "provider": "anthropic",
+        "fallback_providers": ["groq"]
+    }));
+    env.command()
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("anthropic requires an API key"))
+        .stderr(predicate::str::contains("trying").not());
+}
+
+#[test]
+fn test_fallback_without_key_is_skipped() {
+    let server = MockServer::start(vec![MockResponse::error(500, "internal")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({
+        "max_retries": 0,
+        "fallback_providers": ["groq"]
+    }));
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("trying").not())
+        .stderr(predicate::str::contains("API error (500): internal"));
+}
+
+#[test]
+fn test_key_stored_per_provider() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "provider": "anthropic" }));
+    std::fs::write(
+        env.config_dir().join("credentials.json"),
+        r#"{"providers": {"anthropic": "sk-ant", "groq": "gsk-groq"}}"#,
+    )
+    .unwrap();
+
+    env.command()
+        .env("ASK_PROVIDER", "groq")
+        .env("ASK_API_URL", server.url("/openai/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success();
+
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Bearer gsk-groq")
+    );
+}
+
+#[test]
+fn test_key_not_reused_across_providers() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({
+        "provider": "anthropic",
+        "api_key": "sk-ant-legacy"
+    }));
+
+    env.command()
+        .args(["config", "provider=groq"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No API key stored for groq"));
+
+    // The old key moved to the provider it was set up with
+    let config = std::fs::read_to_string(env.config_dir().join("config.json")).unwrap();
+    assert!(!config.contains("sk-ant-legacy"));
+    let credentials: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(env.config_dir().join("credentials.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(credentials["providers"]["anthropic"], "sk-ant-legacy");
+
+    env.command()
+        .env("ASK_API_URL", server.url("/openai/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("groq requires an API key"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn test_config_api_key_for_active_provider() {
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "provider": "openai" }));
+
+    env.command()
+        .args(["config", "api_key=sk-openai"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set api_key for openai"));
+
+    env.command()
+        .args(["config", "show"])
+        .env("GROQ_API_KEY", "gsk-env")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "API keys: groq (GROQ_API_KEY), openai (stored)",
+        ));
+
+    let output = env
+        .command()
+        .args(["--json", "config", "show"])
+        .output()
+        .unwrap();
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["api_keys"]["openai"], "stored");
+    assert!(doc["api_keys"]["anthropic"].is_null());
+}
+
+#[test]
+fn test_api_key_cmd() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "provider": "openai" }));
+
+    env.command()
+        .args(["config", "api_key_cmd=printf sk-from-cmd"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Set api_key_cmd for openai = printf sk-from-cmd",
+        ));
+    let credentials: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(env.config_dir().join("credentials.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        credentials["providers"]["openai"],
+        serde_json::json!({ "command": "printf sk-from-cmd" })
+    );
+
+    env.command()
+        .args(["config", "show"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("API keys: openai (api_key_cmd)"));
+
+    env.command()
+        .env("ASK_API_URL", server.url("/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success();
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Bearer sk-from-cmd")
+    );
+}
+
+#[test]
+fn test_api_key_cmd_runs_only_for_requests() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    let log = env.home.path().join("key-reads");
+    env.write_config(serde_json::json!({ "provider": "openai" }));
+    env.write_config_file(
+        "credentials.json",
+        &serde_json::json!({ "providers": { "openai": {
+            "command": format!("echo read >> '{}'; printf sk-from-cmd", log.display())
+        } } })
+        .to_string(),
+    );
+
+    for args in [
+        &["explain", "ls"][..],
+        &["howto", "--list"],
+        &["system", "uptime"],
+        &["how", "do", "I", "compress", "a", "folder"],
+        &["config", "show"],
+    ] {
+        env.command().args(args).assert().success();
+        assert!(!log.exists(), "ask {} read the key", args.join(" "));
+    }
+
+    env.command()
+        .env("ASK_API_URL", server.url("/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "read\n");
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Bearer sk-from-cmd")
+    );
+}
+
+#[test]
+fn test_fallback_key_read_only_when_tried() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    let log = env.home.path().join("key-reads");
+    env.write_config(serde_json::json!({ "fallback_providers": ["groq"] }));
+    env.write_config_file(
+        "credentials.json",
+        &serde_json::json!({ "providers": { "groq": {
+            "command": format!("echo read >> '{}'; printf gsk-from-cmd", log.display())
+        } } })
+        .to_string(),
+    );
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success();
+    assert!(!log.exists(), "the fallback's key was read");
+}
+
+#[test]
+fn test_api_key_cmd_failure() {
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "provider": "openai" }));
+    std::fs::write(
+        env.config_dir().join("credentials.json"),
+        r#"{"providers": {"openai": {"command": "echo vault sealed >&2; exit 1"}}}"#,
+    )
+    .unwrap();
+
+    env.command()
+        .args(QUERY)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "cannot read the API key for openai",
+        ))
+        .stderr(predicate::str::contains("vault sealed"))
+        .stdout(predicate::str::contains("openai requires an API key"));
+
+    // A command that does not work is rejected when it is set
+    env.command()
+        .args(["config", "api_key_cmd=false"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_config_api_key_offers_storage() {
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "provider": "groq" }));
+
+    // The command option comes after the file and any installed keyrings
+    let keyrings = ["secret-tool", "keyctl"]
+        .iter()
+        .filter(|tool| {
+            std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default())
+                .any(|dir| dir.join(tool).is_file())
+        })
+        .count();
+    let input = format!("{}\nprintf gsk-from-pass\n", keyrings + 2);
+
+    env.command()
+        .args(["config", "api_key"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Where should the API key for groq be kept?",
+        ))
+        .stdout(predicate::str::contains("1) credentials file"))
+        .stdout(predicate::str::contains(
+            "Set api_key_cmd for groq = printf gsk-from-pass",
+        ));
+
+    env.command()
+        .args(["config", "api_key_cmd"])
+        .assert()
+        .success()
+        .stdout("printf gsk-from-pass\n");
+}
+
+#[test]
+fn test_list_openai_models() {
+    let server = MockServer::start(vec![MockResponse::json(
+        r#"{"object":"list","data":[{"id":"gpt-4o-mini","object":"model"},{"id":"gpt-4o","object":"model"}]}"#,
+    )]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .arg("models")
+        .assert()
+        .success()
+        .stdout("Models for openai:\n  gpt-4o (current)\n  gpt-4o-mini\n");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.path, "/v1/models");
+    assert_eq!(request.header("authorization"), Some("Bearer test-key"));
+}
+
+#[test]
+fn test_list_anthropic_models_json() {
+    let server = MockServer::start(vec![MockResponse::json(
+        r#"{"data":[{"type":"model","id":"claude-sonnet-4-5-20250929"},{"type":"model","id":"claude-3-5-haiku-20241022"}],"has_more":false}"#,
+    )]);
+    let env = TestEnv::new();
+
+    let output = env
+        .provider_command("anthropic", &server.url("/v1/messages"))
+        .args(["--json", "models"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(document["provider"], "anthropic");
+    assert_eq!(
+        document["models"],
+        serde_json::json!(["claude-3-5-haiku-20241022", "claude-sonnet-4-5-20250929"])
+    );
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/models?limit=1000");
+    assert_eq!(request.header("x-api-key"), Some("test-key"));
+    assert!(request.header("anthropic-version").is_some());
+}
+
+#[test]
+fn test_list_ollama_models() {
+    let server = MockServer::start(vec![MockResponse::json(
+        r#"{"models":[{"name":"llama3.2:latest","size":2019393189},{"name":"qwen2.5-coder:7b","size":4683087332}]}"#,
+    )]);
+    let env = TestEnv::new();
+
+    env.command()
+        .env("ASK_PROVIDER", "ollama")
+        .env("ASK_API_URL", server.url("/api/chat"))
+        .arg("models")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("llama3.2:latest (current)"))
+        .stdout(predicate::str::contains("qwen2.5-coder:7b"));
+
+    assert_eq!(server.requests()[0].path, "/api/tags");
+}
+
+#[test]
+fn test_list_models_needs_key() {
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["models", "--provider=mistral"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("mistral requires an API key"))
+        .stdout(predicate::str::contains("MISTRAL_API_KEY"));
+}
+
+#[test]
+fn test_config_model_suggestions() {
+    let server = MockServer::start(vec![MockResponse::json(
+        r#"{"data":[{"id":"gpt-4o"},{"id":"gpt-4o-mini"},{"id":"o3-mini"}]}"#,
+    )]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(["config", "model=gpt4o-mini"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set model = gpt4o-mini"))
+        .stderr(predicate::str::contains(
+            "openai does not list a model named 'gpt4o-mini'",
+        ))
+        .stderr(predicate::str::contains("Did you mean: gpt-4o-mini"));
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(["config", "model=o3-mini"])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn test_stats_and_usage_ledger() {
+    let server = MockServer::start(vec![MockResponse::fixture("anthropic.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("anthropic", &server.url("/v1/messages"))
+        .arg("--stats")
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout("Use `du -sh` — it's fast ✓\n")
+        .stderr(predicate::str::contains("Tokens: 25 in, 12 out"))
+        .stderr(predicate::str::contains("first token"))
+        .stderr(predicate::str::contains("~$0.0003"));
+
+    env.command()
+        .arg("usage")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Usage (all time):"))
+        .stdout(predicate::str::contains(
+            "anthropic/claude-sonnet-4-20250514",
+        ))
+        .stdout(predicate::str::contains("$0.0003"));
+
+    let output = env
+        .command()
+        .args(["--json", "usage", "week"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["period"], "week");
+    assert_eq!(report["total"]["requests"], 1);
+    assert_eq!(report["total"]["input_tokens"], 25);
+    assert_eq!(report["models"][0]["model"], "claude-sonnet-4-20250514");
+
+    // Nothing but counts is kept
+    let ledger = std::fs::read_to_string(env.data_dir().join("usage.jsonl")).unwrap();
+    assert!(!ledger.contains("tell me something"));
+    assert!(!ledger.contains("du -sh"));
+}
+
+#[test]
+fn test_price_override() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+
+    env.command()
+        .args(["config", "price.gpt-4o=1000,2000"])
+        .assert()
+        .success();
+
+    let output = env
+        .provider_command("openai", &server.url("/v1/chat/completions"))
+        .arg("--json")
+        .args(QUERY)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    // 20 input tokens at $1000/M plus 6 output tokens at $2000/M
+    let cost = doc["cost_usd"].as_f64().unwrap();
+    assert!((cost - 0.032).abs() < 1e-9, "{}", cost);
+
+    env.command()
+        .args(["config", "price.gpt-4o=cheap"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_code_block_follow_ups() {
+    let server = MockServer::start(vec![
+        MockResponse::fixture("openai_code.sse"),
+        MockResponse::fixture("openai_code.sse"),
+    ]);
+    let env = TestEnv::new();
+    let script = env.home.path().join("hello.py");
+
+    // Save a block of the answer to the question just asked
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(["--save", "2"])
+        .arg(&script)
+        .args(QUERY)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("```python"))
+        .stdout(predicate::str::contains("Saved code block 2 to"));
+    assert_eq!(std::fs::read_to_string(&script).unwrap(), "print('hi')\n");
+
+    // Without a question, follow-ups use the last answer
+    env.command()
+        .args(["--run", "1"])
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Command: echo listed"))
+        .stdout(predicate::str::contains("Run this command? [y/N]: listed"));
+    env.command()
+        .args(["--run", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Code block 2 is python"));
+    env.command()
+        .args(["--copy", "3"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "No code block 3; the last answer has 2 (1-2)",
+        ));
+    env.command()
+        .args(["--save", "2"])
+        .arg(&script)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
+
+    // JSON answers list their blocks
+    let output = env
+        .provider_command("openai", &server.url("/v1/chat/completions"))
+        .arg("--json")
+        .args(QUERY)
+        .output()
+        .unwrap();
+    let answer: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(answer["code_blocks"][0]["language"], "bash");
+    assert_eq!(answer["code_blocks"][0]["code"], "echo listed\n");
+}
+
+#[test]
+fn test_follow_up_without_code_blocks() {
+    let env = TestEnv::new();
+    env.command()
+        .args(["--copy", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The last answer has no code blocks",
+        ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_code_blocks_file_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = MockServer::start(vec![MockResponse::fixture("openai_code.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(QUERY)
+        .assert()
+        .success();
+
+    let path = env.data_dir().join("last_code_blocks.json");
+    let mode = std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn test_print_command() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai_command.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(["--print-command", "--", "show", "folder", "sizes"])
+        .assert()
+        .success()
+        .stdout("du -sh * | sort -h\n")
+        .stderr("");
+
+    let body = server.requests()[0].json();
+    assert!(body["messages"][0]["content"]
+        .as_str()
+        .unwrap()
+        .contains("single shell command"));
+    assert_eq!(body["messages"][1]["content"], "show folder sizes");
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv};
+use predicates::prelude::*;
+
+#[test]
+fn test_failing_commands_keep_the_session() {
+    let env = TestEnv::new();
+    env.command()
+        .arg("-i")
+        .write_stdin("config nosuchkey\nprompt go on?\nn\nconfig max_tokens\n/exit\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Unknown config key: nosuchkey"))
+        .stdout(predicate::str::contains("go on? [y/N]: No"))
+        .stdout(predicate::str::contains("1024"));
+
+    let history = std::fs::read_to_string(env.data_dir().join("history")).unwrap();
+    assert!(history.contains("config max_tokens"));
+}
+
+#[test]
+fn test_provider_change_switches_key_and_model() {
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+    env.write_config(serde_json::json!({ "provider": "openai", "model": "gpt-4o" }));
+    env.write_config_file(
+        "credentials.json",
+        r#"{"providers": {"openai": "sk-openai", "groq": "gsk-groq"}}"#,
+    );
+
+    env.command()
+        .env("ASK_API_URL", server.url("/openai/v1/chat/completions"))
+        .arg("-i")
+        .write_stdin("config provider=groq\ntell me something\n/exit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Set provider = groq"));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("authorization"), Some("Bearer gsk-groq"));
+    assert_eq!(request.json()["model"], "llama-3.3-70b-versatile");
+    assert!(!env.read_config_file("config.json").contains("gpt-4o"));
+}
@@ -0,0 +1,22 @@
+mod common;
+
+use common::{MockResponse, MockServer, TestEnv, QUERY};
+
+#[cfg(unix)]
+#[test]
+fn test_session_file_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = MockServer::start(vec![MockResponse::fixture("openai.sse")]);
+    let env = TestEnv::new();
+
+    env.provider_command("openai", &server.url("/v1/chat/completions"))
+        .args(["--session", "work"])
+        .args(QUERY)
+        .assert()
+        .success();
+
+    let path = env.data_dir().join("sessions").join("work.json");
+    let mode = std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}